@@ -0,0 +1,528 @@
+//! Threshold alerts evaluated on every [`SystemStats`] sample.
+//!
+//! A rule fires once its condition has held for `for_secs`. A threshold rule then stays firing
+//! until the metric comes back past the threshold by `hysteresis`, so a value hovering around
+//! the threshold does not fire over and over; an [`Expression`] rule clears as soon as it no
+//! longer holds. Either only notifies again once `cooldown_secs` have passed since it last did.
+//! [`evaluate`] is a pure function of a rule's previous [`RuleState`] and one sample;
+//! [`AlertEngine`] keeps the states and recent alerts.
+
+use crate::format::format_reading_value;
+use crate::rule::{sample_value, Expression};
+use crate::stats::{Reading, SystemStats, Unit};
+use crate::template::Value;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// How many alerts [`AlertEngine::recent`] keeps.
+pub const RECENT_ALERT_COUNT: usize = 10;
+pub const DEFAULT_ALERT_COOLDOWN_SECS: u64 = 10 * 60;
+/// Hysteresis of rules that do not set one, as a fraction of the threshold.
+pub const DEFAULT_HYSTERESIS_RATIO: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+impl Comparison {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Above => ">",
+            Comparison::Below => "<",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertCondition {
+    Threshold {
+        /// A numeric tray template placeholder such as `mem.pct` or `reading.gpu.usage`.
+        metric: String,
+        comparison: Comparison,
+        threshold: f64,
+        /// How far the metric has to come back past the threshold before the rule clears.
+        hysteresis: f64,
+    },
+    /// A compound condition such as `cpu > 85 and battery.discharging`, see `rule`.
+    Expression(Expression),
+}
+
+impl AlertCondition {
+    /// Whether the condition holds, or `None` while a threshold's metric is not available.
+    fn breached(&self, stats: &SystemStats) -> Option<bool> {
+        match self {
+            AlertCondition::Threshold {
+                metric,
+                comparison,
+                threshold,
+                ..
+            } => {
+                let (value, _) = threshold_value(metric, stats)?;
+                Some(match comparison {
+                    Comparison::Above => value > *threshold,
+                    Comparison::Below => value < *threshold,
+                })
+            }
+            AlertCondition::Expression(expression) => Some(expression.holds(stats)),
+        }
+    }
+
+    fn cleared(&self, stats: &SystemStats) -> bool {
+        match self {
+            AlertCondition::Threshold {
+                metric,
+                comparison,
+                threshold,
+                hysteresis,
+            } => threshold_value(metric, stats).is_some_and(|(value, _)| match comparison {
+                Comparison::Above => value <= threshold - hysteresis,
+                Comparison::Below => value >= threshold + hysteresis,
+            }),
+            AlertCondition::Expression(expression) => !expression.holds(stats),
+        }
+    }
+
+    /// What an alert reports, e.g. `mem.pct is 96.2% (> 95.0%)`.
+    fn describe(&self, stats: &SystemStats) -> String {
+        match self {
+            AlertCondition::Threshold {
+                metric,
+                comparison,
+                threshold,
+                ..
+            } => {
+                let Some((value, unit)) = threshold_value(metric, stats) else {
+                    return String::new();
+                };
+                let format = |value: f64| format_reading_value(&Reading::new("", value, unit));
+                format!(
+                    "{} is {} ({} {})",
+                    metric,
+                    format(value),
+                    comparison.symbol(),
+                    format(*threshold)
+                )
+            }
+            AlertCondition::Expression(expression) => match expression.describe(stats) {
+                values if values.is_empty() => expression.source().to_string(),
+                values => values,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    pub name: String,
+    pub condition: AlertCondition,
+    /// How long the condition has to hold before the rule fires.
+    pub for_secs: u64,
+    pub cooldown_secs: u64,
+}
+
+impl AlertRule {
+    /// The condition in words, e.g. `cpu.pct > 90 for 60s`.
+    pub fn condition(&self) -> String {
+        let condition = match &self.condition {
+            AlertCondition::Threshold {
+                metric,
+                comparison,
+                threshold,
+                ..
+            } => format!("{} {} {}", metric, comparison.symbol(), threshold),
+            // The source already ends in its `for` window, if it has one.
+            AlertCondition::Expression(expression) if expression.for_secs() > 0 => {
+                return expression.source().to_string()
+            }
+            AlertCondition::Expression(expression) => expression.source().to_string(),
+        };
+        match self.for_secs {
+            0 => condition,
+            secs => format!("{} for {}s", condition, secs),
+        }
+    }
+}
+
+/// The rules used without any `[[alerts]]`. A low battery is left to
+/// [`BatteryPolicy`](crate::BatteryPolicy), which already warns about it once per discharge.
+pub fn default_alert_rules() -> Vec<AlertRule> {
+    vec![
+        AlertRule {
+            name: "Memory almost full".to_string(),
+            condition: AlertCondition::Threshold {
+                metric: "mem.pct".to_string(),
+                comparison: Comparison::Above,
+                threshold: 95.0,
+                hysteresis: 5.0,
+            },
+            for_secs: 30,
+            cooldown_secs: DEFAULT_ALERT_COOLDOWN_SECS,
+        },
+        AlertRule {
+            name: "CPU busy".to_string(),
+            condition: AlertCondition::Threshold {
+                metric: "cpu.pct".to_string(),
+                comparison: Comparison::Above,
+                threshold: 90.0,
+                hysteresis: 10.0,
+            },
+            for_secs: 60,
+            cooldown_secs: DEFAULT_ALERT_COOLDOWN_SECS,
+        },
+    ]
+}
+
+/// Where a rule stands after the samples seen so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleState {
+    /// When the current run of samples past the threshold started.
+    pub breached_since: Option<u64>,
+    pub firing: bool,
+    pub last_notified: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub rule: String,
+    /// The values that triggered the alert, e.g. `mem.pct is 96.2% (> 95.0%)`.
+    pub message: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A threshold's metric in `stats`. Battery metrics are missing without a battery, rather than
+/// reading 0%.
+fn threshold_value(metric: &str, stats: &SystemStats) -> Option<(f64, Unit)> {
+    match sample_value(stats, metric)? {
+        Value::Number(value, unit) if value.is_finite() => Some((value, unit)),
+        _ => None,
+    }
+}
+
+/// Advances `rule` by one sample taken at `timestamp`, returning its new state and the alert to
+/// deliver, if any.
+pub fn evaluate(
+    rule: &AlertRule,
+    mut state: RuleState,
+    timestamp: u64,
+    stats: &SystemStats,
+) -> (RuleState, Option<Alert>) {
+    let Some(breached) = rule.condition.breached(stats) else {
+        state.breached_since = None;
+        state.firing = false;
+        return (state, None);
+    };
+
+    if state.firing {
+        if rule.condition.cleared(stats) {
+            state.firing = false;
+            state.breached_since = None;
+        }
+        return (state, None);
+    }
+    if !breached {
+        state.breached_since = None;
+        return (state, None);
+    }
+
+    let since = *state.breached_since.get_or_insert(timestamp);
+    if timestamp.saturating_sub(since) < rule.for_secs {
+        return (state, None);
+    }
+    state.firing = true;
+
+    let cooled_down = state
+        .last_notified
+        .is_none_or(|last| timestamp >= last.saturating_add(rule.cooldown_secs));
+    if !cooled_down {
+        return (state, None);
+    }
+    state.last_notified = Some(timestamp);
+
+    let alert = Alert {
+        rule: rule.name.clone(),
+        message: rule.condition.describe(stats),
+        timestamp,
+    };
+    (state, Some(alert))
+}
+
+#[derive(Debug, Default)]
+pub struct AlertEngine {
+    rules: Vec<(AlertRule, RuleState)>,
+    recent: VecDeque<Alert>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        let mut engine = Self::default();
+        engine.set_rules(rules);
+        engine
+    }
+
+    /// Replaces the rules. Rules that did not change keep their state, so reloading the config
+    /// neither re-fires nor forgets cooldowns.
+    pub fn set_rules(&mut self, rules: Vec<AlertRule>) {
+        let mut previous = std::mem::take(&mut self.rules);
+        self.rules = rules
+            .into_iter()
+            .map(|rule| {
+                let state = previous
+                    .iter()
+                    .position(|(old, _)| *old == rule)
+                    .map(|index| previous.swap_remove(index).1)
+                    .unwrap_or_default();
+                (rule, state)
+            })
+            .collect();
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &AlertRule> {
+        self.rules.iter().map(|(rule, _)| rule)
+    }
+
+    /// Evaluates every rule against a sample and returns the alerts to deliver.
+    pub fn evaluate(&mut self, timestamp: u64, stats: &SystemStats) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for (rule, state) in &mut self.rules {
+            let (next, alert) = evaluate(rule, *state, timestamp, stats);
+            *state = next;
+            alerts.extend(alert);
+        }
+
+        for alert in &alerts {
+            if self.recent.len() == RECENT_ALERT_COUNT {
+                self.recent.pop_back();
+            }
+            self.recent.push_front(alert.clone());
+        }
+        alerts
+    }
+
+    /// Alerts delivered so far, newest first.
+    pub fn recent(&self) -> impl Iterator<Item = &Alert> {
+        self.recent.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::BatteryStats;
+
+    fn threshold(
+        metric: &str,
+        comparison: Comparison,
+        threshold: f64,
+        hysteresis: f64,
+    ) -> AlertCondition {
+        AlertCondition::Threshold {
+            metric: metric.to_string(),
+            comparison,
+            threshold,
+            hysteresis,
+        }
+    }
+
+    fn cpu_rule() -> AlertRule {
+        AlertRule {
+            name: "CPU busy".to_string(),
+            condition: threshold("cpu.pct", Comparison::Above, 90.0, 10.0),
+            for_secs: 60,
+            cooldown_secs: 300,
+        }
+    }
+
+    fn cpu(usage: f32) -> SystemStats {
+        SystemStats {
+            cpu_usage: usage,
+            ..Default::default()
+        }
+    }
+
+    /// Runs `rule` over `(timestamp, cpu usage)` samples and returns the timestamps it fired at.
+    fn fired_at(rule: &AlertRule, samples: &[(u64, f32)]) -> Vec<u64> {
+        let mut state = RuleState::default();
+        let mut fired = Vec::new();
+        for &(timestamp, usage) in samples {
+            let (next, alert) = evaluate(rule, state, timestamp, &cpu(usage));
+            state = next;
+            fired.extend(alert.map(|alert| alert.timestamp));
+        }
+        fired
+    }
+
+    #[test]
+    fn test_evaluate_minimum_duration() {
+        let rule = cpu_rule();
+        // A short spike does not fire.
+        assert!(fired_at(&rule, &[(0, 95.0), (30, 95.0), (60, 50.0), (90, 95.0)]).is_empty());
+        // Staying above for a minute does, once.
+        assert_eq!(
+            fired_at(&rule, &[(0, 95.0), (30, 95.0), (60, 95.0), (90, 99.0)]),
+            [60]
+        );
+
+        let rule = AlertRule {
+            for_secs: 0,
+            ..cpu_rule()
+        };
+        assert_eq!(fired_at(&rule, &[(0, 50.0), (3, 91.0)]), [3]);
+
+        let (state, alert) = evaluate(&rule, RuleState::default(), 3, &cpu(91.0));
+        assert_eq!(alert.unwrap().to_string(), "cpu.pct is 91.0% (> 90.0%)");
+        assert_eq!(
+            state,
+            RuleState {
+                breached_since: Some(3),
+                firing: true,
+                last_notified: Some(3),
+            }
+        );
+        assert_eq!(rule.condition(), "cpu.pct > 90");
+        assert_eq!(cpu_rule().condition(), "cpu.pct > 90 for 60s");
+    }
+
+    #[test]
+    fn test_evaluate_hysteresis_and_cooldown() {
+        let rule = AlertRule {
+            for_secs: 0,
+            ..cpu_rule()
+        };
+        // Hovering around the threshold stays one alert until usage drops below 80%.
+        assert_eq!(
+            fired_at(
+                &rule,
+                &[(0, 91.0), (3, 89.0), (6, 91.0), (9, 85.0), (12, 92.0)]
+            ),
+            [0]
+        );
+        // Clearing and firing again inside the cooldown stays quiet.
+        assert_eq!(
+            fired_at(
+                &rule,
+                &[(0, 91.0), (3, 70.0), (6, 91.0), (300, 70.0), (303, 91.0)]
+            ),
+            [0, 303]
+        );
+
+        let rule = AlertRule {
+            condition: threshold("battery.pct", Comparison::Below, 10.0, 5.0),
+            ..rule
+        };
+        // No battery, no alert, even though the percentage reads 0.
+        let (_, alert) = evaluate(&rule, RuleState::default(), 0, &SystemStats::default());
+        assert_eq!(alert, None);
+
+        let battery = |percent: f32| SystemStats {
+            battery_percent: percent,
+            batteries: vec![BatteryStats::default()],
+            ..Default::default()
+        };
+        let mut state = RuleState::default();
+        let mut fired = Vec::new();
+        for (timestamp, percent) in [(0, 12.0), (3, 9.0), (6, 12.0), (9, 16.0), (700, 8.0)] {
+            let (next, alert) = evaluate(&rule, state, timestamp, &battery(percent));
+            state = next;
+            fired.extend(alert.map(|alert| alert.timestamp));
+        }
+        assert_eq!(fired, [3, 700]);
+    }
+
+    #[test]
+    fn test_evaluate_expression() {
+        let expression =
+            Expression::parse("cpu > 85 and battery.state == \"Discharging\" for 2m").unwrap();
+        let rule = AlertRule {
+            name: "Busy on battery".to_string(),
+            for_secs: expression.for_secs(),
+            condition: AlertCondition::Expression(expression),
+            cooldown_secs: 0,
+        };
+        assert_eq!(
+            rule.condition(),
+            "cpu > 85 and battery.state == \"Discharging\" for 2m"
+        );
+
+        let sample = |usage: f32, state: &str| SystemStats {
+            cpu_usage: usage,
+            battery_state: state.to_string(),
+            batteries: vec![BatteryStats::default()],
+            ..Default::default()
+        };
+        let mut state = RuleState::default();
+        let mut alerts = Vec::new();
+        for (timestamp, usage, battery) in [
+            (0, 90.0, "Discharging"),
+            (60, 90.0, "Charging"),
+            (90, 90.0, "Discharging"),
+            (180, 90.0, "Discharging"),
+            (210, 95.0, "Discharging"),
+            (240, 50.0, "Discharging"),
+            (360, 90.0, "Discharging"),
+            (480, 90.0, "Discharging"),
+        ] {
+            let (next, alert) = evaluate(&rule, state, timestamp, &sample(usage, battery));
+            state = next;
+            alerts.extend(alert);
+        }
+        // Plugging in restarts the window; dropping below 85% clears the alert at once and the
+        // next one needs another two minutes.
+        assert_eq!(
+            alerts
+                .iter()
+                .map(|alert| alert.timestamp)
+                .collect::<Vec<_>>(),
+            [210, 480]
+        );
+        assert_eq!(
+            alerts[0].to_string(),
+            "cpu.pct is 95.0%, battery.state is Discharging"
+        );
+    }
+
+    #[test]
+    fn test_alert_engine() {
+        let instant = AlertRule {
+            name: "Instant".to_string(),
+            for_secs: 0,
+            ..cpu_rule()
+        };
+        let mut engine = AlertEngine::new(vec![cpu_rule(), instant.clone()]);
+        assert_eq!(engine.evaluate(0, &cpu(95.0)).len(), 1);
+        assert_eq!(engine.evaluate(60, &cpu(95.0)).len(), 1);
+        assert_eq!(
+            engine
+                .recent()
+                .map(|alert| alert.rule.as_str())
+                .collect::<Vec<_>>(),
+            ["CPU busy", "Instant"]
+        );
+
+        // Unchanged rules keep their state across a reload; changed ones start over.
+        let stricter = AlertRule {
+            condition: threshold("cpu.pct", Comparison::Above, 80.0, 10.0),
+            ..cpu_rule()
+        };
+        engine.set_rules(vec![instant, stricter]);
+        assert!(engine.evaluate(63, &cpu(95.0)).is_empty());
+        assert_eq!(engine.evaluate(123, &cpu(95.0)).len(), 1);
+        assert_eq!(engine.rules().count(), 2);
+
+        for timestamp in 0..RECENT_ALERT_COUNT as u64 * 2 {
+            let mut engine_stats = cpu(50.0);
+            engine.evaluate(1_000 + timestamp * 1_000, &engine_stats);
+            engine_stats.cpu_usage = 95.0;
+            engine.evaluate(1_500 + timestamp * 1_000, &engine_stats);
+        }
+        assert_eq!(engine.recent().count(), RECENT_ALERT_COUNT);
+    }
+}
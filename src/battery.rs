@@ -0,0 +1,274 @@
+//! Battery reminders: a low charge while discharging, reaching the charge limit while charging,
+//! and changes of the battery state.
+//!
+//! Every reminder fires once per transition rather than on every sample. The low charge warning
+//! is re-armed when the battery stops discharging and the charge limit reminder when it stops
+//! charging. [`check_battery`] is a pure function of the previous [`BatteryReminderState`] and one
+//! sample; [`BatteryMonitor`] keeps the state between samples.
+
+use crate::stats::SystemStats;
+use std::fmt;
+
+pub const DEFAULT_LOW_BATTERY_PERCENT: f32 = 20.0;
+pub const DEFAULT_CHARGE_LIMIT_PERCENT: f32 = 80.0;
+/// Every value of [`SystemStats::battery_state`] while a battery is present.
+pub const BATTERY_STATES: [&str; 5] = ["Charging", "Discharging", "Full", "Empty", "Unknown"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryPolicy {
+    /// Warn once the charge drops to this while discharging. `None` turns the warning off.
+    pub low_percent: Option<f32>,
+    /// Remind to unplug once the charge reaches this while charging. `None` turns it off.
+    pub charge_limit_percent: Option<f32>,
+    /// Notify whenever the battery state changes, e.g. from "Discharging" to "Charging".
+    pub notify_state_changes: bool,
+}
+
+impl Default for BatteryPolicy {
+    fn default() -> Self {
+        Self {
+            low_percent: Some(DEFAULT_LOW_BATTERY_PERCENT),
+            charge_limit_percent: Some(DEFAULT_CHARGE_LIMIT_PERCENT),
+            notify_state_changes: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatteryReminder {
+    Low {
+        percent: f32,
+    },
+    ChargeLimit {
+        percent: f32,
+        limit: f32,
+    },
+    StateChanged {
+        from: String,
+        to: String,
+        percent: f32,
+    },
+}
+
+impl BatteryReminder {
+    pub fn title(&self) -> String {
+        match self {
+            BatteryReminder::Low { .. } => "Battery low".to_string(),
+            BatteryReminder::ChargeLimit { .. } => "Charge limit reached".to_string(),
+            BatteryReminder::StateChanged { to, .. } => format!("Battery {}", to.to_lowercase()),
+        }
+    }
+}
+
+impl fmt::Display for BatteryReminder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatteryReminder::Low { percent } => write!(
+                f,
+                "Battery is at {:.0}% and discharging. Plug in the charger.",
+                percent
+            ),
+            BatteryReminder::ChargeLimit { percent, limit } => write!(
+                f,
+                "Battery is at {:.0}% (limit {:.0}%). Unplug the charger to preserve battery health.",
+                percent, limit
+            ),
+            BatteryReminder::StateChanged { from, to, percent } => write!(
+                f,
+                "Battery is {} at {:.0}%, was {}.",
+                to.to_lowercase(),
+                percent,
+                from.to_lowercase()
+            ),
+        }
+    }
+}
+
+/// What the reminders have seen so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatteryReminderState {
+    /// The battery state of the previous sample, `None` before the first or without a battery.
+    pub last_state: Option<String>,
+    pub low_sent: bool,
+    pub charge_limit_sent: bool,
+}
+
+/// Advances the reminders by one sample, returning their new state and the reminders to deliver.
+pub fn check_battery(
+    policy: &BatteryPolicy,
+    mut state: BatteryReminderState,
+    stats: &SystemStats,
+) -> (BatteryReminderState, Vec<BatteryReminder>) {
+    if stats.batteries.is_empty() {
+        return (BatteryReminderState::default(), Vec::new());
+    }
+
+    let mut reminders = Vec::new();
+    let percent = stats.battery_percent;
+    let current = stats.battery_state.as_str();
+
+    if let Some(previous) = state.last_state.as_deref() {
+        if previous != current && policy.notify_state_changes {
+            reminders.push(BatteryReminder::StateChanged {
+                from: previous.to_string(),
+                to: current.to_string(),
+                percent,
+            });
+        }
+    }
+    state.last_state = Some(current.to_string());
+
+    if current != "Discharging" {
+        state.low_sent = false;
+    } else if let Some(low) = policy.low_percent {
+        if percent <= low && !state.low_sent {
+            state.low_sent = true;
+            reminders.push(BatteryReminder::Low { percent });
+        }
+    }
+
+    if current != "Charging" {
+        state.charge_limit_sent = false;
+    } else if let Some(limit) = policy.charge_limit_percent {
+        if percent >= limit && !state.charge_limit_sent {
+            state.charge_limit_sent = true;
+            reminders.push(BatteryReminder::ChargeLimit { percent, limit });
+        }
+    }
+
+    (state, reminders)
+}
+
+#[derive(Debug, Default)]
+pub struct BatteryMonitor {
+    policy: BatteryPolicy,
+    state: BatteryReminderState,
+}
+
+impl BatteryMonitor {
+    pub fn new(policy: BatteryPolicy) -> Self {
+        Self {
+            policy,
+            state: BatteryReminderState::default(),
+        }
+    }
+
+    /// Replaces the policy without re-sending reminders that already fired.
+    pub fn set_policy(&mut self, policy: BatteryPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn policy(&self) -> &BatteryPolicy {
+        &self.policy
+    }
+
+    pub fn check(&mut self, stats: &SystemStats) -> Vec<BatteryReminder> {
+        let (state, reminders) =
+            check_battery(&self.policy, std::mem::take(&mut self.state), stats);
+        self.state = state;
+        reminders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::BatteryStats;
+
+    fn sample(percent: f32, state: &str) -> SystemStats {
+        SystemStats {
+            battery_percent: percent,
+            battery_state: state.to_string(),
+            batteries: vec![BatteryStats::default()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_low_battery_fires_once_per_discharge() {
+        let mut monitor = BatteryMonitor::new(BatteryPolicy {
+            notify_state_changes: false,
+            ..Default::default()
+        });
+
+        assert!(monitor.check(&sample(25.0, "Discharging")).is_empty());
+        assert_eq!(
+            monitor.check(&sample(20.0, "Discharging")),
+            [BatteryReminder::Low { percent: 20.0 }]
+        );
+        assert!(monitor.check(&sample(15.0, "Discharging")).is_empty());
+        assert!(monitor.check(&sample(16.0, "Charging")).is_empty());
+        assert_eq!(
+            monitor.check(&sample(14.0, "Discharging")),
+            [BatteryReminder::Low { percent: 14.0 }]
+        );
+
+        let reminder = BatteryReminder::Low { percent: 14.0 };
+        assert_eq!(reminder.title(), "Battery low");
+        assert_eq!(
+            reminder.to_string(),
+            "Battery is at 14% and discharging. Plug in the charger."
+        );
+    }
+
+    #[test]
+    fn test_charge_limit_fires_once_per_charge() {
+        let mut monitor = BatteryMonitor::new(BatteryPolicy {
+            notify_state_changes: false,
+            ..Default::default()
+        });
+
+        assert!(monitor.check(&sample(79.0, "Charging")).is_empty());
+        assert_eq!(
+            monitor.check(&sample(80.0, "Charging")),
+            [BatteryReminder::ChargeLimit {
+                percent: 80.0,
+                limit: 80.0,
+            }]
+        );
+        assert!(monitor.check(&sample(85.0, "Charging")).is_empty());
+        assert!(monitor.check(&sample(100.0, "Full")).is_empty());
+        assert!(monitor.check(&sample(99.0, "Discharging")).is_empty());
+        assert_eq!(monitor.check(&sample(99.0, "Charging")).len(), 1);
+
+        monitor.set_policy(BatteryPolicy {
+            low_percent: None,
+            charge_limit_percent: None,
+            notify_state_changes: false,
+        });
+        assert!(monitor.check(&sample(100.0, "Discharging")).is_empty());
+        assert!(monitor.check(&sample(5.0, "Discharging")).is_empty());
+        assert!(monitor.check(&sample(95.0, "Charging")).is_empty());
+    }
+
+    #[test]
+    fn test_state_changes() {
+        let policy = BatteryPolicy::default();
+        let (state, reminders) =
+            check_battery(&policy, Default::default(), &sample(50.0, "Discharging"));
+        assert!(reminders.is_empty(), "the first sample is not a change");
+        assert_eq!(state.last_state.as_deref(), Some("Discharging"));
+
+        let (state, reminders) = check_battery(&policy, state, &sample(50.0, "Charging"));
+        assert_eq!(
+            reminders,
+            [BatteryReminder::StateChanged {
+                from: "Discharging".to_string(),
+                to: "Charging".to_string(),
+                percent: 50.0,
+            }]
+        );
+        assert_eq!(reminders[0].title(), "Battery charging");
+        assert_eq!(
+            reminders[0].to_string(),
+            "Battery is charging at 50%, was discharging."
+        );
+
+        let (state, reminders) = check_battery(&policy, state, &sample(50.0, "Charging"));
+        assert!(reminders.is_empty());
+
+        let (state, reminders) = check_battery(&policy, state, &SystemStats::default());
+        assert!(reminders.is_empty());
+        assert_eq!(state, BatteryReminderState::default());
+    }
+}
@@ -0,0 +1,850 @@
+//! Headless command line interface printing the same stats as the menu bar app, for use over
+//! SSH, in tmux status lines and in CI jobs. The `export` and `import` commands work on the
+//! history the app stores on disk.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use system_stats_lib::export::{
+    format_rfc3339, parse_rfc3339, read_export, summarize_columns, write_export, write_export_file,
+    COLUMNS,
+};
+use system_stats_lib::format::{format_reading_text, format_reading_value};
+use system_stats_lib::store::{read_samples, STORE_DIR_NAME};
+use system_stats_lib::{ExportFormat, ExportRow, Reading, Registry};
+
+const DEFAULT_WATCH_INTERVAL_SECS: f64 = 3.0;
+/// Delay between the two samples of a one-shot run, so CPU usage and rates have a baseline.
+const ONE_SHOT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const CSV_HEADER: &str = "timestamp,key,instance,value,unit";
+/// The app's bundle identifier, which names its data directory.
+const APP_IDENTIFIER: &str = "dev.larskemper.system-stats";
+const DEFAULT_EXPORT_WINDOW_SECS: u64 = 60 * 60;
+
+const USAGE: &str = "Usage: system-stats-cli [OPTIONS]
+       system-stats-cli export [EXPORT OPTIONS]
+       system-stats-cli import <FILE>...
+
+Print a snapshot of system stats.
+
+Options:
+  -f, --format <FORMAT>    Output format: text, json or csv [default: text]
+  -m, --metric <KEY>       Only print readings for KEY, e.g. cpu or cpu.usage. Repeatable and
+                           accepts comma-separated keys
+  -a, --aggregate          Skip per-device readings such as single cores or volumes
+  -w, --watch              Keep printing snapshots until interrupted
+  -n, --interval <SECS>    Seconds between snapshots in watch mode [default: 3]
+  -l, --list               List the available metric keys and exit
+  -h, --help               Print this help
+  -V, --version            Print the version
+
+Commands:
+  export                   Write the history stored by the app as CSV or JSON Lines
+  import                   Load exports and print the average of every column side by side
+
+Export options:
+  -f, --format <FORMAT>    csv or jsonl [default: csv]
+  -s, --since <DURATION>   Export the last DURATION, e.g. 90s, 30m, 6h or 7d [default: 1h]
+      --from <TIME>        Export from an RFC 3339 time instead
+      --to <TIME>          Export up to an RFC 3339 time [default: now]
+  -o, --output <FILE>      Write to FILE instead of standard output
+      --store <DIR>        Read the history from DIR instead of the app's data directory";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    format: OutputFormat,
+    metrics: Vec<String>,
+    aggregate_only: bool,
+    watch: bool,
+    interval: Duration,
+    list: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::default(),
+            metrics: Vec::new(),
+            aggregate_only: false,
+            watch: false,
+            interval: Duration::from_secs_f64(DEFAULT_WATCH_INTERVAL_SECS),
+            list: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ExportOptions {
+    format: ExportFormat,
+    since_secs: u64,
+    from: Option<u64>,
+    to: Option<u64>,
+    output: Option<PathBuf>,
+    store: Option<PathBuf>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::default(),
+            since_secs: DEFAULT_EXPORT_WINDOW_SECS,
+            from: None,
+            to: None,
+            output: None,
+            store: None,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// The exported time range for a run at `now`.
+    fn range(&self, now: u64) -> std::ops::Range<u64> {
+        let to = self.to.unwrap_or(now);
+        let from = self.from.unwrap_or(to.saturating_sub(self.since_secs));
+        from..to.saturating_add(1)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Run(Options),
+    Export(ExportOptions),
+    Import(Vec<PathBuf>),
+    Help,
+    Version,
+}
+
+/// Splits `--flag=value` into its flag and inline value.
+fn split_flag(arg: &str) -> (&str, Option<&str>) {
+    match arg.split_once('=') {
+        Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+        _ => (arg, None),
+    }
+}
+
+/// Parses durations like `90`, `90s`, `30m`, `6h` or `7d` into seconds.
+fn parse_duration_secs(text: &str) -> Option<u64> {
+    let (number, multiplier) = match text.char_indices().last()? {
+        (index, 's') => (&text[..index], 1),
+        (index, 'm') => (&text[..index], 60),
+        (index, 'h') => (&text[..index], 60 * 60),
+        (index, 'd') => (&text[..index], 24 * 60 * 60),
+        _ => (text, 1),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .filter(|&number| number > 0)?
+        .checked_mul(multiplier)
+}
+
+fn parse_time(name: &str, text: &str) -> Result<u64, String> {
+    parse_rfc3339(text).ok_or_else(|| format!("{} expects an RFC 3339 time, got '{}'", name, text))
+}
+
+fn parse_export_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = ExportOptions::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = split_flag(&arg);
+        let mut value = |name: &str| match inline_value {
+            Some(value) => Ok(value.to_string()),
+            None => args
+                .next()
+                .ok_or_else(|| format!("{} requires a value", name)),
+        };
+
+        match flag {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-f" | "--format" => {
+                options.format = match value("--format")?.as_str() {
+                    "csv" => ExportFormat::Csv,
+                    "jsonl" => ExportFormat::JsonLines,
+                    other => return Err(format!("unknown export format '{}'", other)),
+                }
+            }
+            "-s" | "--since" => {
+                let raw = value("--since")?;
+                options.since_secs = parse_duration_secs(&raw)
+                    .ok_or_else(|| format!("invalid duration '{}'", raw))?;
+            }
+            "--from" => options.from = Some(parse_time("--from", &value("--from")?)?),
+            "--to" => options.to = Some(parse_time("--to", &value("--to")?)?),
+            "-o" | "--output" => options.output = Some(PathBuf::from(value("--output")?)),
+            "--store" => options.store = Some(PathBuf::from(value("--store")?)),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    if let (Some(from), Some(to)) = (options.from, options.to) {
+        if from > to {
+            return Err("--from must not be after --to".to_string());
+        }
+    }
+    Ok(Command::Export(options))
+}
+
+fn parse_import_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut files = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(format!("unexpected argument '{}'", arg))
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    if files.is_empty() {
+        return Err("import requires at least one file".to_string());
+    }
+    Ok(Command::Import(files))
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter().peekable();
+
+    match args.peek().map(String::as_str) {
+        Some("export") => return parse_export_args(args.skip(1)),
+        Some("import") => return parse_import_args(args.skip(1)),
+        _ => {}
+    }
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = split_flag(&arg);
+        let mut value = |name: &str| match inline_value {
+            Some(value) => Ok(value.to_string()),
+            None => args
+                .next()
+                .ok_or_else(|| format!("{} requires a value", name)),
+        };
+
+        match flag {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-f" | "--format" => {
+                options.format = match value("--format")?.as_str() {
+                    "text" => OutputFormat::Text,
+                    "json" => OutputFormat::Json,
+                    "csv" => OutputFormat::Csv,
+                    other => return Err(format!("unknown format '{}'", other)),
+                }
+            }
+            "-m" | "--metric" => options.metrics.extend(
+                value("--metric")?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|metric| !metric.is_empty())
+                    .map(str::to_string),
+            ),
+            "-a" | "--aggregate" => options.aggregate_only = true,
+            "-w" | "--watch" => options.watch = true,
+            "-n" | "--interval" => {
+                let raw = value("--interval")?;
+                let secs: f64 = raw
+                    .parse()
+                    .map_err(|_| format!("invalid interval '{}'", raw))?;
+                if !secs.is_finite() || secs <= 0.0 {
+                    return Err(format!("interval must be positive, got '{}'", raw));
+                }
+                options.interval =
+                    Duration::from_secs_f64(secs).max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+            }
+            "-l" | "--list" => options.list = true,
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    Ok(Command::Run(options))
+}
+
+/// Whether `selector` names `key` itself or one of its parents, e.g. `cpu` for `cpu.usage`.
+fn selector_matches(selector: &str, key: &str) -> bool {
+    key == selector
+        || key
+            .strip_prefix(selector)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+fn select_readings(readings: Vec<Reading>, options: &Options) -> Vec<Reading> {
+    readings
+        .into_iter()
+        .filter(|reading| !options.aggregate_only || reading.instance.is_none())
+        .filter(|reading| {
+            options.metrics.is_empty()
+                || options
+                    .metrics
+                    .iter()
+                    .any(|selector| selector_matches(selector, &reading.key))
+        })
+        .collect()
+}
+
+fn unmatched_metrics<'a>(readings: &[Reading], options: &'a Options) -> Vec<&'a str> {
+    options
+        .metrics
+        .iter()
+        .filter(|selector| {
+            !readings
+                .iter()
+                .any(|reading| selector_matches(selector, &reading.key))
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+fn metric_keys(readings: &[Reading]) -> Vec<&str> {
+    let mut keys: Vec<&str> = Vec::new();
+    for reading in readings {
+        if !keys.contains(&reading.key.as_str()) {
+            keys.push(&reading.key);
+        }
+    }
+    keys
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    timestamp: u64,
+    readings: &'a [Reading],
+}
+
+fn render(format: OutputFormat, timestamp: u64, readings: &[Reading]) -> String {
+    match format {
+        OutputFormat::Text => readings
+            .iter()
+            .map(|reading| format!("{}\n", format_reading_text(reading)))
+            .collect(),
+        OutputFormat::Json => {
+            let snapshot = Snapshot {
+                timestamp,
+                readings,
+            };
+            match serde_json::to_string(&snapshot) {
+                Ok(json) => format!("{}\n", json),
+                Err(e) => {
+                    eprintln!("Failed to serialize snapshot: {}", e);
+                    String::new()
+                }
+            }
+        }
+        OutputFormat::Csv => readings
+            .iter()
+            .map(|reading| {
+                format!(
+                    "{},{},{},{},{}\n",
+                    timestamp,
+                    csv_field(&reading.key),
+                    csv_field(reading.instance.as_deref().unwrap_or("")),
+                    reading.value,
+                    reading.unit.name()
+                )
+            })
+            .collect(),
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+fn run(options: &Options) -> io::Result<ExitCode> {
+    let mut registry = Registry::with_builtin_collectors();
+    registry.refresh();
+    std::thread::sleep(ONE_SHOT_SAMPLE_INTERVAL);
+    registry.refresh();
+
+    let mut stdout = io::stdout().lock();
+    let readings = registry.readings();
+
+    if options.list {
+        for key in metric_keys(&readings) {
+            writeln!(stdout, "{}", key)?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let unmatched = unmatched_metrics(&readings, options);
+    for metric in &unmatched {
+        eprintln!("Unknown metric: {}", metric);
+    }
+    if !options.metrics.is_empty() && unmatched.len() == options.metrics.len() {
+        return Ok(ExitCode::from(2));
+    }
+
+    if options.format == OutputFormat::Csv {
+        writeln!(stdout, "{}", CSV_HEADER)?;
+    }
+
+    let mut readings = readings;
+    let mut first = true;
+    loop {
+        if options.watch && options.format == OutputFormat::Text && !first {
+            writeln!(stdout)?;
+        }
+        let output = render(
+            options.format,
+            unix_timestamp(),
+            &select_readings(readings, options),
+        );
+        stdout.write_all(output.as_bytes())?;
+        stdout.flush()?;
+
+        if !options.watch {
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        first = false;
+        std::thread::sleep(options.interval);
+        registry.refresh();
+        readings = registry.readings();
+    }
+}
+
+/// Where the app keeps its history: its data directory, as resolved by Tauri, plus
+/// [`STORE_DIR_NAME`].
+fn default_store_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    let data_dir = if cfg!(target_os = "macos") {
+        PathBuf::from(env_dir("HOME")?).join("Library/Application Support")
+    } else if cfg!(windows) {
+        PathBuf::from(env_dir("APPDATA")?)
+    } else {
+        env_dir("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env_dir("HOME").map(|home| PathBuf::from(home).join(".local/share")))?
+    };
+    Some(data_dir.join(APP_IDENTIFIER).join(STORE_DIR_NAME))
+}
+
+fn run_export(options: &ExportOptions) -> io::Result<ExitCode> {
+    let Some(store) = options.store.clone().or_else(default_store_dir) else {
+        eprintln!("Failed to locate the app's data directory, pass --store");
+        return Ok(ExitCode::FAILURE);
+    };
+    let samples = match read_samples(&store, options.range(unix_timestamp())) {
+        Ok(samples) => samples,
+        Err(e) => {
+            eprintln!("Failed to read stored history: {}", e);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    let rows: Vec<ExportRow> = samples.iter().map(ExportRow::from).collect();
+
+    match &options.output {
+        Some(path) => write_export_file(path, options.format, &rows)?,
+        None => {
+            let mut stdout = io::stdout().lock();
+            write_export(&mut stdout, options.format, &rows)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// A table with one row per column of the export schema and the average of every export next to
+/// each other, preceded by the sample count and time span of each export.
+fn render_comparison(names: &[String], exports: &[Vec<ExportRow>]) -> String {
+    let span = |rows: &[ExportRow], last: bool| {
+        let row = if last { rows.last() } else { rows.first() };
+        row.map_or("-".to_string(), |row| format_rfc3339(row.timestamp))
+    };
+    let summaries: Vec<_> = exports.iter().map(|rows| summarize_columns(rows)).collect();
+
+    let mut table: Vec<Vec<String>> = vec![
+        std::iter::once(String::new())
+            .chain(names.iter().cloned())
+            .collect(),
+        std::iter::once("samples".to_string())
+            .chain(exports.iter().map(|rows| rows.len().to_string()))
+            .collect(),
+        std::iter::once("from".to_string())
+            .chain(exports.iter().map(|rows| span(rows, false)))
+            .collect(),
+        std::iter::once("to".to_string())
+            .chain(exports.iter().map(|rows| span(rows, true)))
+            .collect(),
+    ];
+    for (index, column) in COLUMNS.iter().enumerate() {
+        let cells = summaries.iter().map(|summaries| match &summaries[index] {
+            Some(summary) => {
+                format_reading_value(&Reading::new(column.reading, summary.avg, summary.unit))
+            }
+            None => "-".to_string(),
+        });
+        table.push(
+            std::iter::once(column.name.to_string())
+                .chain(cells)
+                .collect(),
+        );
+    }
+
+    let widths: Vec<usize> = (0..table[0].len())
+        .map(|cell| {
+            table
+                .iter()
+                .map(|row| row[cell].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    table
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            format!("{}\n", cells.join("  ").trim_end())
+        })
+        .collect()
+}
+
+fn run_import(files: &[PathBuf]) -> io::Result<ExitCode> {
+    let mut exports = Vec::new();
+    for path in files {
+        let Some(format) = ExportFormat::from_path(path) else {
+            eprintln!(
+                "Failed to import {}: expected a .csv or .jsonl file",
+                path.display()
+            );
+            return Ok(ExitCode::FAILURE);
+        };
+        let rows = File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| read_export(BufReader::new(file), format).map_err(|e| e.to_string()));
+        match rows {
+            Ok(rows) => exports.push(rows),
+            Err(e) => {
+                eprintln!("Failed to import {}: {}", path.display(), e);
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    let names: Vec<String> = files
+        .iter()
+        .map(|path| {
+            path.file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(render_comparison(&names, &exports).as_bytes())?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    let result = match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Run(options)) => run(&options),
+        Ok(Command::Export(options)) => run_export(&options),
+        Ok(Command::Import(files)) => run_import(&files),
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Command::Version) => {
+            println!("system-stats-cli {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Failed to write output: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use system_stats_lib::Unit;
+
+    fn args(list: &[&str]) -> Result<Command, String> {
+        parse_args(list.iter().map(|arg| arg.to_string()))
+    }
+
+    fn readings() -> Vec<Reading> {
+        vec![
+            Reading::new("cpu.usage", 12.5, Unit::Percent),
+            Reading::new("cpu.usage", 30.0, Unit::Percent).with_instance("core1"),
+            Reading::new("cpu.load_one", 1.25, Unit::Count),
+            Reading::new("cpuset.count", 4.0, Unit::Count),
+            Reading::new("disk.percent", 71.0, Unit::Percent).with_instance("/Volumes/A, \"B\""),
+        ]
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(args(&[]), Ok(Command::Run(Options::default())));
+        assert_eq!(args(&["--help", "--bogus"]), Ok(Command::Help));
+        assert_eq!(args(&["-V"]), Ok(Command::Version));
+
+        let Ok(Command::Run(options)) = args(&[
+            "--format=json",
+            "-m",
+            "cpu.usage, memory",
+            "--metric",
+            "disk",
+            "-a",
+            "--watch",
+            "-n",
+            "0.5",
+        ]) else {
+            panic!("expected options");
+        };
+        assert_eq!(options.format, OutputFormat::Json);
+        assert_eq!(options.metrics, ["cpu.usage", "memory", "disk"]);
+        assert!(options.aggregate_only);
+        assert!(options.watch);
+        assert_eq!(options.interval, Duration::from_millis(500));
+
+        let Ok(Command::Run(options)) = args(&["--interval", "0.01", "-f", "csv"]) else {
+            panic!("expected options");
+        };
+        assert_eq!(options.interval, sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        assert_eq!(options.format, OutputFormat::Csv);
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert_eq!(
+            args(&["--format", "xml"]),
+            Err("unknown format 'xml'".to_string())
+        );
+        assert_eq!(
+            args(&["--metric"]),
+            Err("--metric requires a value".to_string())
+        );
+        assert_eq!(
+            args(&["-n", "soon"]),
+            Err("invalid interval 'soon'".to_string())
+        );
+        assert_eq!(
+            args(&["--interval=0"]),
+            Err("interval must be positive, got '0'".to_string())
+        );
+        assert_eq!(
+            args(&["snapshot"]),
+            Err("unexpected argument 'snapshot'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_export_and_import_args() {
+        assert_eq!(
+            args(&["export"]),
+            Ok(Command::Export(ExportOptions::default()))
+        );
+
+        let Ok(Command::Export(options)) = args(&[
+            "export",
+            "--format=jsonl",
+            "-s",
+            "30m",
+            "-o",
+            "stats.jsonl",
+            "--store",
+            "/tmp/history",
+        ]) else {
+            panic!("expected export options");
+        };
+        assert_eq!(options.format, ExportFormat::JsonLines);
+        assert_eq!(options.since_secs, 1_800);
+        assert_eq!(options.output, Some(PathBuf::from("stats.jsonl")));
+        assert_eq!(options.store, Some(PathBuf::from("/tmp/history")));
+        assert_eq!(options.range(10_000), 8_200..10_001);
+
+        let Ok(Command::Export(options)) = args(&[
+            "export",
+            "--from",
+            "2025-10-17T09:00:00Z",
+            "--to",
+            "2025-10-17T10:00:00+01:00",
+        ]) else {
+            panic!("expected export options");
+        };
+        assert_eq!(options.range(u64::MAX), 1_760_691_600..1_760_691_601);
+
+        assert_eq!(
+            args(&["import", "before.csv", "after.jsonl"]),
+            Ok(Command::Import(vec![
+                PathBuf::from("before.csv"),
+                PathBuf::from("after.jsonl")
+            ]))
+        );
+
+        assert_eq!(
+            args(&["export", "-f", "parquet"]),
+            Err("unknown export format 'parquet'".to_string())
+        );
+        assert_eq!(
+            args(&["export", "--since", "1w"]),
+            Err("invalid duration '1w'".to_string())
+        );
+        assert_eq!(
+            args(&["export", "--from", "yesterday"]),
+            Err("--from expects an RFC 3339 time, got 'yesterday'".to_string())
+        );
+        assert_eq!(
+            args(&[
+                "export",
+                "--from",
+                "2025-10-17T10:00:00Z",
+                "--to",
+                "2025-10-17T09:00:00Z"
+            ]),
+            Err("--from must not be after --to".to_string())
+        );
+        assert_eq!(
+            args(&["import"]),
+            Err("import requires at least one file".to_string())
+        );
+        assert_eq!(
+            args(&["import", "--watch", "a.csv"]),
+            Err("unexpected argument '--watch'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_secs() {
+        assert_eq!(parse_duration_secs("90"), Some(90));
+        assert_eq!(parse_duration_secs("90s"), Some(90));
+        assert_eq!(parse_duration_secs("30m"), Some(1_800));
+        assert_eq!(parse_duration_secs("6h"), Some(21_600));
+        assert_eq!(parse_duration_secs("7d"), Some(604_800));
+        assert_eq!(parse_duration_secs("0h"), None);
+        assert_eq!(parse_duration_secs("h"), None);
+        assert_eq!(parse_duration_secs(""), None);
+    }
+
+    #[test]
+    fn test_render_comparison() {
+        let row = |timestamp, cpu| {
+            let mut values = vec![None; COLUMNS.len()];
+            values[2] = Some(cpu);
+            ExportRow {
+                timestamp,
+                resolution_secs: 0,
+                values,
+            }
+        };
+        let names = ["before.csv".to_string(), "after.jsonl".to_string()];
+        let exports = [
+            vec![row(1_760_693_400, 10.0), row(1_760_693_460, 20.0)],
+            vec![],
+        ];
+
+        let table = render_comparison(&names, &exports);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4 + COLUMNS.len());
+        assert_eq!(
+            lines[0].split_whitespace().collect::<Vec<_>>(),
+            ["before.csv", "after.jsonl"]
+        );
+        assert_eq!(
+            lines[1].split_whitespace().collect::<Vec<_>>(),
+            ["samples", "2", "0"]
+        );
+        assert_eq!(
+            lines[3].split_whitespace().collect::<Vec<_>>(),
+            ["to", "2025-10-17T09:31:00Z", "-"]
+        );
+        assert_eq!(
+            lines[6].split_whitespace().collect::<Vec<_>>(),
+            ["cpu_usage_percent", "15.0%", "-"]
+        );
+    }
+
+    #[test]
+    fn test_select_readings() {
+        let options = Options {
+            metrics: vec!["cpu".to_string()],
+            ..Default::default()
+        };
+        let selected = select_readings(readings(), &options);
+        assert_eq!(
+            selected.iter().map(Reading::id).collect::<Vec<_>>(),
+            ["cpu.usage", "cpu.usage[core1]", "cpu.load_one"]
+        );
+
+        let options = Options {
+            metrics: vec!["cpu.usage".to_string(), "gpu".to_string()],
+            aggregate_only: true,
+            ..Default::default()
+        };
+        let selected = select_readings(readings(), &options);
+        assert_eq!(
+            selected.iter().map(Reading::id).collect::<Vec<_>>(),
+            ["cpu.usage"]
+        );
+        assert_eq!(unmatched_metrics(&readings(), &options), ["gpu"]);
+
+        assert_eq!(
+            select_readings(readings(), &Options::default()).len(),
+            readings().len()
+        );
+        assert_eq!(
+            metric_keys(&readings()),
+            ["cpu.usage", "cpu.load_one", "cpuset.count", "disk.percent"]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let readings = readings();
+
+        let text = render(OutputFormat::Text, 0, &readings[..2]);
+        assert_eq!(text, "cpu.usage: 12.5%\ncpu.usage[core1]: 30.0%\n");
+
+        let json = render(OutputFormat::Json, 1_700_000_000, &readings[..1]);
+        assert_eq!(
+            json,
+            "{\"timestamp\":1700000000,\"readings\":\
+             [{\"key\":\"cpu.usage\",\"value\":12.5,\"unit\":\"percent\"}]}\n"
+        );
+
+        let csv = render(OutputFormat::Csv, 1_700_000_000, &readings[2..]);
+        assert_eq!(
+            csv,
+            "1700000000,cpu.load_one,,1.25,count\n\
+             1700000000,cpuset.count,,4,count\n\
+             1700000000,disk.percent,\"/Volumes/A, \"\"B\"\"\",71,percent\n"
+        );
+    }
+}
@@ -0,0 +1,1758 @@
+//! The built-in collectors, which turn sysinfo and battery readings into [`SystemStats`].
+
+use crate::config::Config;
+use crate::format::{
+    format_battery_details, format_battery_segment, format_battery_text, format_battery_unit_text,
+    format_cpu_core_text, format_cpu_segment, format_cpu_text, format_disk_text,
+    format_disk_volume_text, format_load_text, format_memory_details, format_memory_segment,
+    format_memory_text, format_network_interface_text, format_network_segment, format_network_text,
+    format_rate, format_temperature_segment, format_temperature_text, format_temperature_value,
+    CpuTraySegment, TrayOptions,
+};
+use crate::history::History;
+use crate::menu::{with_summary, MenuEntry};
+use crate::registry::Collector;
+use crate::stats::{
+    BatteryDetails, BatteryStats, DiskStats, DiskVolumeStats, MemoryPressure,
+    NetworkInterfaceStats, NetworkStats, ProcessStats, Reading, SystemStats, TemperatureStats,
+    Unit,
+};
+use starship_battery::units::{
+    electric_potential, energy, power, ratio, thermodynamic_temperature, time,
+};
+use starship_battery::State as BatteryState;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use sysinfo::{Components, Disks, Networks, System};
+
+pub const DEFAULT_EXCLUDED_FILE_SYSTEMS: [&str; 10] = [
+    "tmpfs", "devtmpfs", "devfs", "overlay", "proc", "sysfs", "autofs", "squashfs", "nullfs",
+    "ramfs",
+];
+const BATTERY_RESCAN_INTERVAL_SECS: u64 = 30;
+/// How often the sensor list is rebuilt to pick up added or removed sensors. Rebuilding resets
+/// the max sysinfo tracks, so [`TemperatureCollector`] keeps its own.
+const TEMPERATURE_RESCAN_INTERVAL_SECS: u64 = 60;
+pub const TOP_PROCESS_COUNT: usize = 5;
+
+struct NetworkCollector {
+    networks: Networks,
+    last_refresh: Instant,
+    totals: HashMap<String, (u64, u64)>,
+    latest: SystemStats,
+}
+
+impl NetworkCollector {
+    pub fn new() -> Self {
+        Self {
+            networks: Networks::new_with_refreshed_list(),
+            last_refresh: Instant::now(),
+            totals: HashMap::new(),
+            latest: SystemStats::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryPressureThresholds {
+    pub warning_available_percent: f32,
+    pub critical_available_percent: f32,
+    pub warning_swap_growth_per_sec: u64,
+    pub critical_swap_growth_per_sec: u64,
+}
+
+impl Default for MemoryPressureThresholds {
+    fn default() -> Self {
+        Self {
+            warning_available_percent: 20.0,
+            critical_available_percent: 10.0,
+            warning_swap_growth_per_sec: 1024 * 1024,
+            critical_swap_growth_per_sec: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl MemoryPressureThresholds {
+    pub fn level(&self, available_percent: f32, swap_growth_per_sec: u64) -> MemoryPressure {
+        let from_available = if available_percent <= self.critical_available_percent {
+            MemoryPressure::Critical
+        } else if available_percent <= self.warning_available_percent {
+            MemoryPressure::Warning
+        } else {
+            MemoryPressure::Normal
+        };
+
+        let from_swap = if swap_growth_per_sec >= self.critical_swap_growth_per_sec {
+            MemoryPressure::Critical
+        } else if swap_growth_per_sec >= self.warning_swap_growth_per_sec {
+            MemoryPressure::Warning
+        } else {
+            MemoryPressure::Normal
+        };
+
+        from_available.max(from_swap)
+    }
+}
+
+struct MemoryCollector {
+    thresholds: MemoryPressureThresholds,
+    last_swap_used: Option<u64>,
+    last_refresh: Instant,
+    latest: SystemStats,
+}
+
+impl MemoryCollector {
+    pub fn new(thresholds: MemoryPressureThresholds) -> Self {
+        Self {
+            thresholds,
+            last_swap_used: None,
+            last_refresh: Instant::now(),
+            latest: SystemStats::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskFilter {
+    pub excluded_file_systems: Vec<String>,
+    pub excluded_mount_points: Vec<String>,
+}
+
+impl Default for DiskFilter {
+    fn default() -> Self {
+        Self {
+            excluded_file_systems: DEFAULT_EXCLUDED_FILE_SYSTEMS
+                .iter()
+                .map(|fs| fs.to_string())
+                .collect(),
+            excluded_mount_points: Vec::new(),
+        }
+    }
+}
+
+impl DiskFilter {
+    pub fn includes(&self, file_system: &str, mount_point: &str) -> bool {
+        !self
+            .excluded_file_systems
+            .iter()
+            .any(|fs| fs.eq_ignore_ascii_case(file_system))
+            && !self
+                .excluded_mount_points
+                .iter()
+                .any(|prefix| mount_point.starts_with(prefix.as_str()))
+    }
+}
+
+struct DiskCollector {
+    disks: Disks,
+    filter: DiskFilter,
+    last_refresh: Instant,
+    latest: DiskStats,
+}
+
+impl DiskCollector {
+    pub fn new(filter: DiskFilter) -> Self {
+        Self {
+            disks: Disks::new_with_refreshed_list(),
+            filter,
+            last_refresh: Instant::now(),
+            latest: DiskStats::default(),
+        }
+    }
+}
+
+/// Collapses a failure that repeats every tick into a single log line plus a counter.
+#[derive(Debug, Default)]
+struct ErrorLog {
+    last: Option<String>,
+    repeats: u32,
+}
+
+impl ErrorLog {
+    /// Returns the line to log for `error`, or `None` when it only repeats the previous failure.
+    fn record(&mut self, error: String) -> Option<String> {
+        if self.last.as_deref() == Some(error.as_str()) {
+            self.repeats += 1;
+            return self
+                .repeats
+                .is_multiple_of(100)
+                .then(|| format!("{} (repeated {} times)", error, self.repeats));
+        }
+
+        let line = match self.repeats {
+            0 | 1 => error.clone(),
+            repeats => format!("{} (previous error repeated {} times)", error, repeats),
+        };
+        self.last = Some(error);
+        self.repeats = 1;
+        Some(line)
+    }
+
+    /// Forgets the current failure, returning a recovery line when one had been logged.
+    fn clear(&mut self) -> Option<String> {
+        let last = self.last.take()?;
+        let repeats = std::mem::take(&mut self.repeats);
+        let attempts = if repeats == 1 { "attempt" } else { "attempts" };
+        Some(format!(
+            "Recovered after {} failed {}: {}",
+            repeats, attempts, last
+        ))
+    }
+}
+
+struct BatteryCollector {
+    manager: Option<starship_battery::Manager>,
+    batteries: Vec<starship_battery::Battery>,
+    last_scan: Option<Instant>,
+    errors: ErrorLog,
+    latest: SystemStats,
+}
+
+impl BatteryCollector {
+    pub fn new() -> Self {
+        Self {
+            manager: None,
+            batteries: Vec::new(),
+            last_scan: None,
+            errors: ErrorLog::default(),
+            latest: SystemStats::default(),
+        }
+    }
+}
+
+/// The built-in collectors, in the order their segments appear in the menu bar title.
+pub fn builtin_collectors() -> Vec<Box<dyn Collector>> {
+    vec![
+        Box::new(BatteryCollector::new()),
+        Box::new(CpuCollector::default()),
+        Box::new(MemoryCollector::new(MemoryPressureThresholds::default())),
+        Box::new(DiskCollector::new(DiskFilter::default())),
+        Box::new(NetworkCollector::new()),
+        Box::new(ProcessCollector::default()),
+        Box::new(TemperatureCollector::new()),
+    ]
+}
+
+impl Collector for BatteryCollector {
+    fn name(&self) -> &str {
+        "battery"
+    }
+
+    fn refresh(&mut self, _system: &System) {
+        let (battery_percent, battery_state, batteries) = collect_battery_stats(self);
+        self.latest = SystemStats {
+            battery_percent: battery_percent.clamp(0.0, 100.0),
+            battery_state,
+            batteries,
+            ..Default::default()
+        };
+    }
+
+    fn readings(&self) -> Vec<Reading> {
+        let stats = &self.latest;
+        let mut readings = vec![
+            Reading::new(
+                "battery.percent",
+                stats.battery_percent as f64,
+                Unit::Percent,
+            ),
+            Reading::new(
+                "battery.power",
+                stats
+                    .batteries
+                    .iter()
+                    .map(|battery| battery.details.power_watts as f64)
+                    .sum(),
+                Unit::Watts,
+            ),
+        ];
+        if let Some(secs) = aggregate_time_to_empty(&stats.batteries)
+            .filter(|_| stats.battery_state == "Discharging")
+        {
+            readings.push(Reading::new(
+                "battery.time_to_empty",
+                secs as f64,
+                Unit::Seconds,
+            ));
+        }
+
+        for battery in &stats.batteries {
+            readings.push(
+                Reading::new("battery.percent", battery.percent as f64, Unit::Percent)
+                    .with_instance(&battery.name),
+            );
+            readings.push(
+                Reading::new(
+                    "battery.health",
+                    battery.details.health_percent as f64,
+                    Unit::Percent,
+                )
+                .with_instance(&battery.name),
+            );
+            if let Some(cycles) = battery.details.cycle_count {
+                readings.push(
+                    Reading::new("battery.cycles", cycles as f64, Unit::Count)
+                        .with_instance(&battery.name),
+                );
+            }
+        }
+        readings
+    }
+
+    fn update_stats(&self, stats: &mut SystemStats) {
+        stats.battery_percent = self.latest.battery_percent;
+        stats.battery_state = self.latest.battery_state.clone();
+        stats.batteries = self.latest.batteries.clone();
+    }
+
+    fn tray_segment(&self, options: &TrayOptions, history: Option<&History>) -> Option<String> {
+        let sparkline = history.and_then(|h| h.sparkline("battery.pct", options.sparkline_length));
+        Some(format_battery_segment(
+            &self.latest,
+            options,
+            sparkline.as_deref(),
+        ))
+    }
+
+    fn menu_entries(&self, _options: &TrayOptions, history: &History) -> Vec<MenuEntry> {
+        let stats = &self.latest;
+        let mut text = format_battery_text(stats);
+        // Without a battery the percentage is a placeholder, not worth summarizing.
+        if !stats.batteries.is_empty() {
+            text = with_summary(text, "", history, "battery.pct");
+        }
+        vec![
+            MenuEntry::new(
+                "battery",
+                text,
+                Some(format!("{}%", stats.battery_percent.round() as i32)),
+            ),
+            MenuEntry::submenu("units", "Battery", battery_unit_entries(stats)),
+        ]
+    }
+}
+
+/// A header per battery followed by its details, or the battery state alone without batteries.
+fn battery_unit_entries(stats: &SystemStats) -> Vec<MenuEntry> {
+    if stats.batteries.is_empty() {
+        return vec![MenuEntry::new("unit_none", &stats.battery_state, None)];
+    }
+
+    stats
+        .batteries
+        .iter()
+        .enumerate()
+        .flat_map(|(index, battery)| {
+            let header = MenuEntry::new(
+                format!("unit_{}", index),
+                format_battery_unit_text(battery),
+                Some(format!("{}%", battery.percent.round() as i32)),
+            );
+            let details =
+                format_battery_details(battery)
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(line, text)| {
+                        MenuEntry::new(
+                            format!("unit_{}_detail_{}", index, line),
+                            format!("    {}", text),
+                            None,
+                        )
+                    });
+            std::iter::once(header).chain(details)
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct CpuCollector {
+    latest: SystemStats,
+}
+
+impl Collector for CpuCollector {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+
+    fn refresh(&mut self, system: &System) {
+        let load_average = System::load_average();
+        self.latest = SystemStats {
+            cpu_usage: system.global_cpu_info().cpu_usage().clamp(0.0, 100.0),
+            cpu_cores: system
+                .cpus()
+                .iter()
+                .map(|cpu| cpu.cpu_usage().clamp(0.0, 100.0))
+                .collect(),
+            load_average_one: load_average.one.max(0.0),
+            load_average_five: load_average.five.max(0.0),
+            load_average_fifteen: load_average.fifteen.max(0.0),
+            uptime_secs: System::uptime(),
+            boot_time: System::boot_time(),
+            ..Default::default()
+        };
+    }
+
+    fn readings(&self) -> Vec<Reading> {
+        let stats = &self.latest;
+        let mut readings = vec![Reading::new(
+            "cpu.usage",
+            stats.cpu_usage as f64,
+            Unit::Percent,
+        )];
+        readings.extend(stats.cpu_cores.iter().enumerate().map(|(index, usage)| {
+            Reading::new("cpu.usage", *usage as f64, Unit::Percent)
+                .with_instance(format!("core{}", index + 1))
+        }));
+        readings.extend([
+            Reading::new("cpu.load_one", stats.load_average_one, Unit::Count),
+            Reading::new("cpu.load_five", stats.load_average_five, Unit::Count),
+            Reading::new("cpu.load_fifteen", stats.load_average_fifteen, Unit::Count),
+            Reading::new("cpu.uptime", stats.uptime_secs as f64, Unit::Seconds),
+        ]);
+        readings
+    }
+
+    fn update_stats(&self, stats: &mut SystemStats) {
+        stats.cpu_usage = self.latest.cpu_usage;
+        stats.cpu_cores = self.latest.cpu_cores.clone();
+        stats.load_average_one = self.latest.load_average_one;
+        stats.load_average_five = self.latest.load_average_five;
+        stats.load_average_fifteen = self.latest.load_average_fifteen;
+        stats.uptime_secs = self.latest.uptime_secs;
+        stats.boot_time = self.latest.boot_time;
+    }
+
+    fn tray_segment(&self, options: &TrayOptions, history: Option<&History>) -> Option<String> {
+        let metric = match options.cpu_segment {
+            CpuTraySegment::Usage => "cpu.pct",
+            CpuTraySegment::LoadAverage => "load.pct",
+        };
+        let sparkline = history.and_then(|h| h.sparkline(metric, options.sparkline_length));
+        Some(format_cpu_segment(
+            &self.latest,
+            options,
+            sparkline.as_deref(),
+        ))
+    }
+
+    fn menu_entries(&self, _options: &TrayOptions, history: &History) -> Vec<MenuEntry> {
+        let stats = &self.latest;
+        let cores = stats
+            .cpu_cores
+            .iter()
+            .enumerate()
+            .map(|(index, usage)| {
+                MenuEntry::new(
+                    format!("core_{}", index),
+                    format_cpu_core_text(index, *usage),
+                    Some(format!("{:.1}%", usage)),
+                )
+            })
+            .collect();
+        vec![
+            MenuEntry::new(
+                "usage",
+                with_summary(format_cpu_text(stats), "", history, "cpu.pct"),
+                Some(format!("{:.1}%", stats.cpu_usage)),
+            ),
+            MenuEntry::new(
+                "load",
+                with_summary(format_load_text(stats), "", history, "load.one"),
+                Some(format!(
+                    "{:.2} {:.2} {:.2}",
+                    stats.load_average_one, stats.load_average_five, stats.load_average_fifteen
+                )),
+            ),
+            MenuEntry::submenu("cores", "CPU", cores),
+        ]
+    }
+}
+
+impl Collector for MemoryCollector {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn refresh(&mut self, system: &System) {
+        let memory_total = system.total_memory();
+        let memory_available = system.available_memory().min(memory_total);
+        let memory_used = memory_total - memory_available;
+        let memory_free = system.free_memory().min(memory_available);
+
+        let swap_total = system.total_swap();
+        let swap_used = system.used_swap().min(swap_total);
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refresh).as_secs_f64();
+        self.last_refresh = now;
+        let swap_growth = self
+            .last_swap_used
+            .map(|last| bytes_per_sec(swap_used.saturating_sub(last), elapsed))
+            .unwrap_or(0);
+        self.last_swap_used = Some(swap_used);
+
+        self.latest = SystemStats {
+            memory_used,
+            memory_total,
+            memory_percent: usage_percent(memory_used, memory_total),
+            memory_available,
+            memory_free,
+            memory_reclaimable: memory_available.saturating_sub(memory_free),
+            memory_pressure: self
+                .thresholds
+                .level(usage_percent(memory_available, memory_total), swap_growth),
+            swap_used,
+            swap_total,
+            swap_percent: usage_percent(swap_used, swap_total),
+            ..Default::default()
+        };
+    }
+
+    fn readings(&self) -> Vec<Reading> {
+        let stats = &self.latest;
+        vec![
+            Reading::new("memory.percent", stats.memory_percent as f64, Unit::Percent),
+            Reading::new("memory.used", stats.memory_used as f64, Unit::Bytes),
+            Reading::new("memory.total", stats.memory_total as f64, Unit::Bytes),
+            Reading::new(
+                "memory.available",
+                stats.memory_available as f64,
+                Unit::Bytes,
+            ),
+            Reading::new(
+                "memory.pressure",
+                stats.memory_pressure as u8 as f64,
+                Unit::Count,
+            ),
+            Reading::new("memory.swap_used", stats.swap_used as f64, Unit::Bytes),
+            Reading::new(
+                "memory.swap_percent",
+                stats.swap_percent as f64,
+                Unit::Percent,
+            ),
+        ]
+    }
+
+    fn update_stats(&self, stats: &mut SystemStats) {
+        stats.memory_used = self.latest.memory_used;
+        stats.memory_total = self.latest.memory_total;
+        stats.memory_percent = self.latest.memory_percent;
+        stats.memory_available = self.latest.memory_available;
+        stats.memory_free = self.latest.memory_free;
+        stats.memory_reclaimable = self.latest.memory_reclaimable;
+        stats.memory_pressure = self.latest.memory_pressure;
+        stats.swap_used = self.latest.swap_used;
+        stats.swap_total = self.latest.swap_total;
+        stats.swap_percent = self.latest.swap_percent;
+    }
+
+    fn tray_segment(&self, options: &TrayOptions, history: Option<&History>) -> Option<String> {
+        let sparkline = history.and_then(|h| h.sparkline("mem.pct", options.sparkline_length));
+        Some(format_memory_segment(
+            &self.latest,
+            options,
+            sparkline.as_deref(),
+        ))
+    }
+
+    fn menu_entries(&self, _options: &TrayOptions, history: &History) -> Vec<MenuEntry> {
+        let stats = &self.latest;
+        let details = format_memory_details(stats)
+            .into_iter()
+            .enumerate()
+            .map(|(line, text)| MenuEntry::new(format!("detail_{}", line), text, None))
+            .collect();
+        vec![
+            MenuEntry::new(
+                "memory",
+                with_summary(format_memory_text(stats), "", history, "mem.pct"),
+                Some(format!("{:.1}%", stats.memory_percent)),
+            ),
+            MenuEntry::submenu("details", "Memory", details),
+        ]
+    }
+
+    fn configure(&mut self, config: &Config) {
+        self.thresholds = config.memory_pressure.clone();
+    }
+}
+
+impl Collector for DiskCollector {
+    fn name(&self) -> &str {
+        "disk"
+    }
+
+    fn refresh(&mut self, system: &System) {
+        self.latest = collect_disk_stats(system, self);
+    }
+
+    fn configure(&mut self, config: &Config) {
+        self.filter = config.disk_filter.clone();
+    }
+
+    fn readings(&self) -> Vec<Reading> {
+        let disk = &self.latest;
+        let mut readings = vec![
+            Reading::new("disk.read", disk.read_per_sec as f64, Unit::BytesPerSecond),
+            Reading::new(
+                "disk.written",
+                disk.written_per_sec as f64,
+                Unit::BytesPerSecond,
+            ),
+        ];
+        if let Some(fullest) = disk.fullest_volume() {
+            readings.push(Reading::new(
+                "disk.percent",
+                fullest.percent as f64,
+                Unit::Percent,
+            ));
+        }
+        for volume in &disk.volumes {
+            readings.extend(
+                [
+                    Reading::new("disk.percent", volume.percent as f64, Unit::Percent),
+                    Reading::new("disk.used", volume.used as f64, Unit::Bytes),
+                    Reading::new("disk.total", volume.total as f64, Unit::Bytes),
+                ]
+                .map(|reading| reading.with_instance(&volume.mount_point)),
+            );
+        }
+        readings
+    }
+
+    fn update_stats(&self, stats: &mut SystemStats) {
+        stats.disk = self.latest.clone();
+    }
+
+    fn menu_entries(&self, _options: &TrayOptions, history: &History) -> Vec<MenuEntry> {
+        let stats = SystemStats {
+            disk: self.latest.clone(),
+            ..Default::default()
+        };
+        let volumes = self
+            .latest
+            .volumes
+            .iter()
+            .map(|volume| {
+                MenuEntry::new(
+                    format!("volume_{}", volume.mount_point),
+                    format_disk_volume_text(volume),
+                    Some(format!("{:.1}%", volume.percent)),
+                )
+            })
+            .collect();
+        vec![
+            MenuEntry::new(
+                "disk",
+                with_summary(format_disk_text(&stats), "R ", history, "disk.read"),
+                self.latest
+                    .fullest_volume()
+                    .map(|volume| format!("{:.1}%", volume.percent)),
+            ),
+            MenuEntry::submenu("volumes", "Volumes", volumes),
+        ]
+    }
+}
+
+impl Collector for NetworkCollector {
+    fn name(&self) -> &str {
+        "network"
+    }
+
+    fn refresh(&mut self, _system: &System) {
+        self.latest = SystemStats {
+            network: collect_network_stats(self),
+            ..Default::default()
+        };
+    }
+
+    fn readings(&self) -> Vec<Reading> {
+        let network = &self.latest.network;
+        let mut readings = vec![
+            Reading::new(
+                "network.received",
+                network.received_per_sec as f64,
+                Unit::BytesPerSecond,
+            ),
+            Reading::new(
+                "network.transmitted",
+                network.transmitted_per_sec as f64,
+                Unit::BytesPerSecond,
+            ),
+        ];
+        for interface in &network.interfaces {
+            readings.extend(
+                [
+                    Reading::new(
+                        "network.received",
+                        interface.received_per_sec as f64,
+                        Unit::BytesPerSecond,
+                    ),
+                    Reading::new(
+                        "network.transmitted",
+                        interface.transmitted_per_sec as f64,
+                        Unit::BytesPerSecond,
+                    ),
+                    Reading::new(
+                        "network.total_received",
+                        interface.total_received as f64,
+                        Unit::Bytes,
+                    ),
+                    Reading::new(
+                        "network.total_transmitted",
+                        interface.total_transmitted as f64,
+                        Unit::Bytes,
+                    ),
+                ]
+                .map(|reading| reading.with_instance(&interface.name)),
+            );
+        }
+        readings
+    }
+
+    fn update_stats(&self, stats: &mut SystemStats) {
+        stats.network = self.latest.network.clone();
+    }
+
+    fn tray_segment(&self, _options: &TrayOptions, _history: Option<&History>) -> Option<String> {
+        Some(format_network_segment(&self.latest))
+    }
+
+    fn menu_entries(&self, _options: &TrayOptions, history: &History) -> Vec<MenuEntry> {
+        let network = &self.latest.network;
+        let rates = |received, transmitted| {
+            format!("↓ {} ↑ {}", format_rate(received), format_rate(transmitted))
+        };
+        let interfaces = network
+            .interfaces
+            .iter()
+            .map(|interface| {
+                MenuEntry::new(
+                    format!("if_{}", interface.name),
+                    format_network_interface_text(interface),
+                    Some(rates(
+                        interface.received_per_sec,
+                        interface.transmitted_per_sec,
+                    )),
+                )
+            })
+            .collect();
+        vec![
+            MenuEntry::new(
+                "network",
+                with_summary(format_network_text(&self.latest), "↓ ", history, "net.rx"),
+                Some(rates(network.received_per_sec, network.transmitted_per_sec)),
+            ),
+            MenuEntry::submenu("interfaces", "Interfaces", interfaces),
+        ]
+    }
+}
+
+#[derive(Default)]
+struct ProcessCollector {
+    latest: SystemStats,
+}
+
+impl Collector for ProcessCollector {
+    fn name(&self) -> &str {
+        "processes"
+    }
+
+    fn refresh(&mut self, system: &System) {
+        let processes = collect_processes(system);
+        self.latest = SystemStats {
+            top_cpu_processes: top_processes(&processes, TOP_PROCESS_COUNT, |a, b| {
+                b.cpu_usage.total_cmp(&a.cpu_usage)
+            }),
+            top_memory_processes: top_processes(&processes, TOP_PROCESS_COUNT, |a, b| {
+                b.memory.cmp(&a.memory)
+            }),
+            ..Default::default()
+        };
+    }
+
+    fn readings(&self) -> Vec<Reading> {
+        let instance = process_instance;
+        let cpu = self.latest.top_cpu_processes.iter().map(|process| {
+            Reading::new(
+                "processes.cpu_usage",
+                process.cpu_usage as f64,
+                Unit::Percent,
+            )
+            .with_instance(instance(process))
+        });
+        let memory = self.latest.top_memory_processes.iter().map(|process| {
+            Reading::new("processes.memory", process.memory as f64, Unit::Bytes)
+                .with_instance(instance(process))
+        });
+        cpu.chain(memory).collect()
+    }
+
+    fn update_stats(&self, stats: &mut SystemStats) {
+        stats.top_cpu_processes = self.latest.top_cpu_processes.clone();
+        stats.top_memory_processes = self.latest.top_memory_processes.clone();
+    }
+
+    /// None: the app builds the top process submenus itself, with their signal actions.
+    fn menu_entries(&self, _options: &TrayOptions, _history: &History) -> Vec<MenuEntry> {
+        Vec::new()
+    }
+}
+
+struct TemperatureCollector {
+    components: Components,
+    last_scan: Instant,
+    /// The highest temperature seen per sensor label since the app started.
+    max_by_label: HashMap<String, f32>,
+    latest: SystemStats,
+}
+
+impl TemperatureCollector {
+    fn new() -> Self {
+        Self {
+            components: Components::new_with_refreshed_list(),
+            last_scan: Instant::now(),
+            max_by_label: HashMap::new(),
+            latest: SystemStats::default(),
+        }
+    }
+}
+
+impl Collector for TemperatureCollector {
+    fn name(&self) -> &str {
+        "temperatures"
+    }
+
+    fn refresh(&mut self, _system: &System) {
+        if self.last_scan.elapsed() >= Duration::from_secs(TEMPERATURE_RESCAN_INTERVAL_SECS) {
+            self.components.refresh_list();
+            self.last_scan = Instant::now();
+        } else {
+            self.components.refresh();
+        }
+
+        let mut temperatures = collect_temperatures(&self.components);
+        track_temperature_max(&mut temperatures, &mut self.max_by_label);
+        self.latest = SystemStats {
+            temperatures,
+            ..Default::default()
+        };
+    }
+
+    fn readings(&self) -> Vec<Reading> {
+        let hottest = self.latest.hottest_temperature().map(|hottest| {
+            Reading::new(
+                "temperatures.current",
+                hottest.current as f64,
+                Unit::Celsius,
+            )
+        });
+        let sensors = self.latest.temperatures.iter().map(|temperature| {
+            Reading::new(
+                "temperatures.current",
+                temperature.current as f64,
+                Unit::Celsius,
+            )
+            .with_instance(&temperature.label)
+        });
+        hottest.into_iter().chain(sensors).collect()
+    }
+
+    fn update_stats(&self, stats: &mut SystemStats) {
+        stats.temperatures = self.latest.temperatures.clone();
+    }
+
+    fn tray_segment(&self, options: &TrayOptions, _history: Option<&History>) -> Option<String> {
+        format_temperature_segment(&self.latest, options)
+    }
+
+    /// A submenu of sensors, left out on machines without readable sensors.
+    fn menu_entries(&self, options: &TrayOptions, _history: &History) -> Vec<MenuEntry> {
+        if self.latest.temperatures.is_empty() {
+            return Vec::new();
+        }
+        let sensors = self
+            .latest
+            .temperatures
+            .iter()
+            .map(|temperature| {
+                MenuEntry::new(
+                    format!("sensor_{}", temperature.label),
+                    format_temperature_text(temperature, options.temperature_unit),
+                    Some(format_temperature_value(
+                        temperature.current,
+                        options.temperature_unit,
+                    )),
+                )
+            })
+            .collect();
+        vec![MenuEntry::submenu("sensors", "Temperatures", sensors)]
+    }
+}
+
+/// The instance of a process's readings, e.g. `cargo (42)`; the name alone is not unique.
+fn process_instance(process: &ProcessStats) -> String {
+    format!("{} ({})", process.name, process.pid)
+}
+
+/// The name and pid in the instance of a process reading.
+pub fn parse_process_instance(instance: &str) -> Option<(&str, u32)> {
+    let (name, pid) = instance.strip_suffix(')')?.rsplit_once(" (")?;
+    Some((name, pid.parse().ok()?))
+}
+
+fn collect_temperatures(components: &Components) -> Vec<TemperatureStats> {
+    let mut temperatures: Vec<TemperatureStats> = components
+        .iter()
+        .filter(|component| is_readable_temperature(component.temperature()))
+        .map(|component| TemperatureStats {
+            label: component.label().to_string(),
+            current: component.temperature(),
+            max: component.max().max(component.temperature()),
+            critical: component
+                .critical()
+                .filter(|critical| is_readable_temperature(*critical)),
+        })
+        .collect();
+    temperatures.sort_by(|a, b| a.label.cmp(&b.label));
+    temperatures.dedup_by(|a, b| a.label == b.label);
+
+    temperatures
+}
+
+/// Raises every sensor's `max` to the highest temperature `maxes` has seen for its label, and
+/// records the new sample in `maxes`.
+fn track_temperature_max(temperatures: &mut [TemperatureStats], maxes: &mut HashMap<String, f32>) {
+    for temperature in temperatures {
+        let max = maxes
+            .entry(temperature.label.clone())
+            .or_insert(temperature.max);
+        *max = max.max(temperature.max).max(temperature.current);
+        temperature.max = *max;
+    }
+}
+
+fn is_readable_temperature(celsius: f32) -> bool {
+    celsius.is_finite() && celsius > 0.0
+}
+
+fn collect_processes(sys: &System) -> Vec<ProcessStats> {
+    sys.processes()
+        .iter()
+        .map(|(pid, process)| ProcessStats {
+            pid: pid.as_u32(),
+            name: process.name().to_string(),
+            cpu_usage: process.cpu_usage().max(0.0),
+            memory: process.memory(),
+        })
+        .collect()
+}
+
+fn top_processes<F>(processes: &[ProcessStats], count: usize, compare: F) -> Vec<ProcessStats>
+where
+    F: Fn(&ProcessStats, &ProcessStats) -> std::cmp::Ordering,
+{
+    let mut ranked = processes.to_vec();
+    ranked.sort_by(|a, b| compare(a, b).then_with(|| a.pid.cmp(&b.pid)));
+    ranked.truncate(count);
+    ranked
+}
+
+fn collect_disk_stats(sys: &System, collector: &mut DiskCollector) -> DiskStats {
+    collector.disks.refresh_list();
+
+    let now = Instant::now();
+    let elapsed = now.duration_since(collector.last_refresh).as_secs_f64();
+    collector.last_refresh = now;
+
+    let (read, written) = sys
+        .processes()
+        .values()
+        .map(|process| process.disk_usage())
+        .fold((0u64, 0u64), |(read, written), usage| {
+            (
+                read.saturating_add(usage.read_bytes),
+                written.saturating_add(usage.written_bytes),
+            )
+        });
+
+    let mut volumes: Vec<DiskVolumeStats> = collector
+        .disks
+        .iter()
+        .filter_map(|disk| {
+            let file_system = disk.file_system().to_string_lossy().into_owned();
+            let mount_point = disk.mount_point().to_string_lossy().into_owned();
+            if !collector.filter.includes(&file_system, &mount_point) {
+                return None;
+            }
+
+            let total = disk.total_space();
+            let used = total - disk.available_space().min(total);
+
+            Some(DiskVolumeStats {
+                mount_point,
+                file_system,
+                used,
+                total,
+                percent: usage_percent(used, total),
+            })
+        })
+        .collect();
+    volumes.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+
+    DiskStats {
+        read_per_sec: bytes_per_sec(read, elapsed),
+        written_per_sec: bytes_per_sec(written, elapsed),
+        volumes,
+    }
+}
+
+fn usage_percent(used: u64, total: u64) -> f32 {
+    if total > 0 {
+        ((used as f64 / total as f64) * 100.0).clamp(0.0, 100.0) as f32
+    } else {
+        0.0
+    }
+}
+
+fn collect_network_stats(collector: &mut NetworkCollector) -> NetworkStats {
+    collector.networks.refresh_list();
+
+    let now = Instant::now();
+    let elapsed = now.duration_since(collector.last_refresh).as_secs_f64();
+    collector.last_refresh = now;
+
+    let mut interfaces: Vec<NetworkInterfaceStats> = collector
+        .networks
+        .iter()
+        .map(|(name, data)| {
+            let totals = collector.totals.entry(name.clone()).or_default();
+            totals.0 = totals.0.saturating_add(data.received());
+            totals.1 = totals.1.saturating_add(data.transmitted());
+
+            NetworkInterfaceStats {
+                name: name.clone(),
+                received_per_sec: bytes_per_sec(data.received(), elapsed),
+                transmitted_per_sec: bytes_per_sec(data.transmitted(), elapsed),
+                total_received: totals.0,
+                total_transmitted: totals.1,
+            }
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let external = interfaces
+        .iter()
+        .filter(|interface| !is_loopback_interface(&interface.name));
+
+    NetworkStats {
+        received_per_sec: external.clone().map(|i| i.received_per_sec).sum(),
+        transmitted_per_sec: external.map(|i| i.transmitted_per_sec).sum(),
+        interfaces,
+    }
+}
+
+fn bytes_per_sec(bytes: u64, elapsed_secs: f64) -> u64 {
+    if elapsed_secs > 0.0 {
+        (bytes as f64 / elapsed_secs).round() as u64
+    } else {
+        0
+    }
+}
+
+fn is_loopback_interface(name: &str) -> bool {
+    name == "lo" || name.starts_with("lo0")
+}
+
+/// Refreshes the cached battery handles in place and only re-enumerates batteries
+/// periodically, or straight away when a handle stops refreshing (e.g. a pack was unplugged).
+fn collect_battery_stats(collector: &mut BatteryCollector) -> (f32, String, Vec<BatteryStats>) {
+    let mut errors = Vec::new();
+    let batteries = refresh_batteries(collector, &mut errors);
+
+    let line = if errors.is_empty() {
+        collector.errors.clear()
+    } else {
+        collector.errors.record(errors.join("; "))
+    };
+    if let Some(line) = line {
+        eprintln!("{}", line);
+    }
+
+    match batteries {
+        Some(batteries) => {
+            let (percent, state) = aggregate_batteries(&batteries);
+            (percent, state, batteries)
+        }
+        None => (0.0, "Unknown".to_string(), Vec::new()),
+    }
+}
+
+fn refresh_batteries(
+    collector: &mut BatteryCollector,
+    errors: &mut Vec<String>,
+) -> Option<Vec<BatteryStats>> {
+    if collector.manager.is_none() {
+        match starship_battery::Manager::new() {
+            Ok(manager) => collector.manager = Some(manager),
+            Err(e) => {
+                errors.push(format!("Failed to create battery manager: {}", e));
+                return None;
+            }
+        }
+    }
+    let manager = collector.manager.as_ref()?;
+
+    let mut rescan = collector
+        .last_scan
+        .is_none_or(|last| last.elapsed() >= Duration::from_secs(BATTERY_RESCAN_INTERVAL_SECS));
+    for battery in &mut collector.batteries {
+        if manager.refresh(battery).is_err() {
+            rescan = true;
+        }
+    }
+
+    if rescan {
+        match manager.batteries() {
+            Ok(found) => {
+                let found: Vec<starship_battery::Battery> = found
+                    .enumerate()
+                    .filter_map(|(index, battery)| match battery {
+                        Ok(battery) => Some(battery),
+                        Err(e) => {
+                            errors.push(format!("Failed to read battery {}: {}", index + 1, e));
+                            None
+                        }
+                    })
+                    .collect();
+
+                if collector.last_scan.is_some() {
+                    let previous: Vec<String> =
+                        collector.batteries.iter().map(battery_identity).collect();
+                    let current: Vec<String> = found.iter().map(battery_identity).collect();
+                    let (added, removed) = battery_changes(&previous, &current);
+                    for battery in added {
+                        eprintln!("Battery added: {}", battery);
+                    }
+                    for battery in removed {
+                        eprintln!("Battery removed: {}", battery);
+                    }
+                }
+
+                collector.batteries = found;
+                collector.last_scan = Some(Instant::now());
+            }
+            Err(e) => {
+                errors.push(format!("Failed to get batteries: {}", e));
+                return None;
+            }
+        }
+    }
+
+    Some(
+        collector
+            .batteries
+            .iter()
+            .enumerate()
+            .map(|(index, battery)| get_battery_stats(index, battery))
+            .collect(),
+    )
+}
+
+fn battery_identity(battery: &starship_battery::Battery) -> String {
+    let identity = [battery.vendor(), battery.model(), battery.serial_number()]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if identity.is_empty() {
+        "unnamed battery".to_string()
+    } else {
+        identity
+    }
+}
+
+/// Returns the batteries that appeared and disappeared between two scans, compared by identity.
+fn battery_changes<'a>(
+    previous: &'a [String],
+    current: &'a [String],
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let count = |list: &[String], id: &str| list.iter().filter(|other| *other == id).count();
+    let surplus = |from: &'a [String], other: &[String]| {
+        let mut seen: Vec<&str> = Vec::new();
+        let mut changed = Vec::new();
+        for id in from {
+            if seen.contains(&id.as_str()) {
+                continue;
+            }
+            seen.push(id);
+            let extra = count(from, id).saturating_sub(count(other, id));
+            changed.extend(std::iter::repeat_n(id.as_str(), extra));
+        }
+        changed
+    };
+
+    (surplus(current, previous), surplus(previous, current))
+}
+
+fn get_battery_stats(index: usize, battery: &starship_battery::Battery) -> BatteryStats {
+    let model = [battery.vendor(), battery.model()]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let name = if model.is_empty() {
+        format!("Battery {}", index + 1)
+    } else {
+        format!("Battery {} ({})", index + 1, model)
+    };
+
+    BatteryStats {
+        name,
+        percent: (battery.state_of_charge().value * 100.0).clamp(0.0, 100.0),
+        state: format_battery_state(battery.state()),
+        details: get_battery_details(battery),
+    }
+}
+
+/// Combines all batteries into a single charge, weighted by each battery's full energy so a
+/// small auxiliary pack does not skew the figure.
+pub fn aggregate_batteries(batteries: &[BatteryStats]) -> (f32, String) {
+    if batteries.is_empty() {
+        return (0.0, "No Battery".to_string());
+    }
+
+    let energy: f32 = batteries.iter().map(|b| b.details.energy_wh).sum();
+    let energy_full: f32 = batteries.iter().map(|b| b.details.energy_full_wh).sum();
+    let percent = if energy_full > 0.0 {
+        energy / energy_full * 100.0
+    } else {
+        batteries.iter().map(|b| b.percent).sum::<f32>() / batteries.len() as f32
+    };
+
+    let any_in = |state: &str| batteries.iter().any(|b| b.state == state);
+    let all_in = |state: &str| batteries.iter().all(|b| b.state == state);
+    let state = if any_in("Discharging") {
+        "Discharging"
+    } else if any_in("Charging") {
+        "Charging"
+    } else if all_in("Full") {
+        "Full"
+    } else if all_in("Empty") {
+        "Empty"
+    } else {
+        "Unknown"
+    };
+
+    (percent.clamp(0.0, 100.0), state.to_string())
+}
+
+pub fn aggregate_time_to_empty(batteries: &[BatteryStats]) -> Option<u64> {
+    if let [battery] = batteries {
+        return battery.details.time_to_empty_secs;
+    }
+
+    let discharging = batteries.iter().filter(|b| b.state == "Discharging");
+    let power: f32 = discharging.clone().map(|b| b.details.power_watts).sum();
+    let energy: f32 = batteries.iter().map(|b| b.details.energy_wh).sum();
+
+    (power > 0.0).then(|| (energy / power * 3_600.0).round() as u64)
+}
+
+fn get_battery_details(battery: &starship_battery::Battery) -> BatteryDetails {
+    let finite = |value: f32| if value.is_finite() { value } else { 0.0 };
+    let duration_secs = |value: starship_battery::units::Time| {
+        let secs = value.get::<time::second>();
+        (secs.is_finite() && secs > 0.0).then_some(secs.round() as u64)
+    };
+
+    BatteryDetails {
+        energy_wh: finite(battery.energy().get::<energy::watt_hour>()),
+        energy_full_wh: finite(battery.energy_full().get::<energy::watt_hour>()),
+        energy_full_design_wh: finite(battery.energy_full_design().get::<energy::watt_hour>()),
+        health_percent: finite(battery.state_of_health().get::<ratio::percent>()).max(0.0),
+        power_watts: finite(battery.energy_rate().get::<power::watt>()).abs(),
+        voltage: finite(battery.voltage().get::<electric_potential::volt>()),
+        temperature_celsius: battery
+            .temperature()
+            .map(|temperature| temperature.get::<thermodynamic_temperature::degree_celsius>())
+            .filter(|celsius| celsius.is_finite()),
+        cycle_count: battery.cycle_count(),
+        time_to_full_secs: battery.time_to_full().and_then(duration_secs),
+        time_to_empty_secs: battery.time_to_empty().and_then(duration_secs),
+    }
+}
+
+fn format_battery_state(state: BatteryState) -> String {
+    match state {
+        BatteryState::Charging => "Charging".to_string(),
+        BatteryState::Discharging => "Discharging".to_string(),
+        BatteryState::Full => "Full".to_string(),
+        BatteryState::Empty => "Empty".to_string(),
+        _ => "Unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::TemperatureUnit;
+    use crate::registry::Registry;
+
+    #[test]
+    fn test_format_battery_state() {
+        assert_eq!(format_battery_state(BatteryState::Charging), "Charging");
+        assert_eq!(
+            format_battery_state(BatteryState::Discharging),
+            "Discharging"
+        );
+        assert_eq!(format_battery_state(BatteryState::Full), "Full");
+        assert_eq!(format_battery_state(BatteryState::Empty), "Empty");
+
+        assert_eq!(format_battery_state(BatteryState::Unknown), "Unknown");
+    }
+
+    #[test]
+    fn test_track_temperature_max() {
+        let sample = |current: f32| {
+            vec![TemperatureStats {
+                label: "CPU".to_string(),
+                current,
+                max: current,
+                critical: None,
+            }]
+        };
+        let mut maxes = HashMap::new();
+
+        let mut first = sample(80.0);
+        track_temperature_max(&mut first, &mut maxes);
+        assert_eq!(first[0].max, 80.0);
+
+        let mut second = sample(55.0);
+        track_temperature_max(&mut second, &mut maxes);
+        assert_eq!(second[0].current, 55.0);
+        assert_eq!(second[0].max, 80.0);
+
+        let mut third = sample(90.0);
+        track_temperature_max(&mut third, &mut maxes);
+        assert_eq!(third[0].max, 90.0);
+    }
+
+    #[test]
+    fn test_is_readable_temperature() {
+        assert!(is_readable_temperature(45.0));
+        assert!(!is_readable_temperature(0.0));
+        assert!(!is_readable_temperature(-273.0));
+        assert!(!is_readable_temperature(f32::NAN));
+        assert!(!is_readable_temperature(f32::INFINITY));
+    }
+
+    fn battery(percent: f32, state: &str, energy_wh: f32, energy_full_wh: f32) -> BatteryStats {
+        BatteryStats {
+            name: "Battery".to_string(),
+            percent,
+            state: state.to_string(),
+            details: BatteryDetails {
+                energy_wh,
+                energy_full_wh,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_error_log_deduplicates_repeats() {
+        let mut log = ErrorLog::default();
+        assert_eq!(log.clear(), None);
+
+        assert_eq!(
+            log.record("Failed to get batteries: gone".to_string()),
+            Some("Failed to get batteries: gone".to_string())
+        );
+        for _ in 2..100 {
+            assert_eq!(
+                log.record("Failed to get batteries: gone".to_string()),
+                None
+            );
+        }
+        assert_eq!(
+            log.record("Failed to get batteries: gone".to_string()),
+            Some("Failed to get batteries: gone (repeated 100 times)".to_string())
+        );
+
+        assert_eq!(
+            log.record("Failed to read battery 1: busy".to_string()),
+            Some("Failed to read battery 1: busy (previous error repeated 100 times)".to_string())
+        );
+        assert_eq!(
+            log.record("Failed to read battery 1: busy".to_string()),
+            None
+        );
+        assert_eq!(
+            log.clear(),
+            Some("Recovered after 2 failed attempts: Failed to read battery 1: busy".to_string())
+        );
+        assert_eq!(log.clear(), None);
+    }
+
+    #[test]
+    fn test_battery_changes() {
+        let ids = |list: &[&str]| list.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        let internal = ids(&["SMP bq20z451 A1"]);
+        let docked = ids(&["SMP bq20z451 A1", "ACME Pack B2"]);
+        assert_eq!(
+            battery_changes(&internal, &docked),
+            (vec!["ACME Pack B2"], vec![])
+        );
+        assert_eq!(
+            battery_changes(&docked, &internal),
+            (vec![], vec!["ACME Pack B2"])
+        );
+        assert_eq!(battery_changes(&docked, &docked), (vec![], vec![]));
+
+        let unnamed = ids(&["unnamed battery"]);
+        let two_unnamed = ids(&["unnamed battery", "unnamed battery"]);
+        assert_eq!(
+            battery_changes(&unnamed, &two_unnamed),
+            (vec!["unnamed battery"], vec![])
+        );
+    }
+
+    #[test]
+    fn test_battery_collector_reuses_manager() {
+        assert_eq!(BATTERY_RESCAN_INTERVAL_SECS, 30);
+
+        let mut collector = BatteryCollector::new();
+        let first = collect_battery_stats(&mut collector);
+        let second = collect_battery_stats(&mut collector);
+
+        assert_eq!(first.2.len(), second.2.len());
+        if collector.manager.is_some() {
+            assert!(collector.last_scan.is_some());
+            assert_eq!(collector.batteries.len(), second.2.len());
+        }
+    }
+
+    #[test]
+    fn test_aggregate_batteries() {
+        assert_eq!(aggregate_batteries(&[]), (0.0, "No Battery".to_string()));
+
+        let single = [battery(75.0, "Full", 45.0, 60.0)];
+        assert_eq!(aggregate_batteries(&single), (75.0, "Full".to_string()));
+
+        let internal_and_pack = [
+            battery(90.0, "Charging", 90.0, 100.0),
+            battery(10.0, "Discharging", 2.0, 20.0),
+        ];
+        let (percent, state) = aggregate_batteries(&internal_and_pack);
+        assert!((percent - 76.666_67).abs() < 0.01);
+        assert_eq!(state, "Discharging");
+
+        let no_energy = [
+            battery(40.0, "Charging", 0.0, 0.0),
+            battery(60.0, "Full", 0.0, 0.0),
+        ];
+        assert_eq!(
+            aggregate_batteries(&no_energy),
+            (50.0, "Charging".to_string())
+        );
+
+        let mixed = [
+            battery(100.0, "Full", 1.0, 1.0),
+            battery(0.0, "Empty", 0.0, 1.0),
+        ];
+        assert_eq!(aggregate_batteries(&mixed).1, "Unknown");
+    }
+
+    #[test]
+    fn test_aggregate_time_to_empty() {
+        assert_eq!(aggregate_time_to_empty(&[]), None);
+
+        let mut first = battery(50.0, "Discharging", 30.0, 60.0);
+        first.details.power_watts = 10.0;
+        first.details.time_to_empty_secs = Some(1_234);
+        assert_eq!(aggregate_time_to_empty(&[first.clone()]), Some(1_234));
+
+        let second = battery(50.0, "Full", 30.0, 60.0);
+        assert_eq!(
+            aggregate_time_to_empty(&[first.clone(), second.clone()]),
+            Some(6 * 3_600)
+        );
+
+        first.state = "Charging".to_string();
+        assert_eq!(aggregate_time_to_empty(&[first, second]), None);
+    }
+
+    #[test]
+    fn test_battery_unit_entries() {
+        let empty = SystemStats {
+            battery_state: "No Battery".to_string(),
+            ..Default::default()
+        };
+        let entries = battery_unit_entries(&empty);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].text, "No Battery");
+        assert_eq!(entries[0].copy_text, None);
+
+        let stats = SystemStats {
+            batteries: vec![
+                battery(90.0, "Charging", 90.0, 100.0),
+                battery(10.0, "Discharging", 2.0, 20.0),
+            ],
+            ..Default::default()
+        };
+        let entries = battery_unit_entries(&stats);
+        assert_eq!(entries.len(), 12);
+        assert_eq!(entries[0].id, "unit_0");
+        assert_eq!(entries[0].copy_text.as_deref(), Some("90%"));
+        assert_eq!(entries[6].id, "unit_1");
+        assert_eq!(entries[7].id, "unit_1_detail_0");
+        assert_eq!(entries[7].copy_text, None);
+    }
+
+    #[test]
+    fn test_builtin_menu_entries() {
+        let mut registry = Registry::with_builtin_collectors();
+        registry.refresh();
+        let stats = registry.stats();
+        let entries = registry.menu_entries(&TrayOptions::default());
+
+        let ids: Vec<&str> = entries.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(
+            ids[..10],
+            [
+                "battery/battery",
+                "battery/units",
+                "cpu/usage",
+                "cpu/load",
+                "cpu/cores",
+                "memory/memory",
+                "memory/details",
+                "disk/disk",
+                "disk/volumes",
+                "network/network",
+            ]
+        );
+        assert_eq!(ids[10], "network/interfaces");
+        assert!(ids.len() == 11 || ids[11] == "temperatures/sensors");
+        assert_eq!(entries[4].children.len(), stats.cpu_cores.len());
+        assert_eq!(
+            entries[2].copy_text,
+            Some(format!("{:.1}%", stats.cpu_usage))
+        );
+        assert!(entries[6]
+            .children
+            .iter()
+            .all(|entry| entry.copy_text.is_none()));
+    }
+
+    #[test]
+    fn test_temperature_menu_entries() {
+        let mut collector = TemperatureCollector::new();
+        collector.latest.temperatures = vec![TemperatureStats {
+            label: "CPU die".to_string(),
+            current: 50.0,
+            max: 60.0,
+            critical: None,
+        }];
+        let options = TrayOptions {
+            temperature_unit: TemperatureUnit::Fahrenheit,
+            ..Default::default()
+        };
+
+        let entries = collector.menu_entries(&options, &History::new(1));
+        let sensor = &entries[0].children[0];
+        assert_eq!(sensor.id, "sensor_CPU die");
+        assert_eq!(sensor.text, "CPU die: 122.0°F (max 140.0°F)");
+        assert_eq!(sensor.copy_text.as_deref(), Some("122.0°F"));
+    }
+
+    #[test]
+    fn test_top_processes() {
+        let process = |pid: u32, cpu_usage: f32, memory: u64| ProcessStats {
+            pid,
+            name: format!("proc{}", pid),
+            cpu_usage,
+            memory,
+        };
+        let processes = vec![
+            process(1, 5.0, 300),
+            process(2, 80.0, 100),
+            process(3, 20.0, 900),
+            process(4, 80.0, 200),
+        ];
+
+        let by_cpu = top_processes(&processes, 3, |a, b| b.cpu_usage.total_cmp(&a.cpu_usage));
+        let pids: Vec<u32> = by_cpu.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, vec![2, 4, 3]);
+
+        let by_memory = top_processes(&processes, 2, |a, b| b.memory.cmp(&a.memory));
+        let pids: Vec<u32> = by_memory.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, vec![3, 1]);
+
+        assert!(top_processes(&[], 5, |a, b| a.memory.cmp(&b.memory)).is_empty());
+    }
+
+    #[test]
+    fn test_memory_pressure_level() {
+        let thresholds = MemoryPressureThresholds::default();
+
+        assert_eq!(thresholds.level(50.0, 0), MemoryPressure::Normal);
+        assert_eq!(thresholds.level(20.0, 0), MemoryPressure::Warning);
+        assert_eq!(thresholds.level(5.0, 0), MemoryPressure::Critical);
+
+        assert_eq!(
+            thresholds.level(50.0, 2 * 1024 * 1024),
+            MemoryPressure::Warning
+        );
+        assert_eq!(
+            thresholds.level(50.0, 32 * 1024 * 1024),
+            MemoryPressure::Critical
+        );
+        assert_eq!(
+            thresholds.level(15.0, 32 * 1024 * 1024),
+            MemoryPressure::Critical
+        );
+
+        let strict = MemoryPressureThresholds {
+            warning_available_percent: 50.0,
+            critical_available_percent: 30.0,
+            warning_swap_growth_per_sec: u64::MAX,
+            critical_swap_growth_per_sec: u64::MAX,
+        };
+        assert_eq!(strict.level(40.0, 0), MemoryPressure::Warning);
+        assert_eq!(strict.level(40.0, u64::MAX - 1), MemoryPressure::Warning);
+    }
+
+    #[test]
+    fn test_bytes_per_sec() {
+        assert_eq!(bytes_per_sec(3000, 3.0), 1000);
+        assert_eq!(bytes_per_sec(1000, 0.5), 2000);
+        assert_eq!(bytes_per_sec(1000, 0.0), 0);
+    }
+
+    #[test]
+    fn test_is_loopback_interface() {
+        assert!(is_loopback_interface("lo"));
+        assert!(is_loopback_interface("lo0"));
+        assert!(!is_loopback_interface("en0"));
+        assert!(!is_loopback_interface("eth0"));
+    }
+
+    #[test]
+    fn test_usage_percent() {
+        assert_eq!(usage_percent(0, 0), 0.0);
+        assert_eq!(usage_percent(50, 100), 50.0);
+        assert_eq!(usage_percent(200, 100), 100.0);
+        assert_eq!(usage_percent(u64::MAX, u64::MAX), 100.0);
+    }
+
+    #[test]
+    fn test_disk_filter() {
+        let filter = DiskFilter::default();
+        assert!(filter.includes("apfs", "/"));
+        assert!(filter.includes("ext4", "/home"));
+        assert!(!filter.includes("tmpfs", "/run"));
+        assert!(!filter.includes("devfs", "/dev"));
+        assert!(!filter.includes("overlay", "/"));
+        assert!(!filter.includes("TMPFS", "/tmp"));
+
+        let custom = DiskFilter {
+            excluded_file_systems: vec!["nfs".to_string()],
+            excluded_mount_points: vec!["/System/Volumes/".to_string()],
+        };
+        assert!(custom.includes("tmpfs", "/tmp"));
+        assert!(!custom.includes("nfs", "/mnt/share"));
+        assert!(!custom.includes("apfs", "/System/Volumes/VM"));
+        assert!(custom.includes("apfs", "/"));
+
+        let mut collector = DiskCollector::new(DiskFilter::default());
+        let config = Config {
+            disk_filter: custom.clone(),
+            ..Default::default()
+        };
+        collector.configure(&config);
+        assert_eq!(collector.filter, custom);
+    }
+
+    #[test]
+    fn test_collect_system_stats_validation() {
+        let mut registry = Registry::with_builtin_collectors();
+        registry.refresh();
+
+        let stats = registry.stats();
+        let sys = registry.system();
+
+        assert!(stats.cpu_usage >= 0.0 && stats.cpu_usage <= 100.0);
+        assert!(stats.load_average_one >= 0.0);
+        assert!(stats.load_average_five >= 0.0);
+        assert!(stats.load_average_fifteen >= 0.0);
+        assert!(stats.boot_time > 0);
+        assert!(stats
+            .temperatures
+            .iter()
+            .all(|temperature| is_readable_temperature(temperature.current)
+                && temperature.max >= temperature.current));
+        assert_eq!(stats.cpu_cores.len(), sys.cpus().len());
+        assert!(stats
+            .cpu_cores
+            .iter()
+            .all(|usage| *usage >= 0.0 && *usage <= 100.0));
+        assert!(stats.memory_percent >= 0.0 && stats.memory_percent <= 100.0);
+        assert!(stats.battery_percent >= 0.0 && stats.battery_percent <= 100.0);
+        for battery in &stats.batteries {
+            assert!(battery.percent >= 0.0 && battery.percent <= 100.0);
+            assert!(battery.details.health_percent >= 0.0);
+            assert!(battery.details.power_watts >= 0.0);
+        }
+        assert!(stats.memory_used <= stats.memory_total);
+        assert_eq!(
+            stats.memory_used + stats.memory_available,
+            stats.memory_total
+        );
+        assert_eq!(
+            stats.memory_free + stats.memory_reclaimable,
+            stats.memory_available
+        );
+        assert!(stats.swap_used <= stats.swap_total);
+        assert!(stats.top_cpu_processes.len() <= TOP_PROCESS_COUNT);
+        assert!(stats.top_memory_processes.len() <= TOP_PROCESS_COUNT);
+        assert!(!stats.top_memory_processes.is_empty());
+        assert!(stats
+            .top_memory_processes
+            .windows(2)
+            .all(|pair| pair[0].memory >= pair[1].memory));
+        assert!(stats.swap_percent >= 0.0 && stats.swap_percent <= 100.0);
+
+        let mut memory = MemoryCollector::new(MemoryPressureThresholds::default());
+        memory.refresh(sys);
+        assert_eq!(memory.last_swap_used, Some(memory.latest.swap_used));
+        assert!(stats
+            .network
+            .interfaces
+            .windows(2)
+            .all(|pair| pair[0].name <= pair[1].name));
+        assert!(stats.disk.volumes.iter().all(|volume| {
+            volume.used <= volume.total
+                && volume.percent >= 0.0
+                && volume.percent <= 100.0
+                && DiskFilter::default().includes(&volume.file_system, &volume.mount_point)
+        }));
+
+        assert!(stats.memory_total > 0);
+
+        assert!(
+            stats.battery_state == "Charging"
+                || stats.battery_state == "Discharging"
+                || stats.battery_state == "Full"
+                || stats.battery_state == "Empty"
+                || stats.battery_state == "Unknown"
+                || stats.battery_state == "No Battery"
+        );
+    }
+}
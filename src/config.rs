@@ -0,0 +1,1166 @@
+//! The optional TOML configuration file.
+//!
+//! ```toml
+//! refresh_interval_secs = 3
+//! history_size = 60              # samples kept for sparklines and min/avg/max
+//!
+//! [tray]
+//! segments = ["battery", "cpu", "memory", "network", "temperatures"]
+//! sparklines = []                # segments drawn as sparklines, e.g. ["cpu", "memory"]
+//! sparkline_length = 8
+//! cpu = "usage"            # or "load_average"
+//! show_temperature = false
+//! # Replaces `segments` when set, see `template` for the syntax.
+//! template = "{battery.bar} {battery.pct}% {?cpu.pct > 50}🔥{/}"
+//!
+//! [format]
+//! precision = 0
+//! temperature_unit = "celsius"   # or "fahrenheit"
+//!
+//! [memory]
+//! warning_available_percent = 20
+//! critical_available_percent = 10
+//! warning_swap_growth_per_sec = 1048576
+//! critical_swap_growth_per_sec = 16777216
+//!
+//! [disk]
+//! # File system types and mount point prefixes left out of the volume list. The file system
+//! # list replaces the defaults (tmpfs, devfs, overlay, proc, ...) when set.
+//! exclude_file_systems = ["tmpfs", "devfs", "nfs"]
+//! exclude_mount_points = ["/System/Volumes/"]
+//!
+//! [battery]
+//! low_percent = 20               # warn while discharging, 0 turns it off
+//! charge_limit_percent = 80      # remind to unplug while charging, 0 turns it off
+//! notify_state_changes = true
+//!
+//! [storage]
+//! enabled = true                 # keep samples on disk, see `store`
+//! max_size_mb = 64
+//!
+//! [metrics]
+//! enabled = false                # serve Prometheus metrics on localhost, see `metrics`
+//! port = 9851
+//!
+//! # Replaces the default alerts when present, see `alert`. `alerts = []` turns them off.
+//! [[alerts]]
+//! name = "CPU busy"
+//! metric = "cpu.pct"             # any numeric template placeholder
+//! above = 90                     # or `below`
+//! for_secs = 60
+//! hysteresis = 10                # defaults to 5% of the threshold
+//! cooldown_secs = 600
+//!
+//! [[alerts]]
+//! name = "Busy on battery"
+//! when = 'cpu > 85 and battery.state == "Discharging" for 2m'   # see `rule`
+//!
+//! # Shell commands run on events, see `hook`. Each needs `on_alert`, `on_battery` or both.
+//! [[hooks]]
+//! name = "Pause sync"
+//! command = "syncctl pause"
+//! on_battery = "Discharging"     # the state changed into, e.g. "Charging" or "Full"
+//! on_alert = "Busy on battery"   # an alert rule name, or "*" for every alert
+//! timeout_secs = 30
+//! max_concurrent = 1             # overlapping runs of this hook, the rest are skipped
+//! enabled = true                 # the initial state of its toggle in the tray menu
+//! ```
+//!
+//! Every key is optional. Invalid values are reported as [`ConfigError`]s and replaced by their
+//! defaults, so a broken file never stops the app from starting. A file that does not parse at
+//! all yields a single fatal error.
+
+use crate::alert::{
+    default_alert_rules, AlertCondition, AlertRule, Comparison, DEFAULT_ALERT_COOLDOWN_SECS,
+    DEFAULT_HYSTERESIS_RATIO,
+};
+use crate::battery::{BatteryPolicy, BATTERY_STATES};
+use crate::collect::{DiskFilter, MemoryPressureThresholds};
+use crate::format::{CpuTraySegment, TemperatureUnit, TrayOptions};
+use crate::history::DEFAULT_HISTORY_SIZE;
+use crate::hook::{
+    Hook, HookTrigger, DEFAULT_HOOK_TIMEOUT_SECS, MAX_HOOK_TIMEOUT_SECS, MAX_RUNNING_HOOKS,
+};
+use crate::metrics::DEFAULT_METRICS_PORT;
+use crate::rule::Expression;
+use crate::store::DEFAULT_STORE_MAX_BYTES;
+use crate::template::{is_numeric_metric, TrayTemplate};
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+pub const DEFAULT_REFRESH_INTERVAL_SECS: u64 = 3;
+pub const MIN_REFRESH_INTERVAL_SECS: u64 = 1;
+pub const MAX_REFRESH_INTERVAL_SECS: u64 = 3600;
+pub const MAX_HISTORY_SIZE: usize = 3600;
+pub const MAX_SPARKLINE_LENGTH: usize = 60;
+pub const MAX_PRECISION: usize = 3;
+pub const MAX_STORAGE_SIZE_MB: u64 = 4096;
+pub const DEFAULT_TRAY_SEGMENTS: [&str; 5] =
+    ["battery", "cpu", "memory", "network", "temperatures"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub refresh_interval_secs: u64,
+    pub history_size: usize,
+    /// Collectors shown in the menu bar, in order. All other collectors only appear in the menu.
+    pub tray_segments: Vec<String>,
+    /// Replaces the tray segments when set.
+    pub tray_template: Option<TrayTemplate>,
+    /// Collectors whose tray segment draws a sparkline instead of a bar.
+    pub tray_sparklines: Vec<String>,
+    pub tray: TrayOptions,
+    pub memory_pressure: MemoryPressureThresholds,
+    pub disk_filter: DiskFilter,
+    pub battery: BatteryPolicy,
+    /// Whether samples are written to the on-disk time-series store.
+    pub storage_enabled: bool,
+    pub storage_max_bytes: u64,
+    /// Whether `/metrics` is served on `127.0.0.1:<metrics_port>`.
+    pub metrics_enabled: bool,
+    pub metrics_port: u16,
+    pub alert_rules: Vec<AlertRule>,
+    pub hooks: Vec<Hook>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            refresh_interval_secs: DEFAULT_REFRESH_INTERVAL_SECS,
+            history_size: DEFAULT_HISTORY_SIZE,
+            tray_segments: DEFAULT_TRAY_SEGMENTS.map(str::to_string).to_vec(),
+            tray_template: None,
+            tray_sparklines: Vec::new(),
+            tray: TrayOptions::default(),
+            memory_pressure: MemoryPressureThresholds::default(),
+            disk_filter: DiskFilter::default(),
+            battery: BatteryPolicy::default(),
+            storage_enabled: true,
+            storage_max_bytes: DEFAULT_STORE_MAX_BYTES,
+            metrics_enabled: false,
+            metrics_port: DEFAULT_METRICS_PORT,
+            alert_rules: default_alert_rules(),
+            hooks: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    Read(String),
+    Parse(String),
+    Invalid {
+        field: &'static str,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(message) => write!(f, "Failed to read config file: {}", message),
+            ConfigError::Parse(message) => write!(
+                f,
+                "Failed to parse config file, using defaults: {}",
+                message.trim_end()
+            ),
+            ConfigError::Invalid { field, message } => {
+                write!(f, "Invalid config value for `{}`: {}", field, message)
+            }
+        }
+    }
+}
+
+impl ConfigError {
+    /// Whether the whole file was rejected, rather than a single value.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, ConfigError::Read(_) | ConfigError::Parse(_))
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawConfig {
+    refresh_interval_secs: Option<i64>,
+    history_size: Option<i64>,
+    tray: RawTrayConfig,
+    format: RawFormatConfig,
+    memory: RawMemoryConfig,
+    disk: RawDiskConfig,
+    battery: RawBatteryConfig,
+    storage: RawStorageConfig,
+    metrics: RawMetricsConfig,
+    alerts: Option<Vec<RawAlertRule>>,
+    hooks: Vec<RawHook>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawTrayConfig {
+    segments: Option<Vec<String>>,
+    template: Option<String>,
+    sparklines: Option<Vec<String>>,
+    sparkline_length: Option<i64>,
+    cpu: Option<String>,
+    show_temperature: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawFormatConfig {
+    precision: Option<i64>,
+    temperature_unit: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawMemoryConfig {
+    warning_available_percent: Option<f32>,
+    critical_available_percent: Option<f32>,
+    warning_swap_growth_per_sec: Option<u64>,
+    critical_swap_growth_per_sec: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawDiskConfig {
+    exclude_file_systems: Option<Vec<String>>,
+    exclude_mount_points: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawBatteryConfig {
+    low_percent: Option<f32>,
+    charge_limit_percent: Option<f32>,
+    notify_state_changes: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawStorageConfig {
+    enabled: Option<bool>,
+    max_size_mb: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawMetricsConfig {
+    enabled: Option<bool>,
+    port: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawAlertRule {
+    name: Option<String>,
+    when: Option<String>,
+    metric: Option<String>,
+    above: Option<f64>,
+    below: Option<f64>,
+    for_secs: Option<i64>,
+    hysteresis: Option<f64>,
+    cooldown_secs: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawHook {
+    name: Option<String>,
+    command: Option<String>,
+    on_alert: Option<String>,
+    on_battery: Option<String>,
+    timeout_secs: Option<i64>,
+    max_concurrent: Option<i64>,
+    enabled: Option<bool>,
+}
+
+impl Config {
+    /// Loads the config file at `path`. A missing file is not an error and yields the defaults.
+    pub fn load(path: &Path) -> (Self, Vec<ConfigError>) {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (Self::default(), Vec::new()),
+            Err(e) => (
+                Self::default(),
+                vec![ConfigError::Read(format!("{}: {}", path.display(), e))],
+            ),
+        }
+    }
+
+    /// Parses and validates a config file, falling back to the default for every invalid value.
+    pub fn parse(text: &str) -> (Self, Vec<ConfigError>) {
+        let raw: RawConfig = match toml::from_str(text) {
+            Ok(raw) => raw,
+            Err(e) => {
+                let message = match e.span() {
+                    Some(span) => {
+                        format!("line {}: {}", line_number(text, span.start), e.message())
+                    }
+                    None => e.message().to_string(),
+                };
+                return (Self::default(), vec![ConfigError::Parse(message)]);
+            }
+        };
+
+        let mut config = Self::default();
+        let mut errors = Vec::new();
+
+        if let Some(secs) = raw.refresh_interval_secs {
+            match u64::try_from(secs) {
+                Ok(secs)
+                    if (MIN_REFRESH_INTERVAL_SECS..=MAX_REFRESH_INTERVAL_SECS).contains(&secs) =>
+                {
+                    config.refresh_interval_secs = secs
+                }
+                _ => errors.push(invalid(
+                    "refresh_interval_secs",
+                    format!(
+                        "expected {} to {} seconds, found {}",
+                        MIN_REFRESH_INTERVAL_SECS, MAX_REFRESH_INTERVAL_SECS, secs
+                    ),
+                )),
+            }
+        }
+
+        if let Some(size) = raw.history_size {
+            match usize::try_from(size) {
+                Ok(size) if (1..=MAX_HISTORY_SIZE).contains(&size) => config.history_size = size,
+                _ => errors.push(invalid(
+                    "history_size",
+                    format!("expected 1 to {} samples, found {}", MAX_HISTORY_SIZE, size),
+                )),
+            }
+        }
+
+        if let Some(segments) = raw.tray.segments {
+            match validate_segments(&segments) {
+                Ok(()) => config.tray_segments = segments,
+                Err(message) => errors.push(invalid("tray.segments", message)),
+            }
+        }
+
+        if let Some(template) = raw.tray.template {
+            match TrayTemplate::compile(&template) {
+                Ok(template) => config.tray_template = Some(template),
+                Err(template_errors) => errors.extend(
+                    template_errors
+                        .into_iter()
+                        .map(|error| invalid("tray.template", error.to_string())),
+                ),
+            }
+        }
+
+        if let Some(sparklines) = raw.tray.sparklines {
+            match validate_segments(&sparklines) {
+                Ok(()) => config.tray_sparklines = sparklines,
+                Err(message) => errors.push(invalid("tray.sparklines", message)),
+            }
+        }
+
+        if let Some(length) = raw.tray.sparkline_length {
+            match usize::try_from(length) {
+                Ok(length) if (1..=MAX_SPARKLINE_LENGTH).contains(&length) => {
+                    config.tray.sparkline_length = length
+                }
+                _ => errors.push(invalid(
+                    "tray.sparkline_length",
+                    format!(
+                        "expected 1 to {} samples, found {}",
+                        MAX_SPARKLINE_LENGTH, length
+                    ),
+                )),
+            }
+        }
+
+        if let Some(cpu) = raw.tray.cpu {
+            match cpu.as_str() {
+                "usage" => config.tray.cpu_segment = CpuTraySegment::Usage,
+                "load_average" => config.tray.cpu_segment = CpuTraySegment::LoadAverage,
+                _ => errors.push(invalid(
+                    "tray.cpu",
+                    format!("expected \"usage\" or \"load_average\", found {:?}", cpu),
+                )),
+            }
+        }
+
+        if let Some(show_temperature) = raw.tray.show_temperature {
+            config.tray.show_temperature = show_temperature;
+        }
+
+        if let Some(precision) = raw.format.precision {
+            match usize::try_from(precision) {
+                Ok(precision) if precision <= MAX_PRECISION => config.tray.precision = precision,
+                _ => errors.push(invalid(
+                    "format.precision",
+                    format!("expected 0 to {}, found {}", MAX_PRECISION, precision),
+                )),
+            }
+        }
+
+        if let Some(unit) = raw.format.temperature_unit {
+            match unit.as_str() {
+                "celsius" => config.tray.temperature_unit = TemperatureUnit::Celsius,
+                "fahrenheit" => config.tray.temperature_unit = TemperatureUnit::Fahrenheit,
+                _ => errors.push(invalid(
+                    "format.temperature_unit",
+                    format!("expected \"celsius\" or \"fahrenheit\", found {:?}", unit),
+                )),
+            }
+        }
+
+        let memory = &raw.memory;
+        let thresholds = MemoryPressureThresholds {
+            warning_available_percent: memory
+                .warning_available_percent
+                .unwrap_or(config.memory_pressure.warning_available_percent),
+            critical_available_percent: memory
+                .critical_available_percent
+                .unwrap_or(config.memory_pressure.critical_available_percent),
+            warning_swap_growth_per_sec: memory
+                .warning_swap_growth_per_sec
+                .unwrap_or(config.memory_pressure.warning_swap_growth_per_sec),
+            critical_swap_growth_per_sec: memory
+                .critical_swap_growth_per_sec
+                .unwrap_or(config.memory_pressure.critical_swap_growth_per_sec),
+        };
+        match validate_memory_pressure(&thresholds) {
+            Ok(()) => config.memory_pressure = thresholds,
+            Err(message) => errors.push(invalid("memory", message)),
+        }
+
+        if let Some(file_systems) = raw.disk.exclude_file_systems {
+            match validate_names(&file_systems) {
+                Ok(()) => config.disk_filter.excluded_file_systems = file_systems,
+                Err(message) => errors.push(invalid("disk.exclude_file_systems", message)),
+            }
+        }
+        if let Some(mount_points) = raw.disk.exclude_mount_points {
+            match validate_names(&mount_points) {
+                Ok(()) => config.disk_filter.excluded_mount_points = mount_points,
+                Err(message) => errors.push(invalid("disk.exclude_mount_points", message)),
+            }
+        }
+
+        let battery = &raw.battery;
+        let percent = |value: Option<f32>, default: Option<f32>| match value {
+            Some(0.0) => None,
+            Some(percent) => Some(percent),
+            None => default,
+        };
+        let policy = BatteryPolicy {
+            low_percent: percent(battery.low_percent, config.battery.low_percent),
+            charge_limit_percent: percent(
+                battery.charge_limit_percent,
+                config.battery.charge_limit_percent,
+            ),
+            notify_state_changes: battery
+                .notify_state_changes
+                .unwrap_or(config.battery.notify_state_changes),
+        };
+        match validate_battery_policy(&policy) {
+            Ok(()) => config.battery = policy,
+            Err(message) => errors.push(invalid("battery", message)),
+        }
+
+        if let Some(enabled) = raw.storage.enabled {
+            config.storage_enabled = enabled;
+        }
+
+        if let Some(size) = raw.storage.max_size_mb {
+            match u64::try_from(size) {
+                Ok(size) if (1..=MAX_STORAGE_SIZE_MB).contains(&size) => {
+                    config.storage_max_bytes = size * 1024 * 1024
+                }
+                _ => errors.push(invalid(
+                    "storage.max_size_mb",
+                    format!("expected 1 to {} MB, found {}", MAX_STORAGE_SIZE_MB, size),
+                )),
+            }
+        }
+
+        if let Some(enabled) = raw.metrics.enabled {
+            config.metrics_enabled = enabled;
+        }
+
+        if let Some(port) = raw.metrics.port {
+            match u16::try_from(port) {
+                Ok(port) if port > 0 => config.metrics_port = port,
+                _ => errors.push(invalid(
+                    "metrics.port",
+                    format!("expected 1 to {}, found {}", u16::MAX, port),
+                )),
+            }
+        }
+
+        if let Some(rules) = raw.alerts {
+            config.alert_rules = Vec::new();
+            for (index, rule) in rules.into_iter().enumerate() {
+                match validate_alert_rule(rule) {
+                    Ok(rule) => config.alert_rules.push(rule),
+                    Err(message) => errors.push(invalid(
+                        "alerts",
+                        format!("rule {}: {}", index + 1, message),
+                    )),
+                }
+            }
+        }
+
+        for (index, hook) in raw.hooks.into_iter().enumerate() {
+            match validate_hook(hook, &config.alert_rules) {
+                Ok(hook) => config.hooks.push(hook),
+                Err(message) => {
+                    errors.push(invalid("hooks", format!("hook {}: {}", index + 1, message)))
+                }
+            }
+        }
+
+        (config, errors)
+    }
+
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_interval_secs)
+    }
+}
+
+fn invalid(field: &'static str, message: String) -> ConfigError {
+    ConfigError::Invalid { field, message }
+}
+
+fn line_number(text: &str, offset: usize) -> usize {
+    text.as_bytes()[..offset.min(text.len())]
+        .iter()
+        .filter(|&&byte| byte == b'\n')
+        .count()
+        + 1
+}
+
+fn validate_memory_pressure(thresholds: &MemoryPressureThresholds) -> Result<(), String> {
+    for percent in [
+        thresholds.warning_available_percent,
+        thresholds.critical_available_percent,
+    ] {
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(format!(
+                "available memory thresholds must be 0 to 100 percent, found {}",
+                percent
+            ));
+        }
+    }
+    if thresholds.critical_available_percent > thresholds.warning_available_percent {
+        return Err(
+            "critical_available_percent must not be above warning_available_percent".to_string(),
+        );
+    }
+    if thresholds.critical_swap_growth_per_sec < thresholds.warning_swap_growth_per_sec {
+        return Err(
+            "critical_swap_growth_per_sec must not be below warning_swap_growth_per_sec"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+fn validate_names(names: &[String]) -> Result<(), String> {
+    if names.iter().any(|name| name.trim().is_empty()) {
+        return Err("entries must not be empty".to_string());
+    }
+    Ok(())
+}
+
+fn validate_battery_policy(policy: &BatteryPolicy) -> Result<(), String> {
+    for percent in [policy.low_percent, policy.charge_limit_percent]
+        .into_iter()
+        .flatten()
+    {
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(format!(
+                "battery thresholds must be 0 to 100 percent, found {}",
+                percent
+            ));
+        }
+    }
+    if let (Some(low), Some(limit)) = (policy.low_percent, policy.charge_limit_percent) {
+        if low >= limit {
+            return Err("low_percent must be below charge_limit_percent".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn validate_alert_rule(raw: RawAlertRule) -> Result<AlertRule, String> {
+    let secs = |value: Option<i64>, default: u64, field: &str| match value {
+        None => Ok(default),
+        Some(value) => u64::try_from(value)
+            .map_err(|_| format!("expected a non-negative `{}`, found {}", field, value)),
+    };
+    let mut for_secs = secs(raw.for_secs, 0, "for_secs")?;
+
+    let condition = match raw.when {
+        Some(when) => {
+            if raw.metric.is_some()
+                || raw.above.is_some()
+                || raw.below.is_some()
+                || raw.hysteresis.is_some()
+            {
+                return Err(
+                    "`when` cannot be combined with `metric`, `above`, `below` or `hysteresis`"
+                        .to_string(),
+                );
+            }
+            let expression = Expression::parse(&when).map_err(|e| format!("in `when` at {}", e))?;
+            if expression.for_secs() > 0 {
+                if raw.for_secs.is_some() {
+                    return Err("expected either `for` in `when` or `for_secs`".to_string());
+                }
+                for_secs = expression.for_secs();
+            }
+            AlertCondition::Expression(expression)
+        }
+        None => {
+            let metric = raw.metric.ok_or("missing `metric` or `when`")?;
+            if !is_numeric_metric(&metric) {
+                return Err(format!("unknown metric {:?}", metric));
+            }
+            let (comparison, threshold) = match (raw.above, raw.below) {
+                (Some(threshold), None) => (Comparison::Above, threshold),
+                (None, Some(threshold)) => (Comparison::Below, threshold),
+                _ => return Err("expected exactly one of `above` and `below`".to_string()),
+            };
+            let hysteresis = raw
+                .hysteresis
+                .unwrap_or(threshold.abs() * DEFAULT_HYSTERESIS_RATIO);
+            if !threshold.is_finite() || !hysteresis.is_finite() || hysteresis < 0.0 {
+                return Err("expected finite numbers and a non-negative `hysteresis`".to_string());
+            }
+            AlertCondition::Threshold {
+                metric,
+                comparison,
+                threshold,
+                hysteresis,
+            }
+        }
+    };
+
+    let mut rule = AlertRule {
+        name: raw.name.unwrap_or_default(),
+        condition,
+        for_secs,
+        cooldown_secs: secs(
+            raw.cooldown_secs,
+            DEFAULT_ALERT_COOLDOWN_SECS,
+            "cooldown_secs",
+        )?,
+    };
+    if rule.name.is_empty() {
+        rule.name = rule.condition();
+    }
+    Ok(rule)
+}
+
+fn validate_hook(raw: RawHook, alert_rules: &[AlertRule]) -> Result<Hook, String> {
+    let name = raw
+        .name
+        .filter(|name| !name.is_empty())
+        .ok_or("missing `name`")?;
+    let command = raw
+        .command
+        .filter(|command| !command.trim().is_empty())
+        .ok_or("missing `command`")?;
+
+    let mut triggers = Vec::new();
+    if let Some(rule) = raw.on_alert {
+        if rule == "*" {
+            triggers.push(HookTrigger::Alert(None));
+        } else if alert_rules.iter().any(|alert| alert.name == rule) {
+            triggers.push(HookTrigger::Alert(Some(rule)));
+        } else {
+            return Err(format!("no alert rule named {:?}", rule));
+        }
+    }
+    if let Some(state) = raw.on_battery {
+        let state = BATTERY_STATES
+            .into_iter()
+            .find(|known| known.eq_ignore_ascii_case(&state))
+            .ok_or_else(|| {
+                format!(
+                    "unknown battery state {:?}, expected one of {}",
+                    state,
+                    BATTERY_STATES.join(", ")
+                )
+            })?;
+        triggers.push(HookTrigger::BatteryState(state.to_string()));
+    }
+    if triggers.is_empty() {
+        return Err("expected `on_alert`, `on_battery` or both".to_string());
+    }
+
+    let timeout_secs = match raw.timeout_secs {
+        None => DEFAULT_HOOK_TIMEOUT_SECS,
+        Some(secs) => u64::try_from(secs)
+            .ok()
+            .filter(|secs| (1..=MAX_HOOK_TIMEOUT_SECS).contains(secs))
+            .ok_or_else(|| {
+                format!(
+                    "expected `timeout_secs` of 1 to {}, found {}",
+                    MAX_HOOK_TIMEOUT_SECS, secs
+                )
+            })?,
+    };
+    let max_concurrent = match raw.max_concurrent {
+        None => 1,
+        Some(count) => usize::try_from(count)
+            .ok()
+            .filter(|count| (1..=MAX_RUNNING_HOOKS).contains(count))
+            .ok_or_else(|| {
+                format!(
+                    "expected `max_concurrent` of 1 to {}, found {}",
+                    MAX_RUNNING_HOOKS, count
+                )
+            })?,
+    };
+
+    Ok(Hook {
+        name,
+        command,
+        triggers,
+        timeout_secs,
+        max_concurrent,
+        enabled: raw.enabled.unwrap_or(true),
+    })
+}
+
+fn validate_segments(segments: &[String]) -> Result<(), String> {
+    for (index, segment) in segments.iter().enumerate() {
+        if segment.trim().is_empty() {
+            return Err("segment names must not be empty".to_string());
+        }
+        if segments[..index].contains(segment) {
+            return Err(format!("{:?} is listed more than once", segment));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let (config, errors) = Config::parse("");
+        assert!(errors.is_empty());
+        assert_eq!(config, Config::default());
+        assert_eq!(config.refresh_interval(), Duration::from_secs(3));
+
+        let (config, errors) = Config::parse(
+            r#"
+            refresh_interval_secs = 10
+            history_size = 120
+
+            [tray]
+            segments = ["memory", "cpu"]
+            template = "{cpu.pct}%"
+            sparklines = ["cpu"]
+            sparkline_length = 12
+            cpu = "load_average"
+            show_temperature = true
+
+            [format]
+            precision = 1
+            temperature_unit = "fahrenheit"
+
+            [memory]
+            warning_available_percent = 30
+            critical_swap_growth_per_sec = 1073741824
+
+            [disk]
+            exclude_file_systems = ["nfs"]
+            exclude_mount_points = ["/System/Volumes/"]
+
+            [battery]
+            low_percent = 15
+            charge_limit_percent = 0
+            notify_state_changes = false
+
+            [storage]
+            enabled = false
+            max_size_mb = 16
+
+            [metrics]
+            enabled = true
+            port = 9100
+            "#,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(
+            config.memory_pressure,
+            MemoryPressureThresholds {
+                warning_available_percent: 30.0,
+                critical_swap_growth_per_sec: 1024 * 1024 * 1024,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            config.battery,
+            BatteryPolicy {
+                low_percent: Some(15.0),
+                charge_limit_percent: None,
+                notify_state_changes: false,
+            }
+        );
+        assert_eq!(
+            config.disk_filter,
+            DiskFilter {
+                excluded_file_systems: vec!["nfs".to_string()],
+                excluded_mount_points: vec!["/System/Volumes/".to_string()],
+            }
+        );
+        assert_eq!(config.refresh_interval_secs, 10);
+        assert_eq!(config.history_size, 120);
+        assert_eq!(config.tray_segments, ["memory", "cpu"]);
+        assert_eq!(config.tray_sparklines, ["cpu"]);
+        assert!(!config.storage_enabled);
+        assert_eq!(config.storage_max_bytes, 16 * 1024 * 1024);
+        assert!(config.metrics_enabled);
+        assert_eq!(config.metrics_port, 9100);
+        assert_eq!(
+            config.tray_template.as_ref().map(TrayTemplate::source),
+            Some("{cpu.pct}%")
+        );
+        assert_eq!(
+            config.tray,
+            TrayOptions {
+                cpu_segment: CpuTraySegment::LoadAverage,
+                show_temperature: true,
+                precision: 1,
+                temperature_unit: TemperatureUnit::Fahrenheit,
+                sparkline_length: 12,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_config_invalid_values() {
+        let (config, errors) = Config::parse(
+            r#"
+            refresh_interval_secs = 0
+            history_size = 0
+
+            [tray]
+            segments = ["cpu", "cpu"]
+            template = "{cpu.pct} {gpu.pct}"
+            sparklines = [""]
+            sparkline_length = 100
+            cpu = "load"
+            show_temperature = true
+
+            [format]
+            precision = 7
+            temperature_unit = "kelvin"
+
+            [memory]
+            warning_available_percent = 5
+
+            [disk]
+            exclude_mount_points = ["/Volumes/", ""]
+
+            [battery]
+            low_percent = 90
+            charge_limit_percent = 80
+
+            [storage]
+            max_size_mb = 0
+
+            [metrics]
+            port = 65536
+            "#,
+        );
+
+        let fields: Vec<_> = errors
+            .iter()
+            .map(|error| match error {
+                ConfigError::Invalid { field, .. } => *field,
+                other => panic!("unexpected error: {}", other),
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "refresh_interval_secs",
+                "history_size",
+                "tray.segments",
+                "tray.template",
+                "tray.sparklines",
+                "tray.sparkline_length",
+                "tray.cpu",
+                "format.precision",
+                "format.temperature_unit",
+                "memory",
+                "disk.exclude_mount_points",
+                "battery",
+                "storage.max_size_mb",
+                "metrics.port"
+            ]
+        );
+        assert!(errors.iter().all(|error| !error.is_fatal()));
+        assert_eq!(
+            errors[3].to_string(),
+            "Invalid config value for `tray.template`: column 11: unknown placeholder \"gpu.pct\""
+        );
+        assert_eq!(
+            errors[6].to_string(),
+            "Invalid config value for `tray.cpu`: expected \"usage\" or \"load_average\", found \"load\""
+        );
+
+        assert_eq!(
+            config,
+            Config {
+                tray: TrayOptions {
+                    show_temperature: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_config_alerts() {
+        assert_eq!(Config::default().alert_rules, default_alert_rules());
+        assert!(default_alert_rules()
+            .iter()
+            .all(|rule| !rule.name.contains("Battery")));
+        let (config, errors) = Config::parse("alerts = []");
+        assert!(errors.is_empty());
+        assert!(config.alert_rules.is_empty());
+
+        let (config, errors) = Config::parse(
+            r#"
+            [[alerts]]
+            name = "Hot"
+            metric = "temp.current"
+            above = 80
+            for_secs = 30
+            hysteresis = 2
+
+            [[alerts]]
+            metric = "reading.memory.available"
+            below = 1000
+
+            [[alerts]]
+            metric = "gpu.pct"
+            above = 50
+
+            [[alerts]]
+            metric = "cpu.pct"
+            above = 50
+            below = 10
+
+            [[alerts]]
+            metric = "cpu.pct"
+            above = 50
+            cooldown_secs = -1
+
+            [[alerts]]
+            name = "Busy on battery"
+            when = 'cpu > 85 and battery.state == "Discharging" for 2m'
+
+            [[alerts]]
+            when = "cpu > 85 and gpu > 50"
+
+            [[alerts]]
+            when = "cpu > 85"
+            metric = "cpu.pct"
+
+            [[alerts]]
+            when = "cpu > 85 for 1m"
+            for_secs = 30
+            "#,
+        );
+        assert_eq!(
+            config.alert_rules,
+            [
+                AlertRule {
+                    name: "Hot".to_string(),
+                    condition: AlertCondition::Threshold {
+                        metric: "temp.current".to_string(),
+                        comparison: Comparison::Above,
+                        threshold: 80.0,
+                        hysteresis: 2.0,
+                    },
+                    for_secs: 30,
+                    cooldown_secs: DEFAULT_ALERT_COOLDOWN_SECS,
+                },
+                AlertRule {
+                    name: "reading.memory.available < 1000".to_string(),
+                    condition: AlertCondition::Threshold {
+                        metric: "reading.memory.available".to_string(),
+                        comparison: Comparison::Below,
+                        threshold: 1000.0,
+                        hysteresis: 50.0,
+                    },
+                    for_secs: 0,
+                    cooldown_secs: DEFAULT_ALERT_COOLDOWN_SECS,
+                },
+                AlertRule {
+                    name: "Busy on battery".to_string(),
+                    condition: AlertCondition::Expression(
+                        Expression::parse("cpu > 85 and battery.state == \"Discharging\" for 2m")
+                            .unwrap()
+                    ),
+                    for_secs: 120,
+                    cooldown_secs: DEFAULT_ALERT_COOLDOWN_SECS,
+                },
+            ]
+        );
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "Invalid config value for `alerts`: rule 3: unknown metric \"gpu.pct\"",
+                "Invalid config value for `alerts`: rule 4: expected exactly one of `above` and `below`",
+                "Invalid config value for `alerts`: rule 5: expected a non-negative `cooldown_secs`, found -1",
+                "Invalid config value for `alerts`: rule 7: in `when` at column 14: unknown metric \"gpu\"",
+                "Invalid config value for `alerts`: rule 8: `when` cannot be combined with `metric`, `above`, `below` or `hysteresis`",
+                "Invalid config value for `alerts`: rule 9: expected either `for` in `when` or `for_secs`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_config_hooks() {
+        let (config, errors) = Config::parse(
+            r#"
+            [[hooks]]
+            name = "Pause sync"
+            command = "syncctl pause"
+            on_battery = "discharging"
+            on_alert = "Memory almost full"
+
+            [[hooks]]
+            name = "Log alerts"
+            command = "logger \"$SYSTEM_STATS_ALERT_MESSAGE\""
+            on_alert = "*"
+            timeout_secs = 5
+            max_concurrent = 2
+            enabled = false
+
+            [[hooks]]
+            name = "No trigger"
+            command = "true"
+
+            [[hooks]]
+            name = "Unknown rule"
+            command = "true"
+            on_alert = "GPU hot"
+
+            [[hooks]]
+            name = "Unknown state"
+            command = "true"
+            on_battery = "Idle"
+
+            [[hooks]]
+            name = "Slow"
+            command = "true"
+            on_alert = "*"
+            timeout_secs = 0
+
+            [[hooks]]
+            command = "true"
+            on_alert = "*"
+            "#,
+        );
+        assert_eq!(
+            config.hooks,
+            [
+                Hook {
+                    name: "Pause sync".to_string(),
+                    command: "syncctl pause".to_string(),
+                    triggers: vec![
+                        HookTrigger::Alert(Some("Memory almost full".to_string())),
+                        HookTrigger::BatteryState("Discharging".to_string()),
+                    ],
+                    timeout_secs: DEFAULT_HOOK_TIMEOUT_SECS,
+                    max_concurrent: 1,
+                    enabled: true,
+                },
+                Hook {
+                    name: "Log alerts".to_string(),
+                    command: "logger \"$SYSTEM_STATS_ALERT_MESSAGE\"".to_string(),
+                    triggers: vec![HookTrigger::Alert(None)],
+                    timeout_secs: 5,
+                    max_concurrent: 2,
+                    enabled: false,
+                },
+            ]
+        );
+        let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "Invalid config value for `hooks`: hook 3: expected `on_alert`, `on_battery` or both",
+                "Invalid config value for `hooks`: hook 4: no alert rule named \"GPU hot\"",
+                "Invalid config value for `hooks`: hook 5: unknown battery state \"Idle\", expected one of Charging, Discharging, Full, Empty, Unknown",
+                "Invalid config value for `hooks`: hook 6: expected `timeout_secs` of 1 to 3600, found 0",
+                "Invalid config value for `hooks`: hook 7: missing `name`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_config_syntax_errors() {
+        for text in [
+            "refresh_interval_secs = ",
+            "refresh_interval_secs = \"fast\"",
+            "[tray]\ncolour = \"red\"",
+            "[display]\nprecision = 1",
+        ] {
+            let (config, errors) = Config::parse(text);
+            assert_eq!(config, Config::default());
+            assert!(
+                matches!(errors.as_slice(), [ConfigError::Parse(_)]),
+                "{}: {:?}",
+                text,
+                errors
+            );
+            assert!(errors[0].is_fatal());
+            assert!(!errors[0].to_string().contains('\n'));
+        }
+
+        let (_, errors) = Config::parse("refresh_interval_secs = 3\n\n[tray]\ncolour = \"red\"\n");
+        assert!(
+            errors[0]
+                .to_string()
+                .contains("line 4: unknown field `colour`"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn test_load_config() {
+        let dir = std::env::temp_dir().join(format!("system-stats-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILE_NAME);
+
+        let (config, errors) = Config::load(&path);
+        assert_eq!(config, Config::default());
+        assert!(errors.is_empty());
+
+        std::fs::write(&path, "refresh_interval_secs = 5\n").unwrap();
+        let (config, errors) = Config::load(&path);
+        assert_eq!(config.refresh_interval_secs, 5);
+        assert!(errors.is_empty());
+
+        let (config, errors) = Config::load(&dir);
+        assert_eq!(config, Config::default());
+        assert!(matches!(errors.as_slice(), [ConfigError::Read(_)]));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
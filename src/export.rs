@@ -0,0 +1,573 @@
+//! Export of stored samples to CSV or JSON Lines, and the matching import.
+//!
+//! Both formats share one schema: a `timestamp` in RFC 3339 (UTC), the `resolution_secs` the
+//! row averages over (0 for a raw sample) and one column per entry of [`COLUMNS`], named after
+//! the [`SystemStats`](crate::stats::SystemStats) field with its unit as a suffix. Values are
+//! always in the base unit of that suffix, so exports from different machines and app
+//! versions line up. Missing values are empty CSV fields or JSON `null`s.
+//!
+//! ```text
+//! timestamp,resolution_secs,battery_percent,...,temperature_celsius
+//! 2026-10-17T09:30:00Z,0,81,...,52.5
+//! ```
+
+use crate::format::civil_from_unix;
+use crate::history::Summary;
+use crate::stats::Unit;
+use crate::store::Sample;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+
+/// A column of the export schema and the [`Reading`](crate::stats::Reading) it is filled from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column {
+    pub name: &'static str,
+    pub reading: &'static str,
+    pub unit: Unit,
+}
+
+const fn column(name: &'static str, reading: &'static str, unit: Unit) -> Column {
+    Column {
+        name,
+        reading,
+        unit,
+    }
+}
+
+/// The export schema. Columns are only ever appended, so older exports keep importing.
+pub const COLUMNS: &[Column] = &[
+    column("battery_percent", "battery.percent", Unit::Percent),
+    column("battery_power_watts", "battery.power", Unit::Watts),
+    column("cpu_usage_percent", "cpu.usage", Unit::Percent),
+    column("load_average_one", "cpu.load_one", Unit::Count),
+    column("load_average_five", "cpu.load_five", Unit::Count),
+    column("load_average_fifteen", "cpu.load_fifteen", Unit::Count),
+    column("uptime_seconds", "cpu.uptime", Unit::Seconds),
+    column("memory_percent", "memory.percent", Unit::Percent),
+    column("memory_used_bytes", "memory.used", Unit::Bytes),
+    column("memory_total_bytes", "memory.total", Unit::Bytes),
+    column("memory_available_bytes", "memory.available", Unit::Bytes),
+    column("swap_used_bytes", "memory.swap_used", Unit::Bytes),
+    column("swap_percent", "memory.swap_percent", Unit::Percent),
+    column("disk_percent", "disk.percent", Unit::Percent),
+    column(
+        "disk_read_bytes_per_second",
+        "disk.read",
+        Unit::BytesPerSecond,
+    ),
+    column(
+        "disk_written_bytes_per_second",
+        "disk.written",
+        Unit::BytesPerSecond,
+    ),
+    column(
+        "network_received_bytes_per_second",
+        "network.received",
+        Unit::BytesPerSecond,
+    ),
+    column(
+        "network_transmitted_bytes_per_second",
+        "network.transmitted",
+        Unit::BytesPerSecond,
+    ),
+    column("temperature_celsius", "temperatures.current", Unit::Celsius),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+        }
+    }
+
+    /// Guesses the format from a file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(ExportFormat::Csv),
+            "jsonl" | "ndjson" => Some(ExportFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+/// One exported sample. `values` lines up with [`COLUMNS`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportRow {
+    pub timestamp: u64,
+    pub resolution_secs: u64,
+    pub values: Vec<Option<f64>>,
+}
+
+impl From<&Sample> for ExportRow {
+    fn from(sample: &Sample) -> Self {
+        Self {
+            timestamp: sample.timestamp,
+            resolution_secs: sample.resolution_secs,
+            values: COLUMNS
+                .iter()
+                .map(|column| sample.values.get(column.reading).copied())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+fn import_error(line: usize, message: impl Into<String>) -> ImportError {
+    ImportError {
+        line,
+        message: message.into(),
+    }
+}
+
+pub fn write_export(
+    writer: &mut impl Write,
+    format: ExportFormat,
+    rows: &[ExportRow],
+) -> io::Result<()> {
+    if format == ExportFormat::Csv {
+        let names: Vec<&str> = COLUMNS.iter().map(|column| column.name).collect();
+        writeln!(writer, "timestamp,resolution_secs,{}", names.join(","))?;
+    }
+
+    for row in rows {
+        let timestamp = format_rfc3339(row.timestamp);
+        match format {
+            ExportFormat::Csv => {
+                let values: Vec<String> = row
+                    .values
+                    .iter()
+                    .map(|value| value.map(|value| value.to_string()).unwrap_or_default())
+                    .collect();
+                writeln!(
+                    writer,
+                    "{},{},{}",
+                    timestamp,
+                    row.resolution_secs,
+                    values.join(",")
+                )?;
+            }
+            ExportFormat::JsonLines => {
+                // Written by hand to keep the schema's column order.
+                let mut line = format!(
+                    "{{\"timestamp\":\"{}\",\"resolution_secs\":{}",
+                    timestamp, row.resolution_secs
+                );
+                for (column, value) in COLUMNS.iter().zip(&row.values) {
+                    let value = value
+                        .and_then(serde_json::Number::from_f64)
+                        .map_or("null".to_string(), |value| value.to_string());
+                    line.push_str(&format!(",\"{}\":{}", column.name, value));
+                }
+                writeln!(writer, "{}}}", line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn write_export_file(path: &Path, format: ExportFormat, rows: &[ExportRow]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_export(&mut writer, format, rows)?;
+    writer.flush()
+}
+
+/// Reads an export back. Columns this version does not know are ignored and missing ones are
+/// left empty, so exports from other versions still load.
+pub fn read_export(
+    reader: impl BufRead,
+    format: ExportFormat,
+) -> Result<Vec<ExportRow>, ImportError> {
+    let mut lines = reader
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            line.map(|line| (index + 1, line))
+                .map_err(|e| import_error(index + 1, e.to_string()))
+        })
+        .filter(|line| !matches!(line, Ok((_, text)) if text.trim().is_empty()));
+
+    match format {
+        ExportFormat::Csv => {
+            let Some(header) = lines.next() else {
+                return Ok(Vec::new());
+            };
+            let (header_line, header) = header?;
+            let header: Vec<&str> = header.split(',').map(str::trim).collect();
+            let position = |name: &str| header.iter().position(|field| *field == name);
+            let timestamp_index = position("timestamp")
+                .ok_or_else(|| import_error(header_line, "missing timestamp column"))?;
+            let resolution_index = position("resolution_secs");
+            let indexes: Vec<Option<usize>> =
+                COLUMNS.iter().map(|column| position(column.name)).collect();
+
+            lines
+                .map(|line| {
+                    let (number, line) = line?;
+                    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                    if fields.len() != header.len() {
+                        return Err(import_error(
+                            number,
+                            format!("expected {} fields, found {}", header.len(), fields.len()),
+                        ));
+                    }
+                    let number_field = |index: usize| -> Result<Option<f64>, ImportError> {
+                        match fields[index] {
+                            "" => Ok(None),
+                            field => field.parse().map(Some).map_err(|_| {
+                                import_error(
+                                    number,
+                                    format!("invalid number {:?} in {}", field, header[index]),
+                                )
+                            }),
+                        }
+                    };
+
+                    Ok(ExportRow {
+                        timestamp: parse_timestamp(number, fields[timestamp_index])?,
+                        resolution_secs: match resolution_index {
+                            Some(index) => number_field(index)?.unwrap_or(0.0) as u64,
+                            None => 0,
+                        },
+                        values: indexes
+                            .iter()
+                            .map(|index| index.map_or(Ok(None), number_field))
+                            .collect::<Result<_, _>>()?,
+                    })
+                })
+                .collect()
+        }
+        ExportFormat::JsonLines => lines
+            .map(|line| {
+                let (number, line) = line?;
+                let object: serde_json::Map<String, serde_json::Value> =
+                    serde_json::from_str(&line).map_err(|e| import_error(number, e.to_string()))?;
+                let timestamp = object
+                    .get("timestamp")
+                    .and_then(serde_json::Value::as_str)
+                    .ok_or_else(|| import_error(number, "missing timestamp"))?;
+
+                Ok(ExportRow {
+                    timestamp: parse_timestamp(number, timestamp)?,
+                    resolution_secs: object
+                        .get("resolution_secs")
+                        .and_then(serde_json::Value::as_u64)
+                        .unwrap_or(0),
+                    values: COLUMNS
+                        .iter()
+                        .map(|column| object.get(column.name).and_then(serde_json::Value::as_f64))
+                        .collect(),
+                })
+            })
+            .collect(),
+    }
+}
+
+fn parse_timestamp(line: usize, text: &str) -> Result<u64, ImportError> {
+    parse_rfc3339(text)
+        .ok_or_else(|| import_error(line, format!("invalid RFC 3339 timestamp {:?}", text)))
+}
+
+/// Min/avg/max of every column over `rows`, lined up with [`COLUMNS`].
+pub fn summarize_columns(rows: &[ExportRow]) -> Vec<Option<Summary>> {
+    COLUMNS
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let mut values = rows.iter().filter_map(|row| row.values[index]);
+            let first = values.next()?;
+            let (mut min, mut max, mut sum, mut count) = (first, first, first, 1);
+            for value in values {
+                min = min.min(value);
+                max = max.max(value);
+                sum += value;
+                count += 1;
+            }
+            Some(Summary {
+                min,
+                avg: sum / count as f64,
+                max,
+                unit: column.unit,
+            })
+        })
+        .collect()
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date, the inverse of [`civil_from_unix`].
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp, e.g.
+/// `2026-10-17T09:30:00Z`.
+pub fn format_rfc3339(timestamp: u64) -> String {
+    let (year, month, day, hour, minute, second) = civil_from_unix(timestamp);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Parses an RFC 3339 timestamp with any UTC offset into seconds since the Unix epoch.
+/// Fractional seconds are truncated; times before the epoch are rejected.
+pub fn parse_rfc3339(text: &str) -> Option<u64> {
+    let number = |range: std::ops::Range<usize>| -> Option<u32> {
+        let digits = text.get(range)?;
+        if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    };
+    let separator = |index: usize, allowed: &[u8]| {
+        text.as_bytes()
+            .get(index)
+            .is_some_and(|byte| allowed.contains(byte))
+    };
+
+    if !(separator(4, b"-")
+        && separator(7, b"-")
+        && separator(10, b"Tt ")
+        && separator(13, b":")
+        && separator(16, b":"))
+    {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => return None,
+    };
+    // 60 is a leap second, which the epoch count folds into the next minute.
+    if day == 0 || day > days_in_month || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut rest = &text[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        rest = &fraction[digits..];
+    }
+    let offset_secs = match rest {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match rest.as_bytes().first()? {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let (hours, minutes) = rest[1..].split_once(':')?;
+            if hours.len() != 2 || minutes.len() != 2 {
+                return None;
+            }
+            let (hours, minutes): (i64, i64) = (hours.parse().ok()?, minutes.parse().ok()?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            sign * (hours * 3_600 + minutes * 60)
+        }
+    };
+
+    let days = days_from_civil(year as i64, month, day);
+    let secs = days * 86_400 + (hour * 3_600 + minute * 60 + second) as i64 - offset_secs;
+    u64::try_from(secs).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn rows() -> Vec<ExportRow> {
+        let sample = |timestamp, resolution_secs, cpu: f64, memory: Option<f64>| {
+            let mut values = BTreeMap::from([
+                ("cpu.usage".to_string(), cpu),
+                ("cpu.usage[core0]".to_string(), 99.0),
+            ]);
+            if let Some(memory) = memory {
+                values.insert("memory.used".to_string(), memory);
+            }
+            ExportRow::from(&Sample {
+                timestamp,
+                resolution_secs,
+                values,
+            })
+        };
+        vec![
+            sample(1_760_693_400, 60, 12.5, Some(4_294_967_296.0)),
+            sample(1_760_693_460, 0, 30.0, None),
+        ]
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(format_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc3339(1_760_693_400), "2025-10-17T09:30:00Z");
+        assert_eq!(format_rfc3339(951_825_600), "2000-02-29T12:00:00Z");
+
+        for timestamp in [0, 951_825_600, 1_760_693_400, 4_102_444_799] {
+            assert_eq!(parse_rfc3339(&format_rfc3339(timestamp)), Some(timestamp));
+        }
+        assert_eq!(
+            parse_rfc3339("2025-10-17T11:30:00.250+02:00"),
+            Some(1_760_693_400)
+        );
+        assert_eq!(
+            parse_rfc3339("2025-10-17 04:00:00-05:30"),
+            Some(1_760_693_400)
+        );
+
+        for invalid in [
+            "",
+            "2025-10-17",
+            "2025-10-17T09:30:00",
+            "2025-13-01T00:00:00Z",
+            "2025-02-29T00:00:00Z",
+            "2025-10-17T24:00:00Z",
+            "2025-10-17T09:30:00.Z",
+            "2025-10-17T09:30:00+2:00",
+            "1969-12-31T23:59:59Z",
+            "+025-10-17T09:30:00Z",
+        ] {
+            assert_eq!(parse_rfc3339(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let rows = rows();
+        assert_eq!(rows[0].values[2], Some(12.5));
+        assert_eq!(rows[1].values[8], None);
+
+        let mut csv = Vec::new();
+        write_export(&mut csv, ExportFormat::Csv, &rows).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with(
+            "timestamp,resolution_secs,battery_percent,battery_power_watts,cpu_usage_percent,"
+        ));
+        assert_eq!(
+            lines.next().unwrap(),
+            "2025-10-17T09:30:00Z,60,,,12.5,,,,,,4294967296,,,,,,,,,,"
+        );
+        assert_eq!(
+            read_export(csv.as_bytes(), ExportFormat::Csv).unwrap(),
+            rows
+        );
+
+        let mut jsonl = Vec::new();
+        write_export(&mut jsonl, ExportFormat::JsonLines, &rows).unwrap();
+        let jsonl = String::from_utf8(jsonl).unwrap();
+        assert!(jsonl.starts_with(
+            "{\"timestamp\":\"2025-10-17T09:30:00Z\",\"resolution_secs\":60,\
+             \"battery_percent\":null,\"battery_power_watts\":null,\"cpu_usage_percent\":12.5,"
+        ));
+        assert_eq!(
+            read_export(jsonl.as_bytes(), ExportFormat::JsonLines).unwrap(),
+            rows
+        );
+
+        assert_eq!(
+            ExportFormat::from_path(Path::new("stats.ndjson")),
+            Some(ExportFormat::JsonLines)
+        );
+        assert_eq!(ExportFormat::from_path(Path::new("stats.txt")), None);
+    }
+
+    #[test]
+    fn test_import_other_schemas_and_errors() {
+        let csv = "cpu_usage_percent,gpu_percent,timestamp\n\n40,7,2025-10-17T09:30:00Z\n";
+        let rows = read_export(csv.as_bytes(), ExportFormat::Csv).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].timestamp, 1_760_693_400);
+        assert_eq!(rows[0].resolution_secs, 0);
+        assert_eq!(rows[0].values[2], Some(40.0));
+        assert_eq!(read_export("".as_bytes(), ExportFormat::Csv).unwrap(), []);
+
+        let error = |text: &str, format| read_export(text.as_bytes(), format).unwrap_err();
+        assert_eq!(
+            error("cpu_usage_percent\n1\n", ExportFormat::Csv).to_string(),
+            "line 1: missing timestamp column"
+        );
+        assert_eq!(
+            error(
+                "timestamp,cpu_usage_percent\nyesterday,1\n",
+                ExportFormat::Csv
+            )
+            .to_string(),
+            "line 2: invalid RFC 3339 timestamp \"yesterday\""
+        );
+        assert_eq!(
+            error(
+                "timestamp,cpu_usage_percent\n2025-10-17T09:30:00Z,high\n",
+                ExportFormat::Csv
+            )
+            .to_string(),
+            "line 2: invalid number \"high\" in cpu_usage_percent"
+        );
+        assert_eq!(
+            error(
+                "timestamp,cpu_usage_percent\n2025-10-17T09:30:00Z\n",
+                ExportFormat::Csv
+            )
+            .to_string(),
+            "line 2: expected 2 fields, found 1"
+        );
+        assert_eq!(
+            error("{\"cpu_usage_percent\":1}\n", ExportFormat::JsonLines).to_string(),
+            "line 1: missing timestamp"
+        );
+    }
+
+    #[test]
+    fn test_summarize_columns() {
+        let summaries = summarize_columns(&rows());
+        assert_eq!(summaries.len(), COLUMNS.len());
+        assert_eq!(summaries[0], None);
+        assert_eq!(
+            summaries[2],
+            Some(Summary {
+                min: 12.5,
+                avg: 21.25,
+                max: 30.0,
+                unit: Unit::Percent,
+            })
+        );
+    }
+}
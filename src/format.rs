@@ -0,0 +1,1274 @@
+//! Text formatters for the tray title and menu items.
+
+use crate::collect::aggregate_time_to_empty;
+use crate::history::Summary;
+use crate::stats::{
+    BatteryStats, DiskVolumeStats, MemoryPressure, NetworkInterfaceStats, ProcessStats, Reading,
+    SystemStats, TemperatureStats, Unit,
+};
+
+pub const BYTES_TO_GB: f32 = 1024.0 * 1024.0 * 1024.0;
+pub const RATE_UNITS: [&str; 4] = ["B/s", "KB/s", "MB/s", "GB/s"];
+pub const SIZE_UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+pub const TRAY_SEGMENT_SEPARATOR: &str = " |  ";
+pub const DEFAULT_SPARKLINE_LENGTH: usize = 8;
+pub const BAR_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CpuTraySegment {
+    #[default]
+    Usage,
+    LoadAverage,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub fn convert(self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrayOptions {
+    pub cpu_segment: CpuTraySegment,
+    pub show_temperature: bool,
+    /// Decimal places for percentages and temperatures in the menu bar.
+    pub precision: usize,
+    pub temperature_unit: TemperatureUnit,
+    /// Samples drawn by segments that show a sparkline instead of a bar.
+    pub sparkline_length: usize,
+}
+
+impl Default for TrayOptions {
+    fn default() -> Self {
+        Self {
+            cpu_segment: CpuTraySegment::default(),
+            show_temperature: false,
+            precision: 0,
+            temperature_unit: TemperatureUnit::default(),
+            sparkline_length: DEFAULT_SPARKLINE_LENGTH,
+        }
+    }
+}
+
+pub fn bytes_to_gb(bytes: u64) -> f32 {
+    bytes as f32 / BYTES_TO_GB
+}
+
+pub(crate) fn scale_bytes(bytes: u64, units: &[&'static str]) -> (f64, &'static str) {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    (value, units[unit])
+}
+
+fn format_scaled(bytes: u64, units: &[&'static str]) -> String {
+    let (value, unit) = scale_bytes(bytes, units);
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} {}", value, unit)
+    } else {
+        format!("{:.0} {}", value, unit)
+    }
+}
+
+pub fn format_rate(bytes_per_sec: u64) -> String {
+    format_scaled(bytes_per_sec, &RATE_UNITS)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    format_scaled(bytes, &SIZE_UNITS)
+}
+
+pub(crate) fn format_tray_number(value: f32, precision: usize) -> String {
+    if precision == 0 {
+        (value.round() as i32).to_string()
+    } else {
+        format!("{:.*}", precision, value)
+    }
+}
+
+pub fn create_bar(percentage: f32) -> String {
+    let index = ((percentage / 100.0) * (BAR_BLOCKS.len() - 1) as f32).round() as usize;
+    let index = index.min(BAR_BLOCKS.len() - 1);
+
+    BAR_BLOCKS[index].to_string()
+}
+
+/// One block per value, scaled so that `min` is the lowest and `max` the highest block.
+pub fn create_sparkline(values: &[f64], min: f64, max: f64) -> String {
+    values
+        .iter()
+        .map(|value| {
+            let ratio = if max > min {
+                ((value - min) / (max - min)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let index = (ratio * (BAR_BLOCKS.len() - 1) as f64).round() as usize;
+            BAR_BLOCKS[index.min(BAR_BLOCKS.len() - 1)]
+        })
+        .collect()
+}
+
+fn graph(percentage: f32, sparkline: Option<&str>) -> String {
+    match sparkline {
+        Some(sparkline) if !sparkline.is_empty() => sparkline.to_string(),
+        _ => create_bar(percentage),
+    }
+}
+
+pub(crate) fn load_percent(stats: &SystemStats) -> f32 {
+    let cores = stats.cpu_cores.len().max(1) as f64;
+    ((stats.load_average_one / cores) * 100.0).clamp(0.0, 100.0) as f32
+}
+
+/// Segments draw `sparkline` in place of their bar when it is given.
+pub fn format_battery_segment(
+    stats: &SystemStats,
+    options: &TrayOptions,
+    sparkline: Option<&str>,
+) -> String {
+    format!(
+        "🔋 {} {}%",
+        graph(stats.battery_percent, sparkline),
+        format_tray_number(stats.battery_percent, options.precision)
+    )
+}
+
+pub fn format_cpu_segment(
+    stats: &SystemStats,
+    options: &TrayOptions,
+    sparkline: Option<&str>,
+) -> String {
+    match options.cpu_segment {
+        CpuTraySegment::Usage => format!(
+            "🧠 {} {}%",
+            graph(stats.cpu_usage, sparkline),
+            format_tray_number(stats.cpu_usage, options.precision)
+        ),
+        CpuTraySegment::LoadAverage => format!(
+            "🧠 {} {:.2}",
+            graph(load_percent(stats), sparkline),
+            stats.load_average_one
+        ),
+    }
+}
+
+pub fn format_memory_segment(
+    stats: &SystemStats,
+    options: &TrayOptions,
+    sparkline: Option<&str>,
+) -> String {
+    format!(
+        "💾 {} {}%",
+        graph(stats.memory_percent, sparkline),
+        format_tray_number(stats.memory_percent, options.precision)
+    )
+}
+
+pub fn format_network_segment(stats: &SystemStats) -> String {
+    format!(
+        "↓ {} ↑ {}",
+        format_rate(stats.network.received_per_sec),
+        format_rate(stats.network.transmitted_per_sec)
+    )
+}
+
+pub fn format_temperature_segment(stats: &SystemStats, options: &TrayOptions) -> Option<String> {
+    let hottest = stats
+        .hottest_temperature()
+        .filter(|_| options.show_temperature)?;
+    Some(format!(
+        "🌡 {}{}",
+        format_tray_number(
+            options.temperature_unit.convert(hottest.current),
+            options.precision
+        ),
+        options.temperature_unit.symbol()
+    ))
+}
+
+pub fn join_tray_segments(segments: impl IntoIterator<Item = String>) -> String {
+    segments
+        .into_iter()
+        .collect::<Vec<_>>()
+        .join(TRAY_SEGMENT_SEPARATOR)
+}
+
+pub fn format_tray_title(stats: &SystemStats, options: &TrayOptions) -> String {
+    join_tray_segments(
+        [
+            Some(format_battery_segment(stats, options, None)),
+            Some(format_cpu_segment(stats, options, None)),
+            Some(format_memory_segment(stats, options, None)),
+            Some(format_network_segment(stats)),
+            format_temperature_segment(stats, options),
+        ]
+        .into_iter()
+        .flatten(),
+    )
+}
+
+pub fn format_battery_text(stats: &SystemStats) -> String {
+    let time_to_empty =
+        aggregate_time_to_empty(&stats.batteries).filter(|_| stats.battery_state == "Discharging");
+
+    match time_to_empty {
+        Some(secs) => format!(
+            "🔋 Battery: {}% ({}, {} left)",
+            stats.battery_percent.round() as i32,
+            stats.battery_state,
+            format_duration_short(secs)
+        ),
+        None => format!(
+            "🔋 Battery: {}% ({})",
+            stats.battery_percent.round() as i32,
+            stats.battery_state
+        ),
+    }
+}
+
+pub fn format_battery_unit_text(battery: &BatteryStats) -> String {
+    format!(
+        "{}: {}% ({})",
+        battery.name,
+        battery.percent.round() as i32,
+        battery.state
+    )
+}
+
+pub fn format_battery_details(battery: &BatteryStats) -> Vec<String> {
+    let details = &battery.details;
+
+    let time_remaining = match (details.time_to_empty_secs, details.time_to_full_secs) {
+        (Some(secs), _) if battery.state == "Discharging" => {
+            format!("{} until empty", format_duration_short(secs))
+        }
+        (_, Some(secs)) if battery.state == "Charging" => {
+            format!("{} until full", format_duration_short(secs))
+        }
+        _ => "—".to_string(),
+    };
+
+    let temperature = details
+        .temperature_celsius
+        .map(|celsius| format!(" · {:.1}°C", celsius))
+        .unwrap_or_default();
+
+    vec![
+        format!(
+            "Health: {:.1}% ({:.1} Wh / {:.1} Wh design)",
+            details.health_percent, details.energy_full_wh, details.energy_full_design_wh
+        ),
+        format!(
+            "Cycles: {}",
+            details
+                .cycle_count
+                .map_or("—".to_string(), |cycles| cycles.to_string())
+        ),
+        format!("Power: {:.1} W", details.power_watts),
+        format!("Time remaining: {}", time_remaining),
+        format!("Voltage: {:.2} V{}", details.voltage, temperature),
+    ]
+}
+
+pub fn format_cpu_text(stats: &SystemStats) -> String {
+    format!("🧠 CPU Usage: {:.1}%", stats.cpu_usage)
+}
+
+pub fn format_duration_short(secs: u64) -> String {
+    let days = secs / 86_400;
+    let hours = (secs % 86_400) / 3_600;
+    let minutes = (secs % 3_600) / 60;
+
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Converts seconds since the Unix epoch into a UTC `(year, month, day, hour, minute, second)`
+/// tuple using the days-to-civil algorithm from Howard Hinnant's date library.
+pub fn civil_from_unix(secs: u64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        year,
+        month,
+        day,
+        (rem / 3_600) as u32,
+        ((rem % 3_600) / 60) as u32,
+        (rem % 60) as u32,
+    )
+}
+
+pub fn format_utc_timestamp(secs: u64) -> String {
+    let (year, month, day, hour, minute, _) = civil_from_unix(secs);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year, month, day, hour, minute
+    )
+}
+
+/// The local time zone's offset from UTC in seconds at `secs`, where the platform reports it.
+pub fn local_utc_offset_secs(secs: u64) -> Option<i64> {
+    #[cfg(unix)]
+    {
+        let time = libc::time_t::try_from(secs).ok()?;
+        // SAFETY: `tm` is plain data for which all zeroes is a valid value, and localtime_r(3)
+        // only writes to the `tm` it is given.
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+            return None;
+        }
+        // `c_long` is only 32 bits wide on some targets.
+        #[allow(clippy::useless_conversion)]
+        Some(i64::from(tm.tm_gmtoff))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = secs;
+        None
+    }
+}
+
+/// Formats `secs` as a wall-clock time `offset` seconds from UTC, or as UTC without an offset.
+pub fn format_timestamp_at_offset(secs: u64, offset: Option<i64>) -> String {
+    let Some(offset) = offset else {
+        return format_utc_timestamp(secs);
+    };
+    let (year, month, day, hour, minute, _) = civil_from_unix(secs.saturating_add_signed(offset));
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year, month, day, hour, minute
+    )
+}
+
+/// Formats `secs` in the local time zone, for the menu. Exports stay in UTC.
+pub fn format_local_timestamp(secs: u64) -> String {
+    format_timestamp_at_offset(secs, local_utc_offset_secs(secs))
+}
+
+pub fn format_load_text(stats: &SystemStats) -> String {
+    format!(
+        "📈 Load: {:.2} {:.2} {:.2} · Up {} · Booted {}",
+        stats.load_average_one,
+        stats.load_average_five,
+        stats.load_average_fifteen,
+        format_duration_short(stats.uptime_secs),
+        format_local_timestamp(stats.boot_time)
+    )
+}
+
+pub fn format_cpu_core_text(index: usize, usage: f32) -> String {
+    format!("Core {}: {} {:.1}%", index + 1, create_bar(usage), usage)
+}
+
+pub fn format_memory_text(stats: &SystemStats) -> String {
+    format!(
+        "💾 Memory: {:.1}% ({:.2} GB / {:.2} GB) · Swap: {:.2} GB / {:.2} GB",
+        stats.memory_percent,
+        bytes_to_gb(stats.memory_used),
+        bytes_to_gb(stats.memory_total),
+        bytes_to_gb(stats.swap_used),
+        bytes_to_gb(stats.swap_total)
+    )
+}
+
+pub fn format_memory_pressure(pressure: MemoryPressure) -> String {
+    match pressure {
+        MemoryPressure::Normal => "Normal".to_string(),
+        MemoryPressure::Warning => "Warning".to_string(),
+        MemoryPressure::Critical => "Critical".to_string(),
+    }
+}
+
+pub fn format_memory_details(stats: &SystemStats) -> Vec<String> {
+    vec![
+        format!("Used: {:.2} GB", bytes_to_gb(stats.memory_used)),
+        format!("Available: {:.2} GB", bytes_to_gb(stats.memory_available)),
+        format!("Free: {:.2} GB", bytes_to_gb(stats.memory_free)),
+        format!(
+            "Reclaimable: {:.2} GB",
+            bytes_to_gb(stats.memory_reclaimable)
+        ),
+        format!(
+            "Swap: {:.1}% ({:.2} GB / {:.2} GB)",
+            stats.swap_percent,
+            bytes_to_gb(stats.swap_used),
+            bytes_to_gb(stats.swap_total)
+        ),
+        format!(
+            "Pressure: {}",
+            format_memory_pressure(stats.memory_pressure)
+        ),
+    ]
+}
+
+pub fn format_disk_text(stats: &SystemStats) -> String {
+    let io = format!(
+        "R {} W {}",
+        format_rate(stats.disk.read_per_sec),
+        format_rate(stats.disk.written_per_sec)
+    );
+
+    match stats.disk.fullest_volume() {
+        Some(volume) => format!(
+            "💽 Disk: {:.1}% ({}) · {}",
+            volume.percent, volume.mount_point, io
+        ),
+        None => format!("💽 Disk: {}", io),
+    }
+}
+
+pub fn format_disk_volume_text(volume: &DiskVolumeStats) -> String {
+    format!(
+        "{}: {:.1}% ({:.2} GB / {:.2} GB)",
+        volume.mount_point,
+        volume.percent,
+        bytes_to_gb(volume.used),
+        bytes_to_gb(volume.total)
+    )
+}
+
+pub fn format_process_cpu_text(process: &ProcessStats) -> String {
+    format!(
+        "{} ({}): {:.1}%",
+        process.name, process.pid, process.cpu_usage
+    )
+}
+
+pub fn format_process_memory_text(process: &ProcessStats) -> String {
+    format!(
+        "{} ({}): {}",
+        process.name,
+        process.pid,
+        format_bytes(process.memory)
+    )
+}
+
+pub fn format_temperature_value(celsius: f32, unit: TemperatureUnit) -> String {
+    format!("{:.1}{}", unit.convert(celsius), unit.symbol())
+}
+
+pub fn format_temperature_text(temperature: &TemperatureStats, unit: TemperatureUnit) -> String {
+    let mut text = format!(
+        "{}: {} (max {}",
+        temperature.label,
+        format_temperature_value(temperature.current, unit),
+        format_temperature_value(temperature.max, unit)
+    );
+    if let Some(critical) = temperature.critical {
+        text.push_str(&format!(
+            ", critical {}",
+            format_temperature_value(critical, unit)
+        ));
+    }
+    text.push(')');
+    text
+}
+
+pub fn format_reading_value(reading: &Reading) -> String {
+    let value = if reading.value.is_finite() {
+        reading.value
+    } else {
+        0.0
+    };
+
+    match reading.unit {
+        Unit::Percent => format!("{:.1}%", value),
+        Unit::Bytes => format_bytes(value.max(0.0).round() as u64),
+        Unit::BytesPerSecond => format_rate(value.max(0.0).round() as u64),
+        Unit::Celsius => format!("{:.1}°C", value),
+        Unit::Seconds => format_duration_short(value.max(0.0).round() as u64),
+        Unit::Watts => format!("{:.1} W", value),
+        Unit::Count if value.fract() == 0.0 => format!("{:.0}", value),
+        Unit::Count => format!("{:.2}", value),
+    }
+}
+
+/// Min/avg/max of a metric's recent history, e.g. `min 4.0% avg 12.5% max 31.0%`.
+pub fn format_summary_text(summary: &Summary) -> String {
+    let value = |value: f64| format_reading_value(&Reading::new("", value, summary.unit));
+    format!(
+        "min {} avg {} max {}",
+        value(summary.min),
+        value(summary.avg),
+        value(summary.max)
+    )
+}
+
+pub fn format_reading_text(reading: &Reading) -> String {
+    format!("{}: {}", reading.id(), format_reading_value(reading))
+}
+
+pub fn format_network_text(stats: &SystemStats) -> String {
+    format!(
+        "🌐 Network: ↓ {} ↑ {}",
+        format_rate(stats.network.received_per_sec),
+        format_rate(stats.network.transmitted_per_sec)
+    )
+}
+
+pub fn format_network_interface_text(interface: &NetworkInterfaceStats) -> String {
+    format!(
+        "{}: ↓ {} ↑ {} (total ↓ {} ↑ {})",
+        interface.name,
+        format_rate(interface.received_per_sec),
+        format_rate(interface.transmitted_per_sec),
+        format_bytes(interface.total_received),
+        format_bytes(interface.total_transmitted)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{BatteryDetails, DiskStats, NetworkStats};
+
+    #[test]
+    fn test_bytes_to_gb_conversion() {
+        assert_eq!(BYTES_TO_GB, 1073741824.0);
+        assert_eq!(bytes_to_gb(0), 0.0);
+        assert_eq!(bytes_to_gb(1073741824), 1.0);
+        assert_eq!(bytes_to_gb(2147483648), 2.0);
+
+        let half_gb = bytes_to_gb(536870912);
+        assert!((half_gb - 0.5).abs() < 0.001);
+
+        let large_value = bytes_to_gb(10995116277760);
+        assert!((large_value - 10240.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_create_bar() {
+        assert_eq!(create_bar(0.0), "▁");
+        assert_eq!(create_bar(100.0), "█");
+
+        assert_eq!(create_bar(12.5), "▂");
+        assert_eq!(create_bar(25.0), "▃");
+        assert_eq!(create_bar(37.5), "▄");
+        assert_eq!(create_bar(50.0), "▅");
+        assert_eq!(create_bar(62.5), "▅");
+        assert_eq!(create_bar(75.0), "▆");
+        assert_eq!(create_bar(87.5), "▇");
+
+        assert_eq!(create_bar(1.0), "▁");
+        assert_eq!(create_bar(99.0), "█");
+    }
+
+    #[test]
+    fn test_create_sparkline() {
+        assert_eq!(create_sparkline(&[], 0.0, 100.0), "");
+        assert_eq!(
+            create_sparkline(&[0.0, 12.5, 25.0, 50.0, 75.0, 100.0], 0.0, 100.0),
+            "▁▂▃▅▆█"
+        );
+        assert_eq!(
+            create_sparkline(&[-5.0, 150.0, f64::NAN], 0.0, 100.0),
+            "▁█▁"
+        );
+        assert_eq!(create_sparkline(&[3.0, 3.0], 3.0, 3.0), "▁▁");
+
+        for percent in [0.0, 12.5, 37.5, 62.5, 87.5, 100.0] {
+            assert_eq!(
+                create_sparkline(&[percent as f64], 0.0, 100.0),
+                create_bar(percent)
+            );
+        }
+
+        let stats = SystemStats {
+            cpu_usage: 42.0,
+            ..Default::default()
+        };
+        let options = TrayOptions::default();
+        assert_eq!(format_cpu_segment(&stats, &options, None), "🧠 ▄ 42%");
+        assert_eq!(
+            format_cpu_segment(&stats, &options, Some("▁▃▄")),
+            "🧠 ▁▃▄ 42%"
+        );
+        assert_eq!(format_cpu_segment(&stats, &options, Some("")), "🧠 ▄ 42%");
+    }
+
+    #[test]
+    fn test_create_bar_extreme_values() {
+        let bar_negative = create_bar(-10.0);
+        assert!(!bar_negative.is_empty());
+
+        let bar_over = create_bar(150.0);
+        assert!(!bar_over.is_empty());
+    }
+
+    #[test]
+    fn test_format_tray_title() {
+        let stats = SystemStats {
+            cpu_usage: 45.7,
+            memory_used: 8589934592,
+            memory_total: 17179869184,
+            memory_percent: 50.0,
+            battery_percent: 85.3,
+            battery_state: "Charging".to_string(),
+            ..Default::default()
+        };
+
+        let title = format_tray_title(&stats, &TrayOptions::default());
+        assert!(title.contains("85%"));
+        assert!(title.contains("46%"));
+        assert!(title.contains("50%"));
+        assert!(title.contains("🔋"));
+        assert!(title.contains("🧠"));
+        assert!(title.contains("💾"));
+        assert!(title.contains("↓ 0 B/s ↑ 0 B/s"));
+
+        assert!(title.contains("|"));
+    }
+
+    #[test]
+    fn test_format_tray_title_edge_cases() {
+        let stats_zero = SystemStats {
+            cpu_usage: 0.0,
+            memory_used: 0,
+            memory_total: 17179869184,
+            memory_percent: 0.0,
+            battery_percent: 0.0,
+            battery_state: "Empty".to_string(),
+            ..Default::default()
+        };
+
+        let title = format_tray_title(&stats_zero, &TrayOptions::default());
+        assert!(title.contains("0%"));
+
+        let stats_max = SystemStats {
+            cpu_usage: 100.0,
+            memory_used: 17179869184,
+            memory_total: 17179869184,
+            memory_percent: 100.0,
+            battery_percent: 100.0,
+            battery_state: "Full".to_string(),
+            ..Default::default()
+        };
+
+        let title_max = format_tray_title(&stats_max, &TrayOptions::default());
+        assert!(title_max.contains("100%"));
+    }
+
+    #[test]
+    fn test_format_tray_title_load_average() {
+        let stats = SystemStats {
+            cpu_usage: 12.0,
+            cpu_cores: vec![0.0; 4],
+            load_average_one: 4.0,
+            ..Default::default()
+        };
+
+        let options = TrayOptions {
+            cpu_segment: CpuTraySegment::LoadAverage,
+            ..Default::default()
+        };
+        let title = format_tray_title(&stats, &options);
+        assert!(title.contains("🧠 █ 4.00"));
+        assert!(!title.contains("12%"));
+
+        let half_loaded = SystemStats {
+            load_average_one: 2.0,
+            ..stats.clone()
+        };
+        assert_eq!(load_percent(&half_loaded), 50.0);
+
+        let overloaded = SystemStats {
+            load_average_one: 9.5,
+            ..stats
+        };
+        assert_eq!(load_percent(&overloaded), 100.0);
+        assert_eq!(load_percent(&SystemStats::default()), 0.0);
+    }
+
+    #[test]
+    fn test_format_tray_title_temperature() {
+        let options = TrayOptions {
+            show_temperature: true,
+            ..Default::default()
+        };
+
+        let no_sensors = format_tray_title(&SystemStats::default(), &options);
+        assert!(!no_sensors.contains("🌡"));
+
+        let stats = SystemStats {
+            temperatures: vec![
+                TemperatureStats {
+                    label: "Battery".to_string(),
+                    current: 31.0,
+                    max: 35.0,
+                    critical: None,
+                },
+                TemperatureStats {
+                    label: "CPU die".to_string(),
+                    current: 71.6,
+                    max: 80.0,
+                    critical: Some(100.0),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let title = format_tray_title(&stats, &options);
+        assert!(title.ends_with(" |  🌡 72°C"));
+        assert_eq!(
+            format_temperature_segment(&stats, &options),
+            Some("🌡 72°C".to_string())
+        );
+
+        let hidden = format_tray_title(&stats, &TrayOptions::default());
+        assert!(!hidden.contains("🌡"));
+
+        assert_eq!(stats.hottest_temperature().unwrap().label, "CPU die");
+        assert_eq!(
+            format_temperature_text(&stats.temperatures[1], TemperatureUnit::Celsius),
+            "CPU die: 71.6°C (max 80.0°C, critical 100.0°C)"
+        );
+        assert_eq!(
+            format_temperature_text(&stats.temperatures[0], TemperatureUnit::Celsius),
+            "Battery: 31.0°C (max 35.0°C)"
+        );
+        assert_eq!(
+            format_temperature_text(&stats.temperatures[0], TemperatureUnit::Fahrenheit),
+            "Battery: 87.8°F (max 95.0°F)"
+        );
+        assert_eq!(
+            format_temperature_value(31.0, TemperatureUnit::Fahrenheit),
+            "87.8°F"
+        );
+
+        let fahrenheit = TrayOptions {
+            temperature_unit: TemperatureUnit::Fahrenheit,
+            precision: 1,
+            ..options
+        };
+        assert_eq!(
+            format_temperature_segment(&stats, &fahrenheit),
+            Some("🌡 160.9°F".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_tray_title_precision() {
+        let stats = SystemStats {
+            battery_percent: 80.0,
+            cpu_usage: 12.345,
+            memory_percent: 50.5,
+            ..Default::default()
+        };
+
+        let title = format_tray_title(&stats, &TrayOptions::default());
+        assert!(title.contains("🔋 ▇ 80%"));
+        assert!(title.contains("🧠 ▂ 12%"));
+        assert!(title.contains("💾 ▅ 51%"));
+
+        let options = TrayOptions {
+            precision: 2,
+            ..Default::default()
+        };
+        let title = format_tray_title(&stats, &options);
+        assert!(title.contains("🔋 ▇ 80.00%"));
+        assert!(title.contains("🧠 ▂ 12.35%"));
+        assert!(title.contains("💾 ▅ 50.50%"));
+    }
+
+    #[test]
+    fn test_format_duration_short() {
+        assert_eq!(format_duration_short(0), "0m");
+        assert_eq!(format_duration_short(59), "0m");
+        assert_eq!(format_duration_short(3_660), "1h 1m");
+        assert_eq!(
+            format_duration_short(3 * 86_400 + 4 * 3_600 + 12 * 60),
+            "3d 4h 12m"
+        );
+    }
+
+    #[test]
+    fn test_civil_from_unix() {
+        assert_eq!(civil_from_unix(0), (1970, 1, 1, 0, 0, 0));
+        assert_eq!(civil_from_unix(951_782_400), (2000, 2, 29, 0, 0, 0));
+        assert_eq!(civil_from_unix(1_709_210_096), (2024, 2, 29, 12, 34, 56));
+        assert_eq!(civil_from_unix(4_102_444_799), (2099, 12, 31, 23, 59, 59));
+        assert_eq!(format_utc_timestamp(1_709_210_096), "2024-02-29 12:34 UTC");
+        assert_eq!(
+            format_timestamp_at_offset(1_709_210_096, None),
+            "2024-02-29 12:34 UTC"
+        );
+        assert_eq!(
+            format_timestamp_at_offset(1_709_210_096, Some(2 * 3_600)),
+            "2024-02-29 14:34"
+        );
+        assert_eq!(
+            format_timestamp_at_offset(1_709_210_096, Some(-13 * 3_600)),
+            "2024-02-28 23:34"
+        );
+    }
+
+    #[test]
+    fn test_format_load_text() {
+        let stats = SystemStats {
+            load_average_one: 2.345,
+            load_average_five: 1.8,
+            load_average_fifteen: 1.2,
+            uptime_secs: 90_000,
+            boot_time: 1_709_210_096,
+            ..Default::default()
+        };
+
+        let text = format_load_text(&stats);
+        assert!(text.contains("📈"));
+        assert!(text.contains("1.80 1.20"));
+        assert!(text.contains("Up 1d 1h 0m"));
+        assert!(text.ends_with(&format!("Booted {}", format_local_timestamp(1_709_210_096))));
+    }
+
+    #[test]
+    fn test_format_battery_text() {
+        let stats = SystemStats {
+            cpu_usage: 0.0,
+            memory_used: 0,
+            memory_total: 0,
+            memory_percent: 0.0,
+            battery_percent: 75.5,
+            battery_state: "Discharging".to_string(),
+            ..Default::default()
+        };
+
+        let text = format_battery_text(&stats);
+        assert!(text.contains("76%"));
+        assert!(text.contains("Discharging"));
+        assert!(text.contains("🔋"));
+
+        let stats_charging = SystemStats {
+            cpu_usage: 0.0,
+            memory_used: 0,
+            memory_total: 0,
+            memory_percent: 0.0,
+            battery_percent: 50.0,
+            battery_state: "Charging".to_string(),
+            ..Default::default()
+        };
+
+        let text_charging = format_battery_text(&stats_charging);
+        assert!(text_charging.contains("50%"));
+        assert!(text_charging.contains("Charging"));
+    }
+
+    #[test]
+    fn test_format_battery_text_time_remaining() {
+        let battery = BatteryStats {
+            name: "Battery 1".to_string(),
+            percent: 64.0,
+            state: "Discharging".to_string(),
+            details: BatteryDetails {
+                time_to_empty_secs: Some(2 * 3_600 + 15 * 60),
+                time_to_full_secs: Some(45 * 60),
+                ..Default::default()
+            },
+        };
+        let stats = SystemStats {
+            battery_percent: 64.0,
+            battery_state: "Discharging".to_string(),
+            batteries: vec![battery],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_battery_text(&stats),
+            "🔋 Battery: 64% (Discharging, 2h 15m left)"
+        );
+
+        let charging = SystemStats {
+            battery_state: "Charging".to_string(),
+            ..stats
+        };
+        assert_eq!(format_battery_text(&charging), "🔋 Battery: 64% (Charging)");
+    }
+
+    #[test]
+    fn test_format_battery_details() {
+        let battery = BatteryStats {
+            name: "Battery 1 (SMP bq20z451)".to_string(),
+            percent: 80.4,
+            state: "Charging".to_string(),
+            details: BatteryDetails {
+                energy_wh: 40.0,
+                energy_full_wh: 45.0,
+                energy_full_design_wh: 50.0,
+                health_percent: 90.0,
+                power_watts: 12.34,
+                voltage: 12.5,
+                temperature_celsius: Some(30.25),
+                cycle_count: Some(321),
+                time_to_full_secs: Some(45 * 60),
+                time_to_empty_secs: None,
+            },
+        };
+
+        assert_eq!(
+            format_battery_unit_text(&battery),
+            "Battery 1 (SMP bq20z451): 80% (Charging)"
+        );
+
+        let details = format_battery_details(&battery);
+        assert_eq!(details.len(), 5);
+        assert_eq!(details[0], "Health: 90.0% (45.0 Wh / 50.0 Wh design)");
+        assert_eq!(details[1], "Cycles: 321");
+        assert_eq!(details[2], "Power: 12.3 W");
+        assert_eq!(details[3], "Time remaining: 45m until full");
+        assert_eq!(details[4], "Voltage: 12.50 V · 30.2°C");
+
+        let unknown = format_battery_details(&BatteryStats::default());
+        assert_eq!(unknown.len(), 5);
+        assert_eq!(unknown[1], "Cycles: —");
+        assert_eq!(unknown[3], "Time remaining: —");
+    }
+
+    #[test]
+    fn test_format_cpu_text() {
+        let stats = SystemStats {
+            cpu_usage: 33.7,
+            memory_used: 0,
+            memory_total: 0,
+            memory_percent: 0.0,
+            battery_percent: 0.0,
+            battery_state: "Unknown".to_string(),
+            ..Default::default()
+        };
+
+        let text = format_cpu_text(&stats);
+        assert!(text.contains("33.7%"));
+        assert!(text.contains("🧠"));
+
+        let stats_zero = SystemStats {
+            cpu_usage: 0.0,
+            memory_used: 0,
+            memory_total: 0,
+            memory_percent: 0.0,
+            battery_percent: 0.0,
+            battery_state: "Unknown".to_string(),
+            ..Default::default()
+        };
+
+        let text_zero = format_cpu_text(&stats_zero);
+        assert!(text_zero.contains("0.0%"));
+    }
+
+    #[test]
+    fn test_format_cpu_core_text() {
+        let text = format_cpu_core_text(0, 100.0);
+        assert!(text.contains("Core 1"));
+        assert!(text.contains("█"));
+        assert!(text.contains("100.0%"));
+
+        let text_idle = format_cpu_core_text(7, 0.0);
+        assert!(text_idle.contains("Core 8"));
+        assert!(text_idle.contains("0.0%"));
+    }
+
+    #[test]
+    fn test_format_process_text() {
+        let process = ProcessStats {
+            pid: 4242,
+            name: "cargo".to_string(),
+            cpu_usage: 187.3,
+            memory: 1073741824,
+        };
+
+        assert_eq!(format_process_cpu_text(&process), "cargo (4242): 187.3%");
+        assert_eq!(format_process_memory_text(&process), "cargo (4242): 1.0 GB");
+    }
+
+    #[test]
+    fn test_format_memory_text() {
+        let stats = SystemStats {
+            cpu_usage: 0.0,
+            memory_used: 8589934592,
+            memory_total: 17179869184,
+            memory_percent: 50.0,
+            battery_percent: 0.0,
+            battery_state: "Unknown".to_string(),
+            ..Default::default()
+        };
+
+        let text = format_memory_text(&stats);
+        assert!(text.contains("50.0%"));
+        assert!(text.contains("8.00 GB"));
+        assert!(text.contains("16.00 GB"));
+        assert!(text.contains("💾"));
+
+        let stats_low = SystemStats {
+            cpu_usage: 0.0,
+            memory_used: 1073741824,
+            memory_total: 17179869184,
+            memory_percent: 6.25,
+            battery_percent: 0.0,
+            battery_state: "Unknown".to_string(),
+            ..Default::default()
+        };
+
+        let text_low = format_memory_text(&stats_low);
+        assert!(text_low.contains("1.00 GB"));
+
+        let stats_swap = SystemStats {
+            swap_used: 536870912,
+            swap_total: 2147483648,
+            ..Default::default()
+        };
+
+        let text_swap = format_memory_text(&stats_swap);
+        assert!(text_swap.contains("Swap: 0.50 GB / 2.00 GB"));
+    }
+
+    #[test]
+    fn test_format_memory_details() {
+        let stats = SystemStats {
+            memory_used: 8589934592,
+            memory_available: 8589934592,
+            memory_free: 2147483648,
+            memory_reclaimable: 6442450944,
+            swap_used: 1073741824,
+            swap_total: 4294967296,
+            swap_percent: 25.0,
+            memory_pressure: MemoryPressure::Warning,
+            ..Default::default()
+        };
+
+        let details = format_memory_details(&stats);
+        assert_eq!(details.len(), 6);
+        assert_eq!(details[0], "Used: 8.00 GB");
+        assert_eq!(details[1], "Available: 8.00 GB");
+        assert_eq!(details[2], "Free: 2.00 GB");
+        assert_eq!(details[3], "Reclaimable: 6.00 GB");
+        assert_eq!(details[4], "Swap: 25.0% (1.00 GB / 4.00 GB)");
+        assert_eq!(details[5], "Pressure: Warning");
+    }
+
+    #[test]
+    fn test_format_rate() {
+        assert_eq!(format_rate(0), "0 B/s");
+        assert_eq!(format_rate(512), "512 B/s");
+        assert_eq!(format_rate(120 * 1024), "120 KB/s");
+        assert_eq!(format_rate(3355443), "3.2 MB/s");
+        assert_eq!(format_rate(2 * 1024 * 1024 * 1024), "2.0 GB/s");
+        assert_eq!(format_rate(u64::MAX), "17179869184.0 GB/s");
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(2048), "2 KB");
+        assert_eq!(format_bytes(1073741824), "1.0 GB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024 * 1024), "5.0 TB");
+    }
+
+    #[test]
+    fn test_format_disk_text() {
+        let stats = SystemStats {
+            disk: DiskStats {
+                read_per_sec: 2 * 1024 * 1024,
+                written_per_sec: 512,
+                volumes: vec![
+                    DiskVolumeStats {
+                        mount_point: "/".to_string(),
+                        file_system: "apfs".to_string(),
+                        used: 8589934592,
+                        total: 17179869184,
+                        percent: 50.0,
+                    },
+                    DiskVolumeStats {
+                        mount_point: "/Volumes/Backup".to_string(),
+                        file_system: "apfs".to_string(),
+                        used: 16106127360,
+                        total: 17179869184,
+                        percent: 93.75,
+                    },
+                ],
+            },
+            ..Default::default()
+        };
+
+        let text = format_disk_text(&stats);
+        assert!(text.contains("💽"));
+        assert!(text.contains("93.8% (/Volumes/Backup)"));
+        assert!(text.contains("R 2.0 MB/s W 512 B/s"));
+
+        let volume_text = format_disk_volume_text(&stats.disk.volumes[0]);
+        assert_eq!(volume_text, "/: 50.0% (8.00 GB / 16.00 GB)");
+
+        let text_empty = format_disk_text(&SystemStats::default());
+        assert!(text_empty.contains("R 0 B/s W 0 B/s"));
+    }
+
+    #[test]
+    fn test_format_summary_text() {
+        let summary = Summary {
+            min: 4.0,
+            avg: 12.54,
+            max: 31.0,
+            unit: Unit::Percent,
+        };
+        assert_eq!(
+            format_summary_text(&summary),
+            "min 4.0% avg 12.5% max 31.0%"
+        );
+
+        let summary = Summary {
+            min: 0.0,
+            avg: 512.0,
+            max: 2048.0,
+            unit: Unit::BytesPerSecond,
+        };
+        assert_eq!(
+            format_summary_text(&summary),
+            format!(
+                "min {} avg {} max {}",
+                format_rate(0),
+                format_rate(512),
+                format_rate(2048)
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_reading() {
+        let text = |reading: Reading| format_reading_text(&reading);
+
+        assert_eq!(
+            text(Reading::new("cpu.usage", 42.26, Unit::Percent)),
+            "cpu.usage: 42.3%"
+        );
+        assert_eq!(
+            text(Reading::new(
+                "memory.used",
+                3.0 * 1024.0 * 1024.0 * 1024.0,
+                Unit::Bytes
+            )),
+            "memory.used: 3.0 GB"
+        );
+        assert_eq!(
+            text(
+                Reading::new("network.received", 120.0 * 1024.0, Unit::BytesPerSecond)
+                    .with_instance("en0")
+            ),
+            "network.received[en0]: 120 KB/s"
+        );
+        assert_eq!(
+            text(Reading::new("cpu.uptime", 90_000.0, Unit::Seconds)),
+            "cpu.uptime: 1d 1h 0m"
+        );
+        assert_eq!(
+            text(Reading::new("gpu.temperature", 54.04, Unit::Celsius)),
+            "gpu.temperature: 54.0°C"
+        );
+        assert_eq!(
+            text(Reading::new("battery.power", 11.26, Unit::Watts)),
+            "battery.power: 11.3 W"
+        );
+        assert_eq!(
+            text(Reading::new("queue.depth", 7.0, Unit::Count)),
+            "queue.depth: 7"
+        );
+        assert_eq!(
+            text(Reading::new("cpu.load_one", 1.5, Unit::Count)),
+            "cpu.load_one: 1.50"
+        );
+        assert_eq!(
+            text(Reading::new("broken", f64::NAN, Unit::Percent)),
+            "broken: 0.0%"
+        );
+    }
+
+    #[test]
+    fn test_format_network_text() {
+        let stats = SystemStats {
+            network: NetworkStats {
+                received_per_sec: 3355443,
+                transmitted_per_sec: 120 * 1024,
+                interfaces: vec![NetworkInterfaceStats {
+                    name: "en0".to_string(),
+                    received_per_sec: 3355443,
+                    transmitted_per_sec: 120 * 1024,
+                    total_received: 1073741824,
+                    total_transmitted: 2048,
+                }],
+            },
+            ..Default::default()
+        };
+
+        let text = format_network_text(&stats);
+        assert!(text.contains("🌐"));
+        assert!(text.contains("↓ 3.2 MB/s"));
+        assert!(text.contains("↑ 120 KB/s"));
+
+        let interface_text = format_network_interface_text(&stats.network.interfaces[0]);
+        assert!(interface_text.starts_with("en0:"));
+        assert!(interface_text.contains("total ↓ 1.0 GB ↑ 2 KB"));
+    }
+
+    #[test]
+    fn test_system_stats_boundary_values() {
+        let stats = SystemStats {
+            cpu_usage: 100.0,
+            memory_used: u64::MAX,
+            memory_total: u64::MAX,
+            memory_percent: 100.0,
+            battery_percent: 100.0,
+            battery_state: "Full".to_string(),
+            ..Default::default()
+        };
+
+        let _ = format_tray_title(&stats, &TrayOptions::default());
+        let _ = format_tray_title(
+            &stats,
+            &TrayOptions {
+                cpu_segment: CpuTraySegment::LoadAverage,
+                show_temperature: true,
+                precision: 3,
+                temperature_unit: TemperatureUnit::Fahrenheit,
+                sparkline_length: 0,
+            },
+        );
+        let _ = format_load_text(&stats);
+        let _ = format_battery_text(&stats);
+        let _ = format_cpu_text(&stats);
+        let _ = format_memory_text(&stats);
+        let _ = format_disk_text(&stats);
+        let _ = format_network_text(&stats);
+    }
+}
@@ -0,0 +1,188 @@
+//! A fixed-size ring buffer of recent [`SystemStats`] samples.
+
+use crate::format::create_sparkline;
+use crate::stats::{SystemStats, Unit};
+use crate::template::metric_value;
+use std::collections::VecDeque;
+
+pub const DEFAULT_HISTORY_SIZE: usize = 60;
+
+/// Minimum, average and maximum of a metric over the samples in a [`History`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub unit: Unit,
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    samples: VecDeque<SystemStats>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the capacity, dropping the oldest samples if there are too many.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.samples.len() > capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Appends a sample, dropping the oldest one once the buffer is full.
+    pub fn push(&mut self, stats: SystemStats) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(stats);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<&SystemStats> {
+        self.samples.back()
+    }
+
+    /// Samples from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = &SystemStats> {
+        self.samples.iter()
+    }
+
+    /// The last `count` values of `metric`, oldest first. `metric` is a tray template placeholder
+    /// such as `cpu.pct`; samples without a value are skipped.
+    pub fn values(&self, metric: &str, count: usize) -> Vec<f64> {
+        let mut values: Vec<f64> = self
+            .samples
+            .iter()
+            .rev()
+            .filter_map(|stats| metric_value(stats, metric).map(|(value, _)| value))
+            .take(count)
+            .collect();
+        values.reverse();
+        values
+    }
+
+    pub fn summary(&self, metric: &str) -> Option<Summary> {
+        let mut values = self
+            .samples
+            .iter()
+            .filter_map(|stats| metric_value(stats, metric));
+        let (first, unit) = values.next()?;
+
+        let (mut min, mut max, mut sum, mut count) = (first, first, first, 1);
+        for (value, _) in values {
+            min = min.min(value);
+            max = max.max(value);
+            sum += value;
+            count += 1;
+        }
+        Some(Summary {
+            min,
+            avg: sum / count as f64,
+            max,
+            unit,
+        })
+    }
+
+    /// The last `length` values of `metric` as a sparkline. Percentages are drawn on a fixed
+    /// 0-100 scale, everything else from zero up to the largest value shown.
+    pub fn sparkline(&self, metric: &str, length: usize) -> Option<String> {
+        let values = self.values(metric, length);
+        let unit = metric_value(self.latest()?, metric)?.1;
+        let max = match unit {
+            Unit::Percent => 100.0,
+            _ => values.iter().copied().fold(0.0, f64::max),
+        };
+        Some(create_sparkline(&values, 0.0, max))
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(cpu_usage: f32, received_per_sec: u64) -> SystemStats {
+        let mut stats = SystemStats {
+            cpu_usage,
+            ..Default::default()
+        };
+        stats.network.received_per_sec = received_per_sec;
+        stats
+    }
+
+    #[test]
+    fn test_history_ring_buffer() {
+        let mut history = History::new(3);
+        assert!(history.is_empty());
+        assert_eq!(history.summary("cpu.pct"), None);
+        assert_eq!(history.sparkline("cpu.pct", 4), None);
+
+        for usage in [10.0, 20.0, 30.0, 40.0] {
+            history.push(sample(usage, 0));
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.latest().unwrap().cpu_usage, 40.0);
+        assert_eq!(history.values("cpu.pct", 10), [20.0, 30.0, 40.0]);
+        assert_eq!(history.values("cpu.pct", 2), [30.0, 40.0]);
+        assert!(history.values("temp.current", 2).is_empty());
+
+        history.set_capacity(2);
+        assert_eq!(history.values("cpu.pct", 10), [30.0, 40.0]);
+        history.set_capacity(0);
+        history.push(sample(50.0, 0));
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_history_summary_and_sparkline() {
+        let mut history = History::default();
+        assert_eq!(history.capacity(), DEFAULT_HISTORY_SIZE);
+        for (usage, received) in [(0.0, 0), (50.0, 1024), (100.0, 4096), (25.0, 2048)] {
+            history.push(sample(usage, received));
+        }
+
+        assert_eq!(
+            history.summary("cpu.pct"),
+            Some(Summary {
+                min: 0.0,
+                avg: 43.75,
+                max: 100.0,
+                unit: Unit::Percent,
+            })
+        );
+        assert_eq!(history.summary("net.rx").unwrap().max, 4096.0);
+
+        assert_eq!(history.sparkline("cpu.pct", 8).as_deref(), Some("▁▅█▃"));
+        assert_eq!(history.sparkline("cpu.pct", 2).as_deref(), Some("█▃"));
+        assert_eq!(history.sparkline("net.rx", 8).as_deref(), Some("▁▃█▅"));
+        assert_eq!(history.sparkline("temp.current", 8), None);
+    }
+}
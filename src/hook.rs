@@ -0,0 +1,625 @@
+//! Shell command hooks run on alerts and battery state changes.
+//!
+//! A hook runs its command with `sh -c` (`cmd /C` on Windows). The event is passed in
+//! `SYSTEM_STATS_EVENT` and friends, the latest sample as one `SYSTEM_STATS_<COLUMN>` variable per
+//! export column (e.g. `SYSTEM_STATS_MEMORY_PERCENT`, see [`COLUMNS`]), and both again as JSON on
+//! stdin. A hook still running after `timeout_secs` is killed along with its children. Every run
+//! is appended to the hook log with its exit status and captured output.
+
+use crate::alert::Alert;
+use crate::export::{format_rfc3339, COLUMNS};
+use crate::stats::{Reading, SystemStats};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 30;
+pub const MAX_HOOK_TIMEOUT_SECS: u64 = 3600;
+/// How many hook commands may run at once, across all hooks.
+pub const MAX_RUNNING_HOOKS: usize = 8;
+pub const HOOK_LOG_FILE_NAME: &str = "hooks.log";
+/// Size past which the hook log is moved to `hooks.log.old` and started afresh.
+pub const MAX_HOOK_LOG_BYTES: u64 = 1024 * 1024;
+/// Output kept per stream and run; the rest is dropped.
+const MAX_CAPTURED_OUTPUT_BYTES: usize = 16 * 1024;
+const HOOK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long to wait for output after the command exited, in case a child it left behind keeps
+/// the pipes open.
+const OUTPUT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+const ENV_PREFIX: &str = "SYSTEM_STATS_";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookTrigger {
+    /// An alert from the rule with this name, or from any rule for `None`.
+    Alert(Option<String>),
+    /// The battery changing into this state, e.g. "Discharging".
+    BatteryState(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hook {
+    pub name: String,
+    pub command: String,
+    pub triggers: Vec<HookTrigger>,
+    pub timeout_secs: u64,
+    /// How many runs of this hook may overlap. Events past the limit are skipped.
+    pub max_concurrent: usize,
+    /// Whether the hook starts out enabled. The tray menu can toggle it while the app runs.
+    pub enabled: bool,
+}
+
+impl Hook {
+    pub fn matches(&self, event: &HookEvent) -> bool {
+        self.triggers.iter().any(|trigger| match (trigger, event) {
+            (HookTrigger::Alert(rule), HookEvent::Alert(alert)) => {
+                rule.as_ref().is_none_or(|rule| *rule == alert.rule)
+            }
+            (HookTrigger::BatteryState(state), HookEvent::BatteryState { to, .. }) => {
+                state.eq_ignore_ascii_case(to)
+            }
+            _ => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookEvent {
+    Alert(Alert),
+    BatteryState { from: String, to: String },
+}
+
+impl HookEvent {
+    /// The event's name in `SYSTEM_STATS_EVENT`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HookEvent::Alert(_) => "alert",
+            HookEvent::BatteryState { .. } => "battery_state",
+        }
+    }
+
+    fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![("EVENT", self.kind().to_string())];
+        match self {
+            HookEvent::Alert(alert) => {
+                env.push(("ALERT_RULE", alert.rule.clone()));
+                env.push(("ALERT_MESSAGE", alert.message.clone()));
+            }
+            HookEvent::BatteryState { from, to } => {
+                env.push(("BATTERY_PREVIOUS_STATE", from.clone()));
+                env.push(("BATTERY_STATE", to.clone()));
+            }
+        }
+        env
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            HookEvent::Alert(alert) => serde_json::json!({
+                "type": self.kind(),
+                "rule": alert.rule,
+                "message": alert.message,
+            }),
+            HookEvent::BatteryState { from, to } => serde_json::json!({
+                "type": self.kind(),
+                "from": from,
+                "to": to,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookEvent::Alert(alert) => write!(f, "alert {:?}", alert.rule),
+            HookEvent::BatteryState { from, to } => write!(f, "battery {} -> {}", from, to),
+        }
+    }
+}
+
+/// Everything a hook run gets to see.
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub event: HookEvent,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub stats: SystemStats,
+    pub readings: Vec<Reading>,
+}
+
+impl HookContext {
+    /// The `SYSTEM_STATS_*` environment variables of a run of `hook`.
+    pub fn env(&self, hook: &Hook) -> Vec<(String, String)> {
+        let mut env = vec![
+            ("HOOK".to_string(), hook.name.clone()),
+            ("TIMESTAMP".to_string(), format_rfc3339(self.timestamp)),
+        ];
+        env.extend(
+            self.event
+                .env()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value)),
+        );
+        for column in COLUMNS {
+            if let Some(reading) = self.readings.iter().find(|r| r.id() == column.reading) {
+                env.push((column.name.to_ascii_uppercase(), reading.value.to_string()));
+            }
+        }
+        env.into_iter()
+            .map(|(name, value)| (format!("{}{}", ENV_PREFIX, name), value))
+            .collect()
+    }
+
+    /// The JSON document written to a run's stdin.
+    pub fn to_json(&self, hook: &Hook) -> String {
+        serde_json::json!({
+            "hook": hook.name,
+            "timestamp": format_rfc3339(self.timestamp),
+            "event": self.event.to_json(),
+            "stats": self.stats,
+        })
+        .to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookStatus {
+    /// The command exited with this code, or was ended by a signal for `None`.
+    Exited(Option<i32>),
+    TimedOut,
+    /// The command could not be started.
+    Failed(String),
+}
+
+impl fmt::Display for HookStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookStatus::Exited(Some(code)) => write!(f, "exited with {}", code),
+            HookStatus::Exited(None) => write!(f, "ended by a signal"),
+            HookStatus::TimedOut => write!(f, "timed out and was killed"),
+            HookStatus::Failed(message) => write!(f, "failed to start: {}", message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookOutput {
+    pub status: HookStatus,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration: Duration,
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut shell, 0);
+    shell
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Kills the command and, on Unix, every process it started.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: kill(2) has no memory-safety preconditions. The child leads its own process
+        // group, so the negated pid only reaches the hook's processes.
+        unsafe { libc::kill(-pid, libc::SIGKILL) };
+    }
+    if let Err(e) = child.kill() {
+        if e.kind() != io::ErrorKind::InvalidInput {
+            eprintln!("Failed to kill hook: {}", e);
+        }
+    }
+}
+
+fn capture(mut pipe: impl Read + Send + 'static) -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            match pipe.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => {
+                    let room = MAX_CAPTURED_OUTPUT_BYTES.saturating_sub(output.len());
+                    output.extend_from_slice(&buffer[..read.min(room)]);
+                }
+            }
+        }
+        let _ = sender.send(String::from_utf8_lossy(&output).into_owned());
+    });
+    receiver
+}
+
+/// Runs `hook` for `context` and waits for it to exit or time out.
+pub fn run_hook(hook: &Hook, context: &HookContext) -> HookOutput {
+    let start = Instant::now();
+    let spawned = shell_command(&hook.command)
+        .envs(context.env(hook))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            return HookOutput {
+                status: HookStatus::Failed(e.to_string()),
+                stdout: String::new(),
+                stderr: String::new(),
+                duration: start.elapsed(),
+            }
+        }
+    };
+
+    // Written from its own thread, so a command that never reads stdin cannot block the run.
+    if let Some(mut stdin) = child.stdin.take() {
+        let json = context.to_json(hook);
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(json.as_bytes());
+        });
+    }
+    let stdout = child.stdout.take().map(capture);
+    let stderr = child.stderr.take().map(capture);
+
+    let timeout = Duration::from_secs(hook.timeout_secs);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break HookStatus::Exited(status.code()),
+            Ok(None) if start.elapsed() >= timeout => {
+                kill(&mut child);
+                let _ = child.wait();
+                break HookStatus::TimedOut;
+            }
+            Ok(None) => std::thread::sleep(HOOK_POLL_INTERVAL),
+            Err(e) => {
+                kill(&mut child);
+                break HookStatus::Failed(e.to_string());
+            }
+        }
+    };
+
+    let collect = |output: Option<mpsc::Receiver<String>>| {
+        output
+            .and_then(|output| output.recv_timeout(OUTPUT_GRACE_PERIOD).ok())
+            .unwrap_or_default()
+    };
+    HookOutput {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+        duration: start.elapsed(),
+    }
+}
+
+/// A hook log entry: a header line, then the captured output indented below it.
+pub fn format_hook_log_entry(hook: &Hook, context: &HookContext, output: &HookOutput) -> String {
+    let mut entry = format!(
+        "[{}] {} ({}): {} after {:.1}s\n",
+        format_rfc3339(context.timestamp),
+        hook.name,
+        context.event,
+        output.status,
+        output.duration.as_secs_f64()
+    );
+    for (stream, text) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        for line in text.lines() {
+            entry.push_str(&format!("    {}: {}\n", stream, line));
+        }
+    }
+    entry
+}
+
+/// Appends `entry` to the log at `path`, first moving a log past [`MAX_HOOK_LOG_BYTES`] aside.
+pub fn append_hook_log(path: &Path, entry: &str) -> io::Result<()> {
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() > MAX_HOOK_LOG_BYTES) {
+        fs::rename(path, path.with_extension("log.old"))?;
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(entry.as_bytes())
+}
+
+#[derive(Debug)]
+struct HookSlot {
+    hook: Hook,
+    enabled: bool,
+    running: Arc<AtomicUsize>,
+}
+
+/// Decrements a running count when a hook run ends, however it ends.
+struct RunningGuard(Vec<Arc<AtomicUsize>>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        for count in &self.0 {
+            count.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// The configured hooks with their enabled toggles, and the events that start them.
+#[derive(Debug, Default)]
+pub struct HookRunner {
+    slots: Vec<HookSlot>,
+    running: Arc<AtomicUsize>,
+    last_battery_state: Option<String>,
+    log_path: Option<PathBuf>,
+}
+
+impl HookRunner {
+    pub fn new(hooks: Vec<Hook>, log_path: Option<PathBuf>) -> Self {
+        let mut runner = Self {
+            log_path,
+            ..Default::default()
+        };
+        runner.set_hooks(hooks);
+        runner
+    }
+
+    /// Replaces the hooks. Hooks that did not change keep their toggle and running count.
+    pub fn set_hooks(&mut self, hooks: Vec<Hook>) {
+        let mut previous = std::mem::take(&mut self.slots);
+        self.slots = hooks
+            .into_iter()
+            .map(
+                |hook| match previous.iter().position(|slot| slot.hook == hook) {
+                    Some(index) => previous.swap_remove(index),
+                    None => HookSlot {
+                        enabled: hook.enabled,
+                        hook,
+                        running: Arc::default(),
+                    },
+                },
+            )
+            .collect();
+    }
+
+    /// The hooks in config order, each with whether it is enabled.
+    pub fn hooks(&self) -> impl Iterator<Item = (&Hook, bool)> {
+        self.slots.iter().map(|slot| (&slot.hook, slot.enabled))
+    }
+
+    /// Flips the hook at `index` on or off, returning whether it is now enabled.
+    pub fn toggle(&mut self, index: usize) -> Option<bool> {
+        let slot = self.slots.get_mut(index)?;
+        slot.enabled = !slot.enabled;
+        Some(slot.enabled)
+    }
+
+    /// The hook events in a new sample: its alerts, and a change of the battery state.
+    pub fn events(&mut self, alerts: &[Alert], stats: &SystemStats) -> Vec<HookEvent> {
+        let mut events: Vec<_> = alerts.iter().cloned().map(HookEvent::Alert).collect();
+
+        let state = (!stats.batteries.is_empty()).then(|| stats.battery_state.clone());
+        if let (Some(from), Some(to)) = (&self.last_battery_state, &state) {
+            if from != to {
+                events.push(HookEvent::BatteryState {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+        }
+        self.last_battery_state = state;
+        events
+    }
+
+    /// Starts every enabled hook that matches the context's event on a thread of its own. Hooks
+    /// at their concurrency limit are skipped and the skip is logged.
+    pub fn dispatch(&self, context: &HookContext) {
+        for slot in &self.slots {
+            if !slot.enabled || !slot.hook.matches(&context.event) {
+                continue;
+            }
+            if slot.running.load(Ordering::SeqCst) >= slot.hook.max_concurrent
+                || self.running.load(Ordering::SeqCst) >= MAX_RUNNING_HOOKS
+            {
+                self.log(&format!(
+                    "[{}] {} ({}): skipped, too many runs in progress\n",
+                    format_rfc3339(context.timestamp),
+                    slot.hook.name,
+                    context.event
+                ));
+                continue;
+            }
+
+            slot.running.fetch_add(1, Ordering::SeqCst);
+            self.running.fetch_add(1, Ordering::SeqCst);
+            let guard = RunningGuard(vec![slot.running.clone(), self.running.clone()]);
+            let hook = slot.hook.clone();
+            let context = context.clone();
+            let log_path = self.log_path.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("hook-{}", hook.name))
+                .spawn(move || {
+                    let _guard = guard;
+                    let output = run_hook(&hook, &context);
+                    let entry = format_hook_log_entry(&hook, &context, &output);
+                    write_log(log_path.as_deref(), &entry);
+                });
+            if let Err(e) = spawned {
+                eprintln!("Failed to spawn hook thread: {}", e);
+            }
+        }
+    }
+
+    fn log(&self, entry: &str) {
+        write_log(self.log_path.as_deref(), entry);
+    }
+}
+
+fn write_log(path: Option<&Path>, entry: &str) {
+    match path {
+        Some(path) => {
+            if let Err(e) = append_hook_log(path, entry) {
+                eprintln!("Failed to write hook log {}: {}", path.display(), e);
+            }
+        }
+        None => eprint!("{}", entry),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{BatteryStats, Unit};
+
+    fn hook(command: &str) -> Hook {
+        Hook {
+            name: "Test".to_string(),
+            command: command.to_string(),
+            triggers: vec![HookTrigger::Alert(None)],
+            timeout_secs: 5,
+            max_concurrent: 1,
+            enabled: true,
+        }
+    }
+
+    fn alert(rule: &str) -> Alert {
+        Alert {
+            rule: rule.to_string(),
+            message: "mem.pct is 96.0% (> 95.0%)".to_string(),
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    fn context(event: HookEvent) -> HookContext {
+        HookContext {
+            event,
+            timestamp: 1_700_000_000,
+            stats: SystemStats {
+                memory_percent: 96.0,
+                ..Default::default()
+            },
+            readings: vec![Reading::new("memory.percent", 96.0, Unit::Percent)],
+        }
+    }
+
+    #[test]
+    fn test_hook_matching_and_events() {
+        let hook = Hook {
+            triggers: vec![
+                HookTrigger::Alert(Some("Memory almost full".to_string())),
+                HookTrigger::BatteryState("Discharging".to_string()),
+            ],
+            ..hook("true")
+        };
+        assert!(hook.matches(&HookEvent::Alert(alert("Memory almost full"))));
+        assert!(!hook.matches(&HookEvent::Alert(alert("CPU busy"))));
+        let discharging = HookEvent::BatteryState {
+            from: "Charging".to_string(),
+            to: "Discharging".to_string(),
+        };
+        assert!(hook.matches(&discharging));
+        assert_eq!(discharging.to_string(), "battery Charging -> Discharging");
+
+        let mut runner = HookRunner::new(vec![hook.clone()], None);
+        let battery = |state: &str| SystemStats {
+            battery_state: state.to_string(),
+            batteries: vec![BatteryStats::default()],
+            ..Default::default()
+        };
+        assert!(runner.events(&[], &battery("Charging")).is_empty());
+        assert_eq!(runner.events(&[], &battery("Discharging")), [discharging]);
+        assert_eq!(
+            runner.events(&[alert("CPU busy")], &battery("Discharging")),
+            [HookEvent::Alert(alert("CPU busy"))]
+        );
+        assert!(runner.events(&[], &SystemStats::default()).is_empty());
+
+        // Toggles survive reloading the same hooks, but not a changed hook.
+        assert_eq!(runner.toggle(0), Some(false));
+        assert_eq!(runner.toggle(1), None);
+        runner.set_hooks(vec![hook.clone()]);
+        assert_eq!(
+            runner.hooks().next().map(|(_, enabled)| enabled),
+            Some(false)
+        );
+        runner.set_hooks(vec![Hook {
+            timeout_secs: 10,
+            ..hook
+        }]);
+        assert_eq!(
+            runner.hooks().next().map(|(_, enabled)| enabled),
+            Some(true)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_hook() {
+        let context = context(HookEvent::Alert(alert("Memory almost full")));
+        let output = run_hook(
+            &hook(
+                "echo \"$SYSTEM_STATS_EVENT $SYSTEM_STATS_ALERT_RULE $SYSTEM_STATS_MEMORY_PERCENT\"; \
+                 head -c 9; echo oops >&2; exit 3",
+            ),
+            &context,
+        );
+        assert_eq!(output.status, HookStatus::Exited(Some(3)));
+        assert_eq!(output.stdout, "alert Memory almost full 96\n{\"event\":");
+        assert_eq!(output.stderr, "oops\n");
+
+        let entry = format_hook_log_entry(&hook("true"), &context, &output);
+        assert!(
+            entry.starts_with(
+                "[2023-11-14T22:13:20Z] Test (alert \"Memory almost full\"): exited with 3 after "
+            ),
+            "{}",
+            entry
+        );
+        assert!(entry.ends_with("    stdout: {\"event\":\n    stderr: oops\n"));
+
+        let started = Instant::now();
+        let output = run_hook(
+            &Hook {
+                timeout_secs: 1,
+                ..hook("sleep 30 & sleep 30")
+            },
+            &context,
+        );
+        assert_eq!(output.status, HookStatus::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&context.to_json(&hook("true"))).unwrap();
+        assert_eq!(json["event"]["rule"], "Memory almost full");
+        assert_eq!(json["stats"]["memory_percent"], 96.0);
+    }
+
+    #[test]
+    fn test_append_hook_log() {
+        let dir = std::env::temp_dir().join(format!("system-stats-hooks-{}", std::process::id()));
+        let path = dir.join(HOOK_LOG_FILE_NAME);
+        let _ = fs::remove_dir_all(&dir);
+
+        append_hook_log(&path, "first\n").unwrap();
+        append_hook_log(&path, "second\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+
+        fs::write(&path, vec![b'x'; MAX_HOOK_LOG_BYTES as usize + 1]).unwrap();
+        append_hook_log(&path, "third\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n");
+        assert!(dir.join("hooks.log.old").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
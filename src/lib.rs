@@ -1,4 +1,42 @@
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    eprintln!("This application is designed for macOS menu bar only");
-}
+//! UI-free system statistics engine shared by the menu bar app.
+//!
+//! Keep a [`Registry`] around between ticks so collectors can compute rates and deltas, call
+//! [`Registry::refresh`], then read a [`SystemStats`] snapshot with [`Registry::stats`] or the
+//! flat [`Reading`]s with [`Registry::readings`]. The formatters in [`mod@format`] render both.
+//! Implement [`Collector`] and pass it to [`Registry::register`] to add a metric.
+
+pub mod alert;
+pub mod battery;
+pub mod collect;
+pub mod config;
+pub mod export;
+pub mod format;
+pub mod history;
+pub mod hook;
+pub mod menu;
+pub mod metrics;
+pub mod process;
+pub mod registry;
+pub mod rule;
+pub mod stats;
+pub mod store;
+pub mod template;
+
+pub use alert::{Alert, AlertCondition, AlertEngine, AlertRule};
+pub use battery::{BatteryMonitor, BatteryPolicy, BatteryReminder};
+pub use config::{Config, ConfigError};
+pub use export::{ExportFormat, ExportRow};
+pub use format::{format_tray_title, CpuTraySegment, TemperatureUnit, TrayOptions};
+pub use history::{History, Summary};
+pub use hook::{Hook, HookEvent, HookRunner, HookTrigger};
+pub use menu::MenuEntry;
+pub use metrics::{ExpositionFormat, MetricsServer};
+pub use registry::{Collector, Registry};
+pub use rule::{Expression, RuleError};
+pub use stats::{
+    BatteryDetails, BatteryStats, DiskStats, DiskVolumeStats, MemoryPressure,
+    NetworkInterfaceStats, NetworkStats, ProcessStats, Reading, SystemStats, TemperatureStats,
+    Unit,
+};
+pub use store::{Point, Sample, TimeSeriesStore};
+pub use template::TrayTemplate;
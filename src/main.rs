@@ -1,268 +1,1463 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use serde::{Deserialize, Serialize};
-use starship_battery::State as BatteryState;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use sysinfo::System;
-use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::Pid;
+use system_stats_lib::collect::TOP_PROCESS_COUNT;
+use system_stats_lib::config::CONFIG_FILE_NAME;
+use system_stats_lib::export::{format_rfc3339, write_export_file};
+use system_stats_lib::format::{
+    format_duration_short, format_process_cpu_text, format_process_memory_text,
+};
+use system_stats_lib::hook::{HookContext, HOOK_LOG_FILE_NAME};
+use system_stats_lib::menu::find_menu_entry;
+use system_stats_lib::process::{
+    check_process_action_allowed, send_process_signal, ProcessActionError, ProcessSignal,
+};
+use system_stats_lib::store::{unix_timestamp, STORE_DIR_NAME};
+use system_stats_lib::{
+    Alert, AlertEngine, BatteryMonitor, Config, ConfigError, CpuTraySegment, ExportFormat,
+    ExportRow, Hook, HookRunner, MenuEntry, MetricsServer, ProcessStats, Reading, Registry,
+    SystemStats, TimeSeriesStore, TrayOptions,
+};
+use tauri::menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::tray::TrayIconBuilder;
 use tauri::Manager;
 use tauri_plugin_autostart::ManagerExt;
 use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_notification::NotificationExt;
 
-const UPDATE_INTERVAL_SECS: u64 = 3;
-const BYTES_TO_GB: f32 = 1024.0 * 1024.0 * 1024.0;
+const PROCESS_ACTION_CONFIRM_TIMEOUT_SECS: u64 = 15;
+const CONFIG_POLL_INTERVAL_SECS: u64 = 2;
 const TRAY_ID: &str = "menu_bar_stats_tray";
 
-const MENU_BATTERY: &str = "battery";
-const MENU_CPU: &str = "cpu";
-const MENU_MEMORY: &str = "memory";
+const MENU_TOP_CPU: &str = "top_cpu";
+const MENU_TOP_CPU_PREFIX: &str = "top_cpu_";
+const MENU_TOP_MEMORY: &str = "top_memory";
+const MENU_TOP_MEMORY_PREFIX: &str = "top_memory_";
+const MENU_PROCESS_CONFIRM: &str = "process_confirm";
+const MENU_PROCESS_STATUS: &str = "process_status";
+const MENU_CONFIG_ERRORS: &str = "config_errors";
+const MENU_CONFIG_ERROR_PREFIX: &str = "config_error_";
+const MENU_RECENT_ALERTS: &str = "recent_alerts";
+const MENU_RECENT_ALERT_PREFIX: &str = "recent_alert_";
+const MENU_EXPORT: &str = "history_export";
+const MENU_EXPORT_PREFIX: &str = "export_";
+const MENU_LOAD_AVERAGE_TRAY: &str = "load_average_tray";
+const MENU_TEMPERATURE_TRAY: &str = "temperature_tray";
+const MENU_HOOKS: &str = "hooks";
+const MENU_HOOK_PREFIX: &str = "hook_";
 const MENU_AUTOSTART: &str = "autostart";
 const MENU_QUIT: &str = "quit";
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct SystemStats {
-    cpu_usage: f32,
-    memory_used: u64,
-    memory_total: u64,
-    memory_percent: f32,
-    battery_percent: f32,
-    battery_state: String,
+/// Time windows offered by the export submenu: id key, label and length in seconds.
+const EXPORT_WINDOWS: [(&str, &str, u64); 4] = [
+    ("hour", "Last Hour", 60 * 60),
+    ("day", "Last Day", 24 * 60 * 60),
+    ("week", "Last Week", 7 * 24 * 60 * 60),
+    ("month", "Last 30 Days", 30 * 24 * 60 * 60),
+];
+const EXPORT_FORMATS: [(ExportFormat, &str); 2] = [
+    (ExportFormat::Csv, "CSV"),
+    (ExportFormat::JsonLines, "JSON Lines"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcessMenuAction {
+    Copy,
+    Signal(ProcessSignal),
+}
+
+const PROCESS_MENU_ACTIONS: [ProcessMenuAction; 5] = [
+    ProcessMenuAction::Copy,
+    ProcessMenuAction::Signal(ProcessSignal::Terminate),
+    ProcessMenuAction::Signal(ProcessSignal::Kill),
+    ProcessMenuAction::Signal(ProcessSignal::Stop),
+    ProcessMenuAction::Signal(ProcessSignal::Continue),
+];
+
+impl ProcessMenuAction {
+    fn key(self) -> &'static str {
+        match self {
+            ProcessMenuAction::Copy => "copy",
+            ProcessMenuAction::Signal(ProcessSignal::Terminate) => "sigterm",
+            ProcessMenuAction::Signal(ProcessSignal::Kill) => "sigkill",
+            ProcessMenuAction::Signal(ProcessSignal::Stop) => "sigstop",
+            ProcessMenuAction::Signal(ProcessSignal::Continue) => "sigcont",
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            ProcessMenuAction::Copy => "Copy".to_string(),
+            ProcessMenuAction::Signal(signal) => format!("{} ({})", signal.label(), signal.name()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PendingProcessAction {
+    pid: u32,
+    name: String,
+    signal: ProcessSignal,
+    requested_at: Instant,
+}
+
+impl PendingProcessAction {
+    fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.requested_at)
+            >= Duration::from_secs(PROCESS_ACTION_CONFIRM_TIMEOUT_SECS)
+    }
+}
+
+/// The processes in the top CPU and memory submenus, as last shown. A click resolves its slot
+/// against these rather than the latest sample, which may have re-ranked the processes since.
+#[derive(Debug, Default)]
+struct ShownProcesses {
+    cpu: Vec<ProcessStats>,
+    memory: Vec<ProcessStats>,
+}
+
+#[derive(Default)]
+struct ProcessActionState {
+    pending: Option<PendingProcessAction>,
+    shown: ShownProcesses,
 }
 
 struct AppState {
-    system: Mutex<System>,
+    registry: Mutex<Registry>,
+    tray_options: Mutex<TrayOptions>,
+    process_action: Mutex<ProcessActionState>,
+    /// `None` while storage is disabled or the store failed to open.
+    store: Mutex<Option<TimeSeriesStore>>,
+    alerts: Mutex<AlertEngine>,
+    battery: Mutex<BatteryMonitor>,
+    hooks: Mutex<HookRunner>,
+    /// `None` while the metrics endpoint is disabled or failed to start.
+    metrics_server: Mutex<Option<MetricsServer>>,
+}
+
+fn format_pending_process_action(pending: &PendingProcessAction) -> String {
+    format!(
+        "⚠️ Confirm {} of {} ({})",
+        pending.signal.name(),
+        pending.name,
+        pending.pid
+    )
 }
 
-fn collect_system_stats(sys: &mut System) -> SystemStats {
-    sys.refresh_cpu();
-    sys.refresh_memory();
+fn format_process_action_result(
+    name: &str,
+    pid: u32,
+    signal: ProcessSignal,
+    result: &Result<(), ProcessActionError>,
+) -> String {
+    match result {
+        Ok(()) => format!("✅ Sent {} to {} ({})", signal.name(), name, pid),
+        Err(e) => format!("❌ {} {} ({}): {}", signal.name(), name, pid, e),
+    }
+}
 
-    let cpu_usage = sys.global_cpu_info().cpu_usage().clamp(0.0, 100.0);
+fn process_action_menu_id(prefix: &str, index: usize, action: ProcessMenuAction) -> String {
+    format!("{}{}_{}", prefix, index, action.key())
+}
 
-    let memory_total = sys.total_memory();
-    let memory_available = sys.available_memory().min(memory_total);
-    let memory_used = memory_total - memory_available;
+fn parse_process_action_menu_id(
+    prefix: &str,
+    event_id: &str,
+) -> Option<(usize, ProcessMenuAction)> {
+    let (index, key) = event_id.strip_prefix(prefix)?.split_once('_')?;
+    let action = PROCESS_MENU_ACTIONS
+        .into_iter()
+        .find(|action| action.key() == key)?;
+
+    Some((index.parse().ok()?, action))
+}
+
+type ProcessFormatter = fn(&ProcessStats) -> String;
+
+fn lookup_process_action<'a>(
+    shown: &'a ShownProcesses,
+    event_id: &str,
+) -> Option<(&'a ProcessStats, ProcessMenuAction, ProcessFormatter)> {
+    let lists: [(&str, &[ProcessStats], ProcessFormatter); 2] = [
+        (MENU_TOP_CPU_PREFIX, &shown.cpu, format_process_cpu_text),
+        (
+            MENU_TOP_MEMORY_PREFIX,
+            &shown.memory,
+            format_process_memory_text,
+        ),
+    ];
+
+    lists.into_iter().find_map(|(prefix, processes, format)| {
+        let (index, action) = parse_process_action_menu_id(prefix, event_id)?;
+        Some((processes.get(index)?, action, format))
+    })
+}
 
-    let memory_percent = if memory_total > 0 {
-        ((memory_used as f64 / memory_total as f64) * 100.0).clamp(0.0, 100.0) as f32
+/// Looks up the clicked process among those shown in the menu.
+fn shown_process_action<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    event_id: &str,
+) -> Option<(ProcessStats, ProcessMenuAction, ProcessFormatter)> {
+    let state = app.try_state::<AppState>()?;
+    let process_action = match state.process_action.lock() {
+        Ok(process_action) => process_action,
+        Err(e) => {
+            eprintln!("Failed to lock process action mutex: {}", e);
+            return None;
+        }
+    };
+    lookup_process_action(&process_action.shown, event_id)
+        .map(|(process, action, format)| (process.clone(), action, format))
+}
+
+fn indexed_menu_id(prefix: &str, index: usize) -> String {
+    format!("{}{}", prefix, index)
+}
+
+fn parse_indexed_menu_id(prefix: &str, event_id: &str) -> Option<usize> {
+    event_id.strip_prefix(prefix)?.parse().ok()
+}
+
+fn update_process_submenus<R: tauri::Runtime>(submenus: &[Submenu<R>], texts: Vec<String>) {
+    let mut texts = texts.into_iter();
+    for submenu in submenus {
+        let text = texts.next();
+        let enabled = text.is_some();
+        if let Err(e) = submenu.set_text(text.unwrap_or_else(|| "—".to_string())) {
+            eprintln!("Failed to update process submenu: {}", e);
+        }
+        if let Err(e) = submenu.set_enabled(enabled) {
+            eprintln!("Failed to update process submenu: {}", e);
+        }
+    }
+}
+
+fn build_process_submenu<R: tauri::Runtime, M: Manager<R>>(
+    manager: &M,
+    id: &str,
+    text: &str,
+    item_prefix: &str,
+) -> tauri::Result<(Submenu<R>, Vec<Submenu<R>>)> {
+    let slots = (0..TOP_PROCESS_COUNT)
+        .map(|index| {
+            let actions = PROCESS_MENU_ACTIONS
+                .into_iter()
+                .map(|action| {
+                    MenuItem::with_id(
+                        manager,
+                        process_action_menu_id(item_prefix, index, action),
+                        action.label(),
+                        true,
+                        None::<&str>,
+                    )
+                })
+                .collect::<tauri::Result<Vec<_>>>()?;
+            let action_refs: Vec<&dyn IsMenuItem<R>> = actions
+                .iter()
+                .map(|item| item as &dyn IsMenuItem<R>)
+                .collect();
+            Submenu::with_id_and_items(
+                manager,
+                indexed_menu_id(item_prefix, index),
+                "Loading...",
+                true,
+                &action_refs,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let slot_refs: Vec<&dyn IsMenuItem<R>> = slots
+        .iter()
+        .map(|slot| slot as &dyn IsMenuItem<R>)
+        .collect();
+    let submenu = Submenu::with_id_and_items(manager, id, text, true, &slot_refs)?;
+
+    Ok((submenu, slots))
+}
+
+fn export_menu_id(window: &str, format: ExportFormat) -> String {
+    format!("{}{}_{}", MENU_EXPORT_PREFIX, window, format.extension())
+}
+
+fn parse_export_menu_id(event_id: &str) -> Option<(u64, ExportFormat)> {
+    let (window, extension) = event_id.strip_prefix(MENU_EXPORT_PREFIX)?.split_once('_')?;
+    let (_, _, window_secs) = EXPORT_WINDOWS.iter().find(|(key, _, _)| *key == window)?;
+    let (format, _) = EXPORT_FORMATS
+        .iter()
+        .find(|(format, _)| format.extension() == extension)?;
+    Some((*window_secs, *format))
+}
+
+fn build_export_submenu<R: tauri::Runtime, M: Manager<R>>(
+    manager: &M,
+) -> tauri::Result<Submenu<R>> {
+    let mut items = Vec::new();
+    for (window, window_label, _) in EXPORT_WINDOWS {
+        for (format, format_label) in EXPORT_FORMATS {
+            items.push(MenuItem::with_id(
+                manager,
+                export_menu_id(window, format),
+                format!("{} as {}", window_label, format_label),
+                true,
+                None::<&str>,
+            )?);
+        }
+    }
+    let item_refs: Vec<&dyn IsMenuItem<R>> = items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<R>)
+        .collect();
+    Submenu::with_id_and_items(manager, MENU_EXPORT, "📤 Export History", true, &item_refs)
+}
+
+fn export_file_name(timestamp: u64, format: ExportFormat) -> String {
+    // Colons are not allowed in file names on every platform.
+    format!(
+        "system-stats-{}.{}",
+        format_rfc3339(timestamp).replace(':', ""),
+        format.extension()
+    )
+}
+
+/// Writes the stored history of the last `window_secs` to the downloads directory and copies
+/// the file's path, ready to attach to a bug report.
+fn export_history<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    window_secs: u64,
+    format: ExportFormat,
+) {
+    let Some(state) = app.try_state::<AppState>() else {
+        eprintln!("Failed to get app state");
+        return;
+    };
+    let now = unix_timestamp(SystemTime::now());
+    let samples = match state.store.lock() {
+        Ok(store) => match store.as_ref() {
+            Some(store) => store.samples(now.saturating_sub(window_secs)..now + 1),
+            None => {
+                eprintln!("Stats storage is disabled, nothing to export");
+                return;
+            }
+        },
+        Err(e) => {
+            eprintln!("Failed to lock stats store mutex: {}", e);
+            return;
+        }
+    };
+    let rows: Vec<ExportRow> = match samples {
+        Ok(samples) => samples.iter().map(ExportRow::from).collect(),
+        Err(e) => {
+            eprintln!("Failed to read stored history: {}", e);
+            return;
+        }
+    };
+
+    let dir = match app.path().download_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("Failed to resolve downloads directory: {}", e);
+            return;
+        }
+    };
+    let path = dir.join(export_file_name(now, format));
+    if let Err(e) = write_export_file(&path, format, &rows) {
+        eprintln!("Failed to export history to {}: {}", path.display(), e);
+        return;
+    }
+
+    if let Err(e) = app.clipboard().write_text(path.display().to_string()) {
+        eprintln!("Failed to write to clipboard: {}", e);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SubmenuEntry {
+    id: String,
+    text: String,
+    enabled: bool,
+}
+
+struct DynamicSubmenu<R: tauri::Runtime> {
+    submenu: Submenu<R>,
+    items: Vec<(String, MenuItem<R>)>,
+}
+
+/// Updates the submenu in place while its entries keep the same ids, and rebuilds it
+/// whenever entries are added, removed or reordered.
+fn sync_dynamic_submenu<R: tauri::Runtime, M: Manager<R>>(
+    manager: &M,
+    menu: &mut DynamicSubmenu<R>,
+    entries: &[SubmenuEntry],
+) {
+    let unchanged = menu.items.len() == entries.len()
+        && menu
+            .items
+            .iter()
+            .zip(entries)
+            .all(|((id, _), entry)| id == &entry.id);
+
+    if unchanged {
+        for ((_, item), entry) in menu.items.iter().zip(entries) {
+            if let Err(e) = item.set_text(&entry.text) {
+                eprintln!("Failed to update submenu item: {}", e);
+            }
+        }
+        return;
+    }
+
+    for (_, item) in menu.items.drain(..) {
+        if let Err(e) = menu.submenu.remove(&item) {
+            eprintln!("Failed to remove submenu item: {}", e);
+        }
+    }
+
+    for entry in entries {
+        let item =
+            match MenuItem::with_id(manager, &entry.id, &entry.text, entry.enabled, None::<&str>) {
+                Ok(item) => item,
+                Err(e) => {
+                    eprintln!("Failed to create submenu item: {}", e);
+                    continue;
+                }
+            };
+        if let Err(e) = menu.submenu.append(&item) {
+            eprintln!("Failed to append submenu item: {}", e);
+            continue;
+        }
+        menu.items.push((entry.id.clone(), item));
+    }
+}
+
+/// A dynamic submenu that is only shown in the tray menu while it has entries, right after the
+/// item with id `after`.
+struct OptionalSubmenu<R: tauri::Runtime> {
+    menu: DynamicSubmenu<R>,
+    after: &'static str,
+    visible: bool,
+}
+
+fn sync_optional_submenu<R: tauri::Runtime, M: Manager<R>>(
+    manager: &M,
+    tray_menu: &Menu<R>,
+    optional: &mut OptionalSubmenu<R>,
+    entries: &[SubmenuEntry],
+) {
+    sync_dynamic_submenu(manager, &mut optional.menu, entries);
+
+    let show = !entries.is_empty();
+    if show == optional.visible {
+        return;
+    }
+
+    let result = if show {
+        tray_menu.items().and_then(|items| {
+            let position = items
+                .iter()
+                .position(|item| item.id() == optional.after)
+                .map_or(items.len(), |index| index + 1);
+            tray_menu.insert(&optional.menu.submenu, position)
+        })
     } else {
-        0.0
+        tray_menu.remove(&optional.menu.submenu)
     };
+    match result {
+        Ok(()) => optional.visible = show,
+        Err(e) => eprintln!("Failed to toggle submenu: {}", e),
+    }
+}
+
+/// A top-level tray menu item built from a collector's [`MenuEntry`].
+enum CollectorMenuItem<R: tauri::Runtime> {
+    Item(MenuItem<R>),
+    Submenu(DynamicSubmenu<R>),
+}
 
-    let (battery_percent, battery_state) = get_battery_info();
+impl<R: tauri::Runtime> CollectorMenuItem<R> {
+    fn as_menu_item(&self) -> &dyn IsMenuItem<R> {
+        match self {
+            CollectorMenuItem::Item(item) => item,
+            CollectorMenuItem::Submenu(menu) => &menu.submenu,
+        }
+    }
 
-    SystemStats {
-        cpu_usage,
-        memory_used,
-        memory_total,
-        memory_percent,
-        battery_percent: battery_percent.clamp(0.0, 100.0),
-        battery_state,
+    fn matches(&self, id: &str, entry: &MenuEntry) -> bool {
+        let is_item = matches!(self, CollectorMenuItem::Item(_));
+        id == entry.id && is_item == entry.children.is_empty()
     }
 }
 
-fn get_battery_info() -> (f32, String) {
-    match starship_battery::Manager::new() {
-        Ok(manager) => match manager.batteries() {
-            Ok(mut batteries) => {
-                if let Some(Ok(battery)) = batteries.next() {
-                    let percent = battery.state_of_charge().value * 100.0;
-                    let state = format_battery_state(battery.state());
-                    (percent, state)
-                } else {
-                    (0.0, "No Battery".to_string())
+/// Entries without children are plain items, enabled when there is something to copy.
+fn submenu_entries(entries: &[MenuEntry]) -> Vec<SubmenuEntry> {
+    entries
+        .iter()
+        .map(|entry| SubmenuEntry {
+            id: entry.id.clone(),
+            text: entry.text.clone(),
+            enabled: entry.copy_text.is_some(),
+        })
+        .collect()
+}
+
+fn build_collector_menu_item<R: tauri::Runtime, M: Manager<R>>(
+    manager: &M,
+    entry: &MenuEntry,
+) -> tauri::Result<CollectorMenuItem<R>> {
+    if entry.children.is_empty() {
+        let enabled = entry.copy_text.is_some();
+        let item = MenuItem::with_id(manager, &entry.id, &entry.text, enabled, None::<&str>)?;
+        return Ok(CollectorMenuItem::Item(item));
+    }
+
+    let mut menu = DynamicSubmenu {
+        submenu: Submenu::with_id(manager, &entry.id, &entry.text, true)?,
+        items: Vec::new(),
+    };
+    sync_dynamic_submenu(manager, &mut menu, &submenu_entries(&entry.children));
+    Ok(CollectorMenuItem::Submenu(menu))
+}
+
+/// Updates the collectors' items at the top of the tray menu in place while they keep the same
+/// ids and kinds, and rebuilds them whenever entries are added, removed or reordered.
+fn sync_collector_menu<R: tauri::Runtime, M: Manager<R>>(
+    manager: &M,
+    tray_menu: &Menu<R>,
+    items: &mut Vec<(String, CollectorMenuItem<R>)>,
+    entries: &[MenuEntry],
+) {
+    let unchanged = items.len() == entries.len()
+        && items
+            .iter()
+            .zip(entries)
+            .all(|((id, item), entry)| item.matches(id, entry));
+
+    if unchanged {
+        for ((_, item), entry) in items.iter_mut().zip(entries) {
+            let result = match item {
+                CollectorMenuItem::Item(item) => item
+                    .set_text(&entry.text)
+                    .and_then(|()| item.set_enabled(entry.copy_text.is_some())),
+                CollectorMenuItem::Submenu(menu) => {
+                    sync_dynamic_submenu(manager, menu, &submenu_entries(&entry.children));
+                    menu.submenu.set_text(&entry.text)
                 }
+            };
+            if let Err(e) = result {
+                eprintln!("Failed to update collector menu item: {}", e);
             }
+        }
+        return;
+    }
+
+    for (_, item) in items.drain(..) {
+        if let Err(e) = tray_menu.remove(item.as_menu_item()) {
+            eprintln!("Failed to remove collector menu item: {}", e);
+        }
+    }
+
+    for entry in entries {
+        let item = match build_collector_menu_item(manager, entry) {
+            Ok(item) => item,
             Err(e) => {
-                eprintln!("Failed to get batteries: {}", e);
-                (0.0, "Unknown".to_string())
+                eprintln!("Failed to create collector menu item: {}", e);
+                continue;
             }
+        };
+        if let Err(e) = tray_menu.insert(item.as_menu_item(), items.len()) {
+            eprintln!("Failed to insert collector menu item: {}", e);
+            continue;
+        }
+        items.push((entry.id.clone(), item));
+    }
+}
+
+struct StatsMenu<R: tauri::Runtime> {
+    /// The registry's menu entries, shown at the top of the tray menu.
+    collectors: Vec<(String, CollectorMenuItem<R>)>,
+    top_cpu: Vec<Submenu<R>>,
+    top_memory: Vec<Submenu<R>>,
+    process_actions: ProcessActionMenu<R>,
+    tray_menu: Menu<R>,
+    config_errors: OptionalSubmenu<R>,
+    recent_alerts: OptionalSubmenu<R>,
+    hooks: Submenu<R>,
+    load_average_tray: CheckMenuItem<R>,
+    temperature_tray: CheckMenuItem<R>,
+}
+
+struct ProcessActionMenu<R: tauri::Runtime> {
+    confirm: MenuItem<R>,
+    status: MenuItem<R>,
+}
+
+impl<R: tauri::Runtime> Clone for ProcessActionMenu<R> {
+    fn clone(&self) -> Self {
+        Self {
+            confirm: self.confirm.clone(),
+            status: self.status.clone(),
+        }
+    }
+}
+
+impl<R: tauri::Runtime> ProcessActionMenu<R> {
+    fn set_pending(&self, pending: Option<&PendingProcessAction>) {
+        let (text, enabled) = match pending {
+            Some(pending) => (format_pending_process_action(pending), true),
+            None => ("No pending process action".to_string(), false),
+        };
+
+        if let Err(e) = self.confirm.set_text(text) {
+            eprintln!("Failed to update process confirm menu item: {}", e);
+        }
+        if let Err(e) = self.confirm.set_enabled(enabled) {
+            eprintln!("Failed to update process confirm menu item: {}", e);
+        }
+    }
+
+    fn set_status(&self, text: String) {
+        if let Err(e) = self.status.set_text(text) {
+            eprintln!("Failed to update process status menu item: {}", e);
+        }
+    }
+}
+
+fn update_menu_items<R: tauri::Runtime, M: Manager<R>>(
+    manager: &M,
+    entries: &[MenuEntry],
+    stats: &SystemStats,
+    menu: &mut StatsMenu<R>,
+) {
+    sync_collector_menu(manager, &menu.tray_menu, &mut menu.collectors, entries);
+
+    update_process_submenus(
+        &menu.top_cpu,
+        stats
+            .top_cpu_processes
+            .iter()
+            .map(format_process_cpu_text)
+            .collect(),
+    );
+    update_process_submenus(
+        &menu.top_memory,
+        stats
+            .top_memory_processes
+            .iter()
+            .map(format_process_memory_text)
+            .collect(),
+    );
+
+    match manager.try_state::<AppState>() {
+        Some(state) => match state.process_action.lock() {
+            Ok(mut process_action) => {
+                process_action.shown = ShownProcesses {
+                    cpu: stats.top_cpu_processes.clone(),
+                    memory: stats.top_memory_processes.clone(),
+                }
+            }
+            Err(e) => eprintln!("Failed to lock process action mutex: {}", e),
         },
+        None => eprintln!("Failed to get app state"),
+    }
+}
+
+/// The copy text of the collector menu entry with `event_id`, looked up in fresh entries so the
+/// value is the latest one.
+fn collector_copy_text<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    event_id: &str,
+) -> Option<String> {
+    let options = current_tray_options(app);
+    let state = app.try_state::<AppState>()?;
+    let entries = match state.registry.lock() {
+        Ok(registry) => registry.menu_entries(&options),
         Err(e) => {
-            eprintln!("Failed to create battery manager: {}", e);
-            (0.0, "Unknown".to_string())
+            eprintln!("Failed to lock registry mutex: {}", e);
+            return None;
         }
+    };
+    find_menu_entry(&entries, event_id)?.copy_text.clone()
+}
+
+fn handle_menu_click<R: tauri::Runtime>(app: &tauri::AppHandle<R>, event_id: &str) {
+    let text = match collector_copy_text(app, event_id) {
+        Some(text) => text,
+        None => match shown_process_action(app, event_id) {
+            Some((process, ProcessMenuAction::Copy, format)) => format(&process),
+            _ => return,
+        },
+    };
+
+    if let Err(e) = app.clipboard().write_text(text) {
+        eprintln!("Failed to write to clipboard: {}", e);
     }
 }
 
-fn format_battery_state(state: BatteryState) -> String {
-    match state {
-        BatteryState::Charging => "Charging".to_string(),
-        BatteryState::Discharging => "Discharging".to_string(),
-        BatteryState::Full => "Full".to_string(),
-        BatteryState::Empty => "Empty".to_string(),
-        _ => "Unknown".to_string(),
+fn current_tray_options<R: tauri::Runtime, M: Manager<R>>(manager: &M) -> TrayOptions {
+    manager
+        .try_state::<AppState>()
+        .and_then(|state| state.tray_options.lock().ok().map(|options| *options))
+        .unwrap_or_default()
+}
+
+fn toggle_load_average(options: &mut TrayOptions) -> bool {
+    options.cpu_segment = match options.cpu_segment {
+        CpuTraySegment::Usage => CpuTraySegment::LoadAverage,
+        CpuTraySegment::LoadAverage => CpuTraySegment::Usage,
+    };
+    options.cpu_segment == CpuTraySegment::LoadAverage
+}
+
+fn toggle_temperature(options: &mut TrayOptions) -> bool {
+    options.show_temperature = !options.show_temperature;
+    options.show_temperature
+}
+
+fn toggle_tray_option<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    item: &CheckMenuItem<R>,
+    toggle: fn(&mut TrayOptions) -> bool,
+) {
+    let Some(state) = app.try_state::<AppState>() else {
+        eprintln!("Failed to get app state");
+        return;
+    };
+
+    let (options, checked) = match state.tray_options.lock() {
+        Ok(mut options) => {
+            let checked = toggle(&mut options);
+            (*options, checked)
+        }
+        Err(e) => {
+            eprintln!("Failed to lock tray options mutex: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = item.set_checked(checked) {
+        eprintln!("Failed to update tray option checkbox: {}", e);
+    }
+
+    let title = match state.registry.lock() {
+        Ok(registry) => registry.tray_title(&options),
+        Err(e) => {
+            eprintln!("Failed to lock registry mutex: {}", e);
+            return;
+        }
+    };
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        if let Err(e) = tray.set_title(Some(&title)) {
+            eprintln!("Failed to update tray title: {}", e);
+        }
     }
 }
 
-fn bytes_to_gb(bytes: u64) -> f32 {
-    bytes as f32 / BYTES_TO_GB
+fn request_process_action<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    event_id: &str,
+    menu: &ProcessActionMenu<R>,
+) -> bool {
+    let Some((process, ProcessMenuAction::Signal(signal), _)) = shown_process_action(app, event_id)
+    else {
+        return false;
+    };
+
+    if let Err(e) = check_process_action_allowed(process.pid, std::process::id()) {
+        menu.set_status(format_process_action_result(
+            &process.name,
+            process.pid,
+            signal,
+            &Err(e),
+        ));
+        return true;
+    }
+
+    let pending = PendingProcessAction {
+        pid: process.pid,
+        name: process.name,
+        signal,
+        requested_at: Instant::now(),
+    };
+    menu.set_pending(Some(&pending));
+
+    match app.try_state::<AppState>() {
+        Some(state) => match state.process_action.lock() {
+            Ok(mut process_action) => process_action.pending = Some(pending),
+            Err(e) => eprintln!("Failed to lock process action mutex: {}", e),
+        },
+        None => eprintln!("Failed to get app state"),
+    }
+
+    true
 }
 
-fn create_bar(percentage: f32) -> String {
-    let blocks = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
-    let index = ((percentage / 100.0) * (blocks.len() - 1) as f32).round() as usize;
-    let index = index.min(blocks.len() - 1);
+fn confirm_process_action<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    menu: &ProcessActionMenu<R>,
+) {
+    let Some(state) = app.try_state::<AppState>() else {
+        eprintln!("Failed to get app state");
+        return;
+    };
 
-    blocks[index].to_string()
+    let pending = match state.process_action.lock() {
+        Ok(mut process_action) => process_action.pending.take(),
+        Err(e) => {
+            eprintln!("Failed to lock process action mutex: {}", e);
+            return;
+        }
+    };
+    menu.set_pending(None);
+
+    let Some(pending) = pending else {
+        return;
+    };
+
+    let result = if pending.is_expired(Instant::now()) {
+        Err(ProcessActionError::Failed(
+            "confirmation expired".to_string(),
+        ))
+    } else {
+        let still_running = state
+            .registry
+            .lock()
+            .ok()
+            .and_then(|registry| {
+                registry
+                    .system()
+                    .process(Pid::from_u32(pending.pid))
+                    .map(|process| process.name() == pending.name)
+            })
+            .unwrap_or(false);
+
+        if still_running {
+            send_process_signal(pending.pid, pending.signal)
+        } else {
+            Err(ProcessActionError::NotFound)
+        }
+    };
+
+    menu.set_status(format_process_action_result(
+        &pending.name,
+        pending.pid,
+        pending.signal,
+        &result,
+    ));
 }
 
-fn format_tray_title(stats: &SystemStats) -> String {
-    format!(
-        "🔋 {} {}% |  🧠 {} {}% |  💾 {} {}%",
-        create_bar(stats.battery_percent),
-        stats.battery_percent.round() as i32,
-        create_bar(stats.cpu_usage),
-        stats.cpu_usage.round() as i32,
-        create_bar(stats.memory_percent),
-        stats.memory_percent.round() as i32
-    )
+fn expire_process_action<R: tauri::Runtime>(state: &AppState, menu: &ProcessActionMenu<R>) {
+    let Ok(mut process_action) = state.process_action.lock() else {
+        eprintln!("Failed to lock process action mutex");
+        return;
+    };
+
+    if let Some(pending) = &process_action.pending {
+        if pending.is_expired(Instant::now()) {
+            process_action.pending = None;
+            menu.set_pending(None);
+        }
+    }
 }
 
-fn format_battery_text(stats: &SystemStats) -> String {
-    format!(
-        "🔋 Battery: {}% ({})",
-        stats.battery_percent.round() as i32,
-        stats.battery_state
-    )
+/// A freshly loaded config file, sent from the config watcher to the stats updater.
+struct ConfigUpdate {
+    config: Config,
+    errors: Vec<ConfigError>,
 }
 
-fn format_cpu_text(stats: &SystemStats) -> String {
-    format!("🧠 CPU Usage: {:.1}%", stats.cpu_usage)
+fn config_path<R: tauri::Runtime, M: Manager<R>>(manager: &M) -> Option<PathBuf> {
+    match manager.path().app_config_dir() {
+        Ok(dir) => Some(dir.join(CONFIG_FILE_NAME)),
+        Err(e) => {
+            eprintln!("Failed to resolve config directory: {}", e);
+            None
+        }
+    }
 }
 
-fn format_memory_text(stats: &SystemStats) -> String {
-    format!(
-        "💾 Memory: {:.1}% ({:.2} GB / {:.2} GB)",
-        stats.memory_percent,
-        bytes_to_gb(stats.memory_used),
-        bytes_to_gb(stats.memory_total)
-    )
+fn config_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
 }
 
-fn update_menu_items<R: tauri::Runtime>(
-    stats: &SystemStats,
-    battery: &MenuItem<R>,
-    cpu: &MenuItem<R>,
-    memory: &MenuItem<R>,
+fn load_config(path: Option<&Path>) -> ConfigUpdate {
+    let (config, errors) = match path {
+        Some(path) => Config::load(path),
+        None => (Config::default(), Vec::new()),
+    };
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+    ConfigUpdate { config, errors }
+}
+
+fn configure_registry(registry: &mut Registry, config: &Config) -> Vec<ConfigError> {
+    let errors = registry.configure(config);
+    for error in &errors {
+        eprintln!("{}", error);
+    }
+    errors
+}
+
+fn store_dir<R: tauri::Runtime, M: Manager<R>>(manager: &M) -> Option<PathBuf> {
+    match manager.path().app_data_dir() {
+        Ok(dir) => Some(dir.join(STORE_DIR_NAME)),
+        Err(e) => {
+            eprintln!("Failed to resolve data directory: {}", e);
+            None
+        }
+    }
+}
+
+/// Opens, resizes or closes the time-series store to match `config`.
+fn configure_store(store: &mut Option<TimeSeriesStore>, dir: Option<&Path>, config: &Config) {
+    if !config.storage_enabled {
+        *store = None;
+        return;
+    }
+    if let Some(store) = store {
+        store.set_max_bytes(config.storage_max_bytes);
+        return;
+    }
+    if let Some(dir) = dir {
+        match TimeSeriesStore::open(dir, config.storage_max_bytes) {
+            Ok(opened) => *store = Some(opened),
+            Err(e) => eprintln!("Failed to open stats store {}: {}", dir.display(), e),
+        }
+    }
+}
+
+fn record_sample(state: &AppState, readings: &[Reading]) {
+    let Ok(mut store) = state.store.lock() else {
+        eprintln!("Failed to lock stats store mutex");
+        return;
+    };
+    if let Some(store) = store.as_mut() {
+        if let Err(e) = store.append(unix_timestamp(SystemTime::now()), readings) {
+            eprintln!("Failed to write stats sample: {}", e);
+        }
+    }
+}
+
+fn log_dir<R: tauri::Runtime, M: Manager<R>>(manager: &M) -> Option<PathBuf> {
+    match manager.path().app_log_dir() {
+        Ok(dir) => Some(dir),
+        Err(e) => {
+            eprintln!("Failed to resolve log directory: {}", e);
+            None
+        }
+    }
+}
+
+fn build_app_state(
+    config: &Config,
+    store_dir: Option<&Path>,
+    log_dir: Option<&Path>,
+) -> (AppState, Vec<ConfigError>) {
+    let mut registry = Registry::with_builtin_collectors();
+    let errors = configure_registry(&mut registry, config);
+    let mut store = None;
+    configure_store(&mut store, store_dir, config);
+
+    let state = AppState {
+        registry: Mutex::new(registry),
+        tray_options: Mutex::new(config.tray),
+        process_action: Mutex::new(ProcessActionState::default()),
+        store: Mutex::new(store),
+        alerts: Mutex::new(AlertEngine::new(config.alert_rules.clone())),
+        battery: Mutex::new(BatteryMonitor::new(config.battery)),
+        hooks: Mutex::new(HookRunner::new(
+            config.hooks.clone(),
+            log_dir.map(|dir| dir.join(HOOK_LOG_FILE_NAME)),
+        )),
+        metrics_server: Mutex::new(None),
+    };
+    (state, errors)
+}
+
+/// Starts, moves or stops the metrics endpoint to match `config`.
+fn configure_metrics_server<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    state: &AppState,
+    config: &Config,
 ) {
-    if let Err(e) = battery.set_text(format_battery_text(stats)) {
-        eprintln!("Failed to update battery menu item: {}", e);
+    let Ok(mut server) = state.metrics_server.lock() else {
+        eprintln!("Failed to lock metrics server mutex");
+        return;
+    };
+    let port = config.metrics_enabled.then_some(config.metrics_port);
+    if server.as_ref().map(MetricsServer::port) == port {
+        return;
     }
+    *server = None;
 
-    if let Err(e) = cpu.set_text(format_cpu_text(stats)) {
-        eprintln!("Failed to update CPU menu item: {}", e);
+    let Some(port) = port else {
+        return;
+    };
+    let app_handle = app_handle.clone();
+    let snapshot = move || {
+        let state = app_handle.try_state::<AppState>()?;
+        let registry = state.registry.lock().ok()?;
+        Some((registry.stats(), registry.readings()))
+    };
+    match MetricsServer::start(port, snapshot) {
+        Ok(started) => *server = Some(started),
+        Err(e) => eprintln!("Failed to start metrics server on port {}: {}", port, e),
     }
+}
 
-    if let Err(e) = memory.set_text(format_memory_text(stats)) {
-        eprintln!("Failed to update memory menu item: {}", e);
+/// Evaluates the alert rules against a new sample and shows a notification for every alert.
+/// Returns the new alerts and the entries of the recent alerts submenu.
+fn check_alerts<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    state: &AppState,
+    stats: &SystemStats,
+) -> Option<(Vec<Alert>, Vec<SubmenuEntry>)> {
+    let now = unix_timestamp(SystemTime::now());
+    let Ok(mut engine) = state.alerts.lock() else {
+        eprintln!("Failed to lock alerts mutex");
+        return None;
+    };
+    let alerts = engine.evaluate(now, stats);
+    for alert in &alerts {
+        show_notification(app_handle, &alert.rule, alert.to_string());
     }
+    Some((alerts, alert_entries(engine.recent(), now)))
+}
+
+/// Starts the hooks for the new alerts and a change of the battery state in the latest sample.
+fn run_hooks(state: &AppState, alerts: &[Alert], stats: &SystemStats, readings: &[Reading]) {
+    let Ok(mut runner) = state.hooks.lock() else {
+        eprintln!("Failed to lock hooks mutex");
+        return;
+    };
+    let timestamp = unix_timestamp(SystemTime::now());
+    for event in runner.events(alerts, stats) {
+        runner.dispatch(&HookContext {
+            event,
+            timestamp,
+            stats: stats.clone(),
+            readings: readings.to_vec(),
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HookMenuEntry {
+    id: String,
+    text: String,
+    checked: bool,
 }
 
-fn handle_menu_click<R: tauri::Runtime>(
+fn hook_entries<'a>(hooks: impl Iterator<Item = (&'a Hook, bool)>) -> Vec<HookMenuEntry> {
+    hooks
+        .enumerate()
+        .map(|(index, (hook, enabled))| HookMenuEntry {
+            id: indexed_menu_id(MENU_HOOK_PREFIX, index),
+            text: hook.name.clone(),
+            checked: enabled,
+        })
+        .collect()
+}
+
+/// Rebuilds the hooks submenu with one checkbox per hook, or a placeholder without hooks.
+fn sync_hooks_submenu<R: tauri::Runtime, M: Manager<R>>(
+    manager: &M,
+    submenu: &Submenu<R>,
+    state: &AppState,
+) {
+    let entries = match state.hooks.lock() {
+        Ok(runner) => hook_entries(runner.hooks()),
+        Err(e) => {
+            eprintln!("Failed to lock hooks mutex: {}", e);
+            return;
+        }
+    };
+
+    match submenu.items() {
+        Ok(items) => {
+            for item in items {
+                if let Err(e) = submenu.remove(&item) {
+                    eprintln!("Failed to remove submenu item: {}", e);
+                }
+            }
+        }
+        Err(e) => eprintln!("Failed to read hooks submenu: {}", e),
+    }
+
+    let result = if entries.is_empty() {
+        MenuItem::with_id(
+            manager,
+            format!("{}none", MENU_HOOK_PREFIX),
+            "No hooks configured",
+            false,
+            None::<&str>,
+        )
+        .and_then(|item| submenu.append(&item))
+    } else {
+        entries.iter().try_for_each(|entry| {
+            let item = CheckMenuItem::with_id(
+                manager,
+                &entry.id,
+                &entry.text,
+                true,
+                entry.checked,
+                None::<&str>,
+            )?;
+            submenu.append(&item)
+        })
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to build hooks submenu: {}", e);
+    }
+}
+
+fn toggle_hook<R: tauri::Runtime>(
     app: &tauri::AppHandle<R>,
+    submenu: &Submenu<R>,
     event_id: &str,
-    current_stats: &Arc<Mutex<Option<SystemStats>>>,
+    index: usize,
 ) {
-    let stats = match current_stats.lock() {
-        Ok(guard) => match guard.as_ref() {
-            Some(stats) => stats.clone(),
-            None => {
-                eprintln!("No stats available to copy");
-                return;
-            }
-        },
+    let Some(state) = app.try_state::<AppState>() else {
+        eprintln!("Failed to get app state");
+        return;
+    };
+    let enabled = match state.hooks.lock() {
+        Ok(mut runner) => runner.toggle(index),
         Err(e) => {
-            eprintln!("Failed to lock stats mutex: {}", e);
+            eprintln!("Failed to lock hooks mutex: {}", e);
             return;
         }
     };
+    let (Some(enabled), Some(item)) = (
+        enabled,
+        submenu
+            .get(event_id)
+            .and_then(|item| item.as_check_menuitem().cloned()),
+    ) else {
+        return;
+    };
+    if let Err(e) = item.set_checked(enabled) {
+        eprintln!("Failed to update hook checkbox: {}", e);
+    }
+}
 
-    let text = match event_id {
-        MENU_BATTERY => format!("{}%", stats.battery_percent.round() as i32),
-        MENU_CPU => format!("{:.1}%", stats.cpu_usage),
-        MENU_MEMORY => format!("{:.1}%", stats.memory_percent),
-        _ => return,
+fn check_battery_reminders<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    state: &AppState,
+    stats: &SystemStats,
+) {
+    let reminders = match state.battery.lock() {
+        Ok(mut monitor) => monitor.check(stats),
+        Err(e) => {
+            eprintln!("Failed to lock battery monitor mutex: {}", e);
+            return;
+        }
     };
+    for reminder in reminders {
+        show_notification(app_handle, &reminder.title(), reminder.to_string());
+    }
+}
 
-    if let Err(e) = app.clipboard().write_text(text) {
-        eprintln!("Failed to write to clipboard: {}", e);
+fn show_notification<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    title: &str,
+    body: String,
+) {
+    if let Err(e) = app_handle
+        .notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+    {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
+
+fn alert_entries<'a>(alerts: impl Iterator<Item = &'a Alert>, now: u64) -> Vec<SubmenuEntry> {
+    alerts
+        .enumerate()
+        .map(|(index, alert)| SubmenuEntry {
+            id: indexed_menu_id(MENU_RECENT_ALERT_PREFIX, index),
+            text: format!(
+                "⚠️ {}: {} ({} ago)",
+                alert.rule,
+                alert,
+                format_duration_short(now.saturating_sub(alert.timestamp))
+            ),
+            enabled: false,
+        })
+        .collect()
+}
+
+fn config_error_entries(errors: &[ConfigError]) -> Vec<SubmenuEntry> {
+    errors
+        .iter()
+        .enumerate()
+        .map(|(index, error)| SubmenuEntry {
+            id: format!("{}{}", MENU_CONFIG_ERROR_PREFIX, index),
+            text: error.to_string(),
+            enabled: false,
+        })
+        .collect()
+}
+
+/// Polls the config file and sends every new version to the stats updater.
+fn spawn_config_watcher(
+    path: PathBuf,
+    mut last_modified: Option<SystemTime>,
+    updates: Sender<ConfigUpdate>,
+) {
+    std::thread::Builder::new()
+        .name("config-watcher".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(Duration::from_secs(CONFIG_POLL_INTERVAL_SECS));
+
+            let modified = config_modified(&path);
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            if updates.send(load_config(Some(&path))).is_err() {
+                break;
+            }
+        })
+        .expect("Failed to spawn config watcher thread");
+}
+
+/// Applies a reloaded config to the registry, tray options and menu in one step. A file that
+/// failed to parse only shows its error and keeps the current settings.
+fn apply_config_update<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    state: &AppState,
+    update: ConfigUpdate,
+    menu: &mut StatsMenu<R>,
+    interval: &mut Duration,
+) {
+    let ConfigUpdate { config, mut errors } = update;
+
+    if !errors.iter().any(ConfigError::is_fatal) {
+        let Ok(mut registry) = state.registry.lock() else {
+            eprintln!("Failed to lock registry mutex");
+            return;
+        };
+        errors.extend(configure_registry(&mut registry, &config));
+        match state.tray_options.lock() {
+            Ok(mut options) => *options = config.tray,
+            Err(e) => eprintln!("Failed to lock tray options mutex: {}", e),
+        }
+        let stats = registry.stats();
+        let entries = registry.menu_entries(&config.tray);
+        let title = registry.tray_title(&config.tray);
+        drop(registry);
+
+        *interval = config.refresh_interval();
+
+        match state.store.lock() {
+            Ok(mut store) => configure_store(&mut store, store_dir(app_handle).as_deref(), &config),
+            Err(e) => eprintln!("Failed to lock stats store mutex: {}", e),
+        }
+        configure_metrics_server(app_handle, state, &config);
+        match state.alerts.lock() {
+            Ok(mut engine) => engine.set_rules(config.alert_rules),
+            Err(e) => eprintln!("Failed to lock alerts mutex: {}", e),
+        }
+        match state.battery.lock() {
+            Ok(mut monitor) => monitor.set_policy(config.battery),
+            Err(e) => eprintln!("Failed to lock battery monitor mutex: {}", e),
+        }
+        match state.hooks.lock() {
+            Ok(mut runner) => runner.set_hooks(config.hooks),
+            Err(e) => eprintln!("Failed to lock hooks mutex: {}", e),
+        }
+        sync_hooks_submenu(app_handle, &menu.hooks, state);
+
+        if let Err(e) = menu
+            .load_average_tray
+            .set_checked(config.tray.cpu_segment == CpuTraySegment::LoadAverage)
+        {
+            eprintln!("Failed to update tray option checkbox: {}", e);
+        }
+        if let Err(e) = menu
+            .temperature_tray
+            .set_checked(config.tray.show_temperature)
+        {
+            eprintln!("Failed to update tray option checkbox: {}", e);
+        }
+
+        if let Some(tray) = app_handle.tray_by_id(TRAY_ID) {
+            if let Err(e) = tray.set_title(Some(&title)) {
+                eprintln!("Failed to update tray title: {}", e);
+            }
+        }
+        update_menu_items(app_handle, &entries, &stats, menu);
     }
+
+    sync_optional_submenu(
+        app_handle,
+        &menu.tray_menu,
+        &mut menu.config_errors,
+        &config_error_entries(&errors),
+    );
 }
 
 fn spawn_stats_updater<R: tauri::Runtime>(
     app_handle: tauri::AppHandle<R>,
-    current_stats: Arc<Mutex<Option<SystemStats>>>,
-    battery_item: MenuItem<R>,
-    cpu_item: MenuItem<R>,
-    memory_item: MenuItem<R>,
+    mut menu: StatsMenu<R>,
+    mut interval: Duration,
+    config_updates: Receiver<ConfigUpdate>,
 ) {
     std::thread::Builder::new()
         .name("stats-updater".to_string())
         .spawn(move || loop {
-            std::thread::sleep(Duration::from_secs(UPDATE_INTERVAL_SECS));
+            let update = match config_updates.recv_timeout(interval) {
+                Ok(update) => Some(update),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    std::thread::sleep(interval);
+                    None
+                }
+            };
 
             let Some(state) = app_handle.try_state::<AppState>() else {
                 eprintln!("Failed to get app state");
                 continue;
             };
 
-            let Ok(mut sys) = state.system.lock() else {
-                eprintln!("Failed to lock system mutex");
+            if let Some(update) = update {
+                apply_config_update(&app_handle, &state, update, &mut menu, &mut interval);
+                continue;
+            }
+
+            let Ok(mut registry) = state.registry.lock() else {
+                eprintln!("Failed to lock registry mutex");
                 continue;
             };
 
-            let stats = collect_system_stats(&mut sys);
+            registry.refresh();
+            let stats = registry.stats();
+            let readings = registry.readings();
+            let options = current_tray_options(&app_handle);
+            let entries = registry.menu_entries(&options);
+            let title = registry.tray_title(&options);
+            drop(registry);
+
+            record_sample(&state, &readings);
+            let alerts = match check_alerts(&app_handle, &state, &stats) {
+                Some((alerts, entries)) => {
+                    sync_optional_submenu(
+                        &app_handle,
+                        &menu.tray_menu,
+                        &mut menu.recent_alerts,
+                        &entries,
+                    );
+                    alerts
+                }
+                None => Vec::new(),
+            };
+            check_battery_reminders(&app_handle, &state, &stats);
+            run_hooks(&state, &alerts, &stats, &readings);
 
-            if let Ok(mut current) = current_stats.lock() {
-                *current = Some(stats.clone());
-            } else {
-                eprintln!("Failed to lock current stats mutex");
-            }
+            expire_process_action(&state, &menu.process_actions);
 
             if let Some(tray) = app_handle.tray_by_id(TRAY_ID) {
-                if let Err(e) = tray.set_title(Some(&format_tray_title(&stats))) {
+                if let Err(e) = tray.set_title(Some(&title)) {
                     eprintln!("Failed to update tray title: {}", e);
                 }
             }
 
-            update_menu_items(&stats, &battery_item, &cpu_item, &memory_item);
+            update_menu_items(&app_handle, &entries, &stats, &mut menu);
         })
         .expect("Failed to spawn stats updater thread");
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 fn main() {
-    let mut sys = System::new();
-    sys.refresh_cpu();
-    sys.refresh_memory();
-
-    let current_stats: Arc<Mutex<Option<SystemStats>>> = Arc::new(Mutex::new(None));
-
     tauri::Builder::default()
         .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_autostart::init(
             tauri_plugin_autostart::MacosLauncher::LaunchAgent,
             Some(vec!["--flag", "autostart"]),
         ))
-        .manage(AppState {
-            system: Mutex::new(sys),
-        })
         .setup(move |app| {
             #[cfg(target_os = "macos")]
             app.set_activation_policy(tauri::ActivationPolicy::Accessory);
 
-            let battery_item =
-                MenuItem::with_id(app, MENU_BATTERY, "Battery: Loading...", true, None::<&str>)?;
-            let cpu_item = MenuItem::with_id(app, MENU_CPU, "CPU: Loading...", true, None::<&str>)?;
-            let memory_item =
-                MenuItem::with_id(app, MENU_MEMORY, "Memory: Loading...", true, None::<&str>)?;
+            let config_path = config_path(app);
+            let config_modified = config_path.as_deref().and_then(config_modified);
+            let ConfigUpdate {
+                config,
+                errors: mut config_errors,
+            } = load_config(config_path.as_deref());
+            let (state, segment_errors) =
+                build_app_state(&config, store_dir(app).as_deref(), log_dir(app).as_deref());
+            config_errors.extend(segment_errors);
+            let hooks_submenu = Submenu::with_id(app, MENU_HOOKS, "🪝 Hooks", true)?;
+            sync_hooks_submenu(app, &hooks_submenu, &state);
+            app.manage(state);
+
+            let (top_cpu_submenu, top_cpu_items) =
+                build_process_submenu(app, MENU_TOP_CPU, "Top CPU", MENU_TOP_CPU_PREFIX)?;
+            let (top_memory_submenu, top_memory_items) =
+                build_process_submenu(app, MENU_TOP_MEMORY, "Top Memory", MENU_TOP_MEMORY_PREFIX)?;
+            let process_action_menu = ProcessActionMenu {
+                confirm: MenuItem::with_id(
+                    app,
+                    MENU_PROCESS_CONFIRM,
+                    "No pending process action",
+                    false,
+                    None::<&str>,
+                )?,
+                status: MenuItem::with_id(
+                    app,
+                    MENU_PROCESS_STATUS,
+                    "No recent process actions",
+                    false,
+                    None::<&str>,
+                )?,
+            };
+            let config_errors_menu = OptionalSubmenu {
+                menu: DynamicSubmenu {
+                    submenu: Submenu::with_id(app, MENU_CONFIG_ERRORS, "⚠️ Config Errors", true)?,
+                    items: Vec::new(),
+                },
+                after: MENU_PROCESS_STATUS,
+                visible: false,
+            };
+            let recent_alerts_menu = OptionalSubmenu {
+                menu: DynamicSubmenu {
+                    submenu: Submenu::with_id(app, MENU_RECENT_ALERTS, "🔔 Recent Alerts", true)?,
+                    items: Vec::new(),
+                },
+                after: MENU_PROCESS_STATUS,
+                visible: false,
+            };
             let separator = PredefinedMenuItem::separator(app)?;
+            let export_submenu = build_export_submenu(app)?;
 
             let autostart_manager = app.autolaunch();
             let is_autostart_enabled = autostart_manager.is_enabled().unwrap_or(false);
+            let load_average_tray_item = CheckMenuItem::with_id(
+                app,
+                MENU_LOAD_AVERAGE_TRAY,
+                "Show Load Average in Menu Bar",
+                true,
+                config.tray.cpu_segment == CpuTraySegment::LoadAverage,
+                None::<&str>,
+            )?;
+            let temperature_tray_item = CheckMenuItem::with_id(
+                app,
+                MENU_TEMPERATURE_TRAY,
+                "Show Temperature in Menu Bar",
+                true,
+                config.tray.show_temperature,
+                None::<&str>,
+            )?;
             let autostart_item = CheckMenuItem::with_id(
                 app,
                 MENU_AUTOSTART,
@@ -277,17 +1472,42 @@ fn main() {
             let menu = Menu::with_items(
                 app,
                 &[
-                    &battery_item,
-                    &cpu_item,
-                    &memory_item,
+                    &top_cpu_submenu,
+                    &top_memory_submenu,
+                    &process_action_menu.confirm,
+                    &process_action_menu.status,
                     &separator,
+                    &export_submenu,
+                    &load_average_tray_item,
+                    &temperature_tray_item,
+                    &hooks_submenu,
                     &autostart_item,
                     &quit_item,
                 ],
             )?;
 
-            let current_stats_for_menu = current_stats.clone();
+            let mut stats_menu = StatsMenu {
+                collectors: Vec::new(),
+                top_cpu: top_cpu_items,
+                top_memory: top_memory_items,
+                process_actions: process_action_menu.clone(),
+                tray_menu: menu.clone(),
+                config_errors: config_errors_menu,
+                recent_alerts: recent_alerts_menu,
+                hooks: hooks_submenu.clone(),
+                load_average_tray: load_average_tray_item.clone(),
+                temperature_tray: temperature_tray_item.clone(),
+            };
+            sync_optional_submenu(
+                app,
+                &stats_menu.tray_menu,
+                &mut stats_menu.config_errors,
+                &config_error_entries(&config_errors),
+            );
+
             let autostart_item_clone = autostart_item.clone();
+            let load_average_tray_item_clone = load_average_tray_item.clone();
+            let temperature_tray_item_clone = temperature_tray_item.clone();
             let tray = TrayIconBuilder::with_id(TRAY_ID)
                 .menu(&menu)
                 .title("Loading...")
@@ -317,31 +1537,51 @@ fn main() {
                             Err(e) => eprintln!("Failed to check autostart status: {}", e),
                         }
                     }
-                    _ => handle_menu_click(app, event.id.as_ref(), &current_stats_for_menu),
+                    MENU_LOAD_AVERAGE_TRAY => {
+                        toggle_tray_option(app, &load_average_tray_item_clone, toggle_load_average)
+                    }
+                    MENU_TEMPERATURE_TRAY => {
+                        toggle_tray_option(app, &temperature_tray_item_clone, toggle_temperature)
+                    }
+                    MENU_PROCESS_CONFIRM => confirm_process_action(app, &process_action_menu),
+                    id => {
+                        if let Some((window_secs, format)) = parse_export_menu_id(id) {
+                            export_history(app, window_secs, format);
+                        } else if let Some(index) = parse_indexed_menu_id(MENU_HOOK_PREFIX, id) {
+                            toggle_hook(app, &hooks_submenu, id, index);
+                        } else if !request_process_action(app, id, &process_action_menu) {
+                            handle_menu_click(app, id)
+                        }
+                    }
                 })
                 .build(app)?;
 
             if let Some(state) = app.try_state::<AppState>() {
-                if let Ok(mut sys) = state.system.lock() {
-                    let stats = collect_system_stats(&mut sys);
+                if let Ok(mut registry) = state.registry.lock() {
+                    registry.refresh();
+                    let stats = registry.stats();
 
-                    if let Ok(mut current) = current_stats.lock() {
-                        *current = Some(stats.clone());
-                    }
-
-                    if let Err(e) = tray.set_title(Some(&format_tray_title(&stats))) {
+                    let options = current_tray_options(app);
+                    let title = registry.tray_title(&options);
+                    if let Err(e) = tray.set_title(Some(&title)) {
                         eprintln!("Failed to set initial tray title: {}", e);
                     }
-                    update_menu_items(&stats, &battery_item, &cpu_item, &memory_item);
+                    let entries = registry.menu_entries(&options);
+                    drop(registry);
+                    update_menu_items(app, &entries, &stats, &mut stats_menu);
                 }
+                configure_metrics_server(app.handle(), &state, &config);
             }
 
+            let (config_sender, config_updates) = mpsc::channel();
+            if let Some(path) = config_path {
+                spawn_config_watcher(path, config_modified, config_sender);
+            }
             spawn_stats_updater(
                 app.handle().clone(),
-                current_stats.clone(),
-                battery_item.clone(),
-                cpu_item.clone(),
-                memory_item.clone(),
+                stats_menu,
+                config.refresh_interval(),
+                config_updates,
             );
 
             Ok(())
@@ -353,290 +1593,368 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use system_stats_lib::format::TRAY_SEGMENT_SEPARATOR;
 
     #[test]
-    fn test_bytes_to_gb_conversion() {
-        assert_eq!(bytes_to_gb(0), 0.0);
-        assert_eq!(bytes_to_gb(1073741824), 1.0);
-        assert_eq!(bytes_to_gb(2147483648), 2.0);
+    fn test_tray_option_toggles() {
+        let mut options = TrayOptions::default();
 
-        let half_gb = bytes_to_gb(536870912);
-        assert!((half_gb - 0.5).abs() < 0.001);
+        assert!(toggle_load_average(&mut options));
+        assert_eq!(options.cpu_segment, CpuTraySegment::LoadAverage);
+        assert!(!toggle_load_average(&mut options));
+        assert_eq!(options.cpu_segment, CpuTraySegment::Usage);
 
-        let large_value = bytes_to_gb(10995116277760);
-        assert!((large_value - 10240.0).abs() < 1.0);
+        assert!(toggle_temperature(&mut options));
+        assert!(options.show_temperature);
+        assert!(!toggle_temperature(&mut options));
     }
 
     #[test]
-    fn test_format_battery_state() {
-        assert_eq!(format_battery_state(BatteryState::Charging), "Charging");
+    fn test_indexed_menu_id_roundtrip() {
+        assert_eq!(indexed_menu_id(MENU_TOP_CPU_PREFIX, 3), "top_cpu_3");
+        assert_eq!(
+            parse_indexed_menu_id(
+                MENU_TOP_CPU_PREFIX,
+                &indexed_menu_id(MENU_TOP_CPU_PREFIX, 3)
+            ),
+            Some(3)
+        );
+        assert_eq!(
+            parse_indexed_menu_id(MENU_TOP_CPU_PREFIX, "top_cpu_x"),
+            None
+        );
+        assert_eq!(
+            parse_indexed_menu_id(MENU_TOP_CPU_PREFIX, MENU_TOP_CPU),
+            None
+        );
         assert_eq!(
-            format_battery_state(BatteryState::Discharging),
-            "Discharging"
+            parse_indexed_menu_id(MENU_TOP_MEMORY_PREFIX, "top_memory_4"),
+            Some(4)
         );
-        assert_eq!(format_battery_state(BatteryState::Full), "Full");
-        assert_eq!(format_battery_state(BatteryState::Empty), "Empty");
+    }
+
+    #[test]
+    fn test_process_action_menu_id_roundtrip() {
+        for action in PROCESS_MENU_ACTIONS {
+            let id = process_action_menu_id(MENU_TOP_CPU_PREFIX, 2, action);
+            assert_eq!(
+                parse_process_action_menu_id(MENU_TOP_CPU_PREFIX, &id),
+                Some((2, action))
+            );
+            assert_eq!(
+                parse_process_action_menu_id(MENU_TOP_MEMORY_PREFIX, &id),
+                None
+            );
+        }
 
-        assert_eq!(format_battery_state(BatteryState::Unknown), "Unknown");
+        assert_eq!(
+            process_action_menu_id(
+                MENU_TOP_MEMORY_PREFIX,
+                0,
+                ProcessMenuAction::Signal(ProcessSignal::Kill)
+            ),
+            "top_memory_0_sigkill"
+        );
+        assert_eq!(
+            parse_process_action_menu_id(MENU_TOP_CPU_PREFIX, "top_cpu_1"),
+            None
+        );
+        assert_eq!(
+            parse_process_action_menu_id(MENU_TOP_CPU_PREFIX, "top_cpu_1_sighup"),
+            None
+        );
     }
 
     #[test]
-    fn test_create_bar() {
-        assert_eq!(create_bar(0.0), "▁");
-        assert_eq!(create_bar(100.0), "█");
+    fn test_lookup_process_action() {
+        let shown = ShownProcesses {
+            cpu: vec![ProcessStats {
+                pid: 10,
+                name: "busy".to_string(),
+                cpu_usage: 99.0,
+                memory: 0,
+            }],
+            memory: vec![ProcessStats {
+                pid: 20,
+                name: "hungry".to_string(),
+                cpu_usage: 0.0,
+                memory: 2048,
+            }],
+        };
+
+        let (process, action, format) = lookup_process_action(&shown, "top_cpu_0_copy").unwrap();
+        assert_eq!(process.pid, 10);
+        assert_eq!(action, ProcessMenuAction::Copy);
+        assert_eq!(format(process), "busy (10): 99.0%");
 
-        assert_eq!(create_bar(12.5), "▂");
-        assert_eq!(create_bar(25.0), "▃");
-        assert_eq!(create_bar(37.5), "▄");
-        assert_eq!(create_bar(50.0), "▅");
-        assert_eq!(create_bar(62.5), "▅");
-        assert_eq!(create_bar(75.0), "▆");
-        assert_eq!(create_bar(87.5), "▇");
+        let (process, action, format) =
+            lookup_process_action(&shown, "top_memory_0_sigstop").unwrap();
+        assert_eq!(process.pid, 20);
+        assert_eq!(action, ProcessMenuAction::Signal(ProcessSignal::Stop));
+        assert_eq!(format(process), "hungry (20): 2 KB");
 
-        assert_eq!(create_bar(1.0), "▁");
-        assert_eq!(create_bar(99.0), "█");
+        assert!(lookup_process_action(&shown, "top_cpu_1_copy").is_none());
+        assert!(lookup_process_action(&shown, "cpu/usage").is_none());
     }
 
     #[test]
-    fn test_create_bar_extreme_values() {
-        let bar_negative = create_bar(-10.0);
-        assert!(!bar_negative.is_empty());
-
-        let bar_over = create_bar(150.0);
-        assert!(!bar_over.is_empty());
+    fn test_format_process_action_result() {
+        assert_eq!(
+            format_process_action_result("cargo", 42, ProcessSignal::Terminate, &Ok(())),
+            "✅ Sent SIGTERM to cargo (42)"
+        );
+        assert_eq!(
+            format_process_action_result(
+                "cargo",
+                42,
+                ProcessSignal::Kill,
+                &Err(ProcessActionError::PermissionDenied)
+            ),
+            "❌ SIGKILL cargo (42): permission denied"
+        );
+        assert_eq!(
+            format_process_action_result(
+                "cargo",
+                42,
+                ProcessSignal::Stop,
+                &Err(ProcessActionError::NotFound)
+            ),
+            "❌ SIGSTOP cargo (42): process already gone"
+        );
     }
 
     #[test]
-    fn test_format_tray_title() {
-        let stats = SystemStats {
-            cpu_usage: 45.7,
-            memory_used: 8589934592,
-            memory_total: 17179869184,
-            memory_percent: 50.0,
-            battery_percent: 85.3,
-            battery_state: "Charging".to_string(),
+    fn test_pending_process_action_expiry() {
+        let pending = PendingProcessAction {
+            pid: 42,
+            name: "cargo".to_string(),
+            signal: ProcessSignal::Continue,
+            requested_at: Instant::now(),
         };
 
-        let title = format_tray_title(&stats);
-        assert!(title.contains("85%"));
-        assert!(title.contains("46%"));
-        assert!(title.contains("50%"));
-        assert!(title.contains("🔋"));
-        assert!(title.contains("🧠"));
-        assert!(title.contains("💾"));
-
-        assert!(title.contains("|"));
+        assert!(!pending.is_expired(pending.requested_at));
+        assert!(pending.is_expired(
+            pending.requested_at + Duration::from_secs(PROCESS_ACTION_CONFIRM_TIMEOUT_SECS)
+        ));
+        assert_eq!(
+            format_pending_process_action(&pending),
+            "⚠️ Confirm SIGCONT of cargo (42)"
+        );
     }
 
     #[test]
-    fn test_format_tray_title_edge_cases() {
-        let stats_zero = SystemStats {
-            cpu_usage: 0.0,
-            memory_used: 0,
-            memory_total: 17179869184,
-            memory_percent: 0.0,
-            battery_percent: 0.0,
-            battery_state: "Empty".to_string(),
-        };
-
-        let title = format_tray_title(&stats_zero);
-        assert!(title.contains("0%"));
+    fn test_export_menu_id_roundtrip() {
+        for (window, _, window_secs) in EXPORT_WINDOWS {
+            for (format, _) in EXPORT_FORMATS {
+                assert_eq!(
+                    parse_export_menu_id(&export_menu_id(window, format)),
+                    Some((window_secs, format))
+                );
+            }
+        }
+        assert_eq!(parse_export_menu_id(MENU_EXPORT), None);
+        assert_eq!(parse_export_menu_id("export_year_csv"), None);
+        assert_eq!(parse_export_menu_id("export_day_xml"), None);
+        assert_eq!(
+            export_file_name(1_760_693_400, ExportFormat::JsonLines),
+            "system-stats-2025-10-17T093000Z.jsonl"
+        );
+    }
 
-        let stats_max = SystemStats {
-            cpu_usage: 100.0,
-            memory_used: 17179869184,
-            memory_total: 17179869184,
-            memory_percent: 100.0,
-            battery_percent: 100.0,
-            battery_state: "Full".to_string(),
-        };
+    #[test]
+    fn test_build_app_state() {
+        let (config, errors) = Config::parse(
+            r#"
+            [tray]
+            segments = ["memory", "gpu"]
+            cpu = "load_average"
+            "#,
+        );
+        assert!(errors.is_empty());
 
-        let title_max = format_tray_title(&stats_max);
-        assert!(title_max.contains("100%"));
+        let (state, errors) = build_app_state(&config, None, None);
+        assert_eq!(
+            errors,
+            [ConfigError::Invalid {
+                field: "tray.segments",
+                message: "no collector named \"gpu\"".to_string(),
+            }]
+        );
+        let options = *state.tray_options.lock().unwrap();
+        assert_eq!(options.cpu_segment, CpuTraySegment::LoadAverage);
+
+        let mut registry = state.registry.lock().unwrap();
+        registry.refresh();
+        let title = registry.tray_title(&options);
+        assert!(title.starts_with("💾 "));
+        assert!(!title.contains(TRAY_SEGMENT_SEPARATOR));
     }
 
     #[test]
-    fn test_format_battery_text() {
-        let stats = SystemStats {
-            cpu_usage: 0.0,
-            memory_used: 0,
-            memory_total: 0,
-            memory_percent: 0.0,
-            battery_percent: 75.5,
-            battery_state: "Discharging".to_string(),
-        };
+    fn test_configure_store() {
+        let dir =
+            std::env::temp_dir().join(format!("system-stats-app-store-{}", std::process::id()));
+        let mut config = Config::default();
 
-        let text = format_battery_text(&stats);
-        assert!(text.contains("76%"));
-        assert!(text.contains("Discharging"));
-        assert!(text.contains("🔋"));
-
-        let stats_charging = SystemStats {
-            cpu_usage: 0.0,
-            memory_used: 0,
-            memory_total: 0,
-            memory_percent: 0.0,
-            battery_percent: 50.0,
-            battery_state: "Charging".to_string(),
-        };
+        let mut store = None;
+        configure_store(&mut store, None, &config);
+        assert!(store.is_none());
 
-        let text_charging = format_battery_text(&stats_charging);
-        assert!(text_charging.contains("50%"));
-        assert!(text_charging.contains("Charging"));
-    }
+        configure_store(&mut store, Some(&dir), &config);
+        assert!(dir.is_dir());
 
-    #[test]
-    fn test_format_cpu_text() {
-        let stats = SystemStats {
-            cpu_usage: 33.7,
-            memory_used: 0,
-            memory_total: 0,
-            memory_percent: 0.0,
-            battery_percent: 0.0,
-            battery_state: "Unknown".to_string(),
-        };
+        config.storage_max_bytes = 1024;
+        configure_store(&mut store, Some(&dir), &config);
+        assert_eq!(store.as_ref().map(TimeSeriesStore::max_bytes), Some(1024));
 
-        let text = format_cpu_text(&stats);
-        assert!(text.contains("33.7%"));
-        assert!(text.contains("🧠"));
-
-        let stats_zero = SystemStats {
-            cpu_usage: 0.0,
-            memory_used: 0,
-            memory_total: 0,
-            memory_percent: 0.0,
-            battery_percent: 0.0,
-            battery_state: "Unknown".to_string(),
-        };
+        config.storage_enabled = false;
+        configure_store(&mut store, Some(&dir), &config);
+        assert!(store.is_none());
 
-        let text_zero = format_cpu_text(&stats_zero);
-        assert!(text_zero.contains("0.0%"));
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_format_memory_text() {
-        let stats = SystemStats {
-            cpu_usage: 0.0,
-            memory_used: 8589934592,
-            memory_total: 17179869184,
-            memory_percent: 50.0,
-            battery_percent: 0.0,
-            battery_state: "Unknown".to_string(),
-        };
-
-        let text = format_memory_text(&stats);
-        assert!(text.contains("50.0%"));
-        assert!(text.contains("8.00 GB"));
-        assert!(text.contains("16.00 GB"));
-        assert!(text.contains("💾"));
-
-        let stats_low = SystemStats {
-            cpu_usage: 0.0,
-            memory_used: 1073741824,
-            memory_total: 17179869184,
-            memory_percent: 6.25,
-            battery_percent: 0.0,
-            battery_state: "Unknown".to_string(),
-        };
-
-        let text_low = format_memory_text(&stats_low);
-        assert!(text_low.contains("1.00 GB"));
+    fn test_submenu_entries() {
+        let entries = [
+            MenuEntry::new("disk/volume_/", "/: 50.0%", Some("50.0%".to_string())),
+            MenuEntry::new("memory/detail_0", "Used: 1.00 GB", None),
+        ];
+        assert_eq!(
+            submenu_entries(&entries),
+            [
+                SubmenuEntry {
+                    id: "disk/volume_/".to_string(),
+                    text: "/: 50.0%".to_string(),
+                    enabled: true,
+                },
+                SubmenuEntry {
+                    id: "memory/detail_0".to_string(),
+                    text: "Used: 1.00 GB".to_string(),
+                    enabled: false,
+                },
+            ]
+        );
     }
 
     #[test]
-    fn test_collect_system_stats_validation() {
-        let mut sys = System::new();
-        sys.refresh_cpu();
-        sys.refresh_memory();
-
-        let stats = collect_system_stats(&mut sys);
-
-        assert!(stats.cpu_usage >= 0.0 && stats.cpu_usage <= 100.0);
-        assert!(stats.memory_percent >= 0.0 && stats.memory_percent <= 100.0);
-        assert!(stats.battery_percent >= 0.0 && stats.battery_percent <= 100.0);
-        assert!(stats.memory_used <= stats.memory_total);
-
-        assert!(stats.memory_total > 0);
-
-        assert!(
-            stats.battery_state == "Charging"
-                || stats.battery_state == "Discharging"
-                || stats.battery_state == "Full"
-                || stats.battery_state == "Empty"
-                || stats.battery_state == "Unknown"
-                || stats.battery_state == "No Battery"
+    fn test_config_error_entries() {
+        let (_, errors) = Config::parse("[format]\nprecision = 9\n");
+        let entries = config_error_entries(&errors);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, "config_error_0");
+        assert_eq!(
+            entries[0].text,
+            "Invalid config value for `format.precision`: expected 0 to 3, found 9"
         );
+        assert!(!entries[0].enabled);
+        assert!(config_error_entries(&[]).is_empty());
     }
 
     #[test]
-    fn test_system_stats_clone() {
-        let stats = SystemStats {
-            cpu_usage: 50.0,
-            memory_used: 1073741824,
-            memory_total: 2147483648,
-            memory_percent: 50.0,
-            battery_percent: 80.0,
-            battery_state: "Charging".to_string(),
+    fn test_alert_entries() {
+        let alert = |rule: &str, timestamp| Alert {
+            rule: rule.to_string(),
+            message: "cpu.pct is 95.0% (> 90.0%)".to_string(),
+            timestamp,
         };
+        let alerts = [alert("CPU busy", 1_000), alert("Hot", 100)];
+        let entries = alert_entries(alerts.iter(), 1_120);
+        assert_eq!(
+            entries,
+            [
+                SubmenuEntry {
+                    id: "recent_alert_0".to_string(),
+                    text: "⚠️ CPU busy: cpu.pct is 95.0% (> 90.0%) (2m ago)".to_string(),
+                    enabled: false,
+                },
+                SubmenuEntry {
+                    id: "recent_alert_1".to_string(),
+                    text: "⚠️ Hot: cpu.pct is 95.0% (> 90.0%) (17m ago)".to_string(),
+                    enabled: false,
+                },
+            ]
+        );
+        assert!(alert_entries([].iter(), 0).is_empty());
+    }
 
-        let cloned = stats.clone();
-        assert_eq!(stats.cpu_usage, cloned.cpu_usage);
-        assert_eq!(stats.memory_used, cloned.memory_used);
-        assert_eq!(stats.memory_total, cloned.memory_total);
-        assert_eq!(stats.memory_percent, cloned.memory_percent);
-        assert_eq!(stats.battery_percent, cloned.battery_percent);
-        assert_eq!(stats.battery_state, cloned.battery_state);
+    #[test]
+    fn test_hook_entries() {
+        let (config, errors) = Config::parse(
+            r#"
+            [[hooks]]
+            name = "Pause sync"
+            command = "syncctl pause"
+            on_battery = "Discharging"
+
+            [[hooks]]
+            name = "Log alerts"
+            command = "logger alert"
+            on_alert = "*"
+            enabled = false
+            "#,
+        );
+        assert!(errors.is_empty());
+        let mut runner = HookRunner::new(config.hooks, None);
+        runner.toggle(0);
+        let entries = hook_entries(runner.hooks());
+        assert_eq!(
+            entries,
+            [
+                HookMenuEntry {
+                    id: "hook_0".to_string(),
+                    text: "Pause sync".to_string(),
+                    checked: false,
+                },
+                HookMenuEntry {
+                    id: "hook_1".to_string(),
+                    text: "Log alerts".to_string(),
+                    checked: false,
+                },
+            ]
+        );
+        assert_eq!(
+            parse_indexed_menu_id(MENU_HOOK_PREFIX, &entries[1].id),
+            Some(1)
+        );
+        assert_eq!(parse_indexed_menu_id(MENU_HOOK_PREFIX, "hook_none"), None);
+        assert_eq!(parse_indexed_menu_id(MENU_HOOK_PREFIX, MENU_HOOKS), None);
     }
 
     #[test]
-    fn test_system_stats_serialization() {
-        let stats = SystemStats {
-            cpu_usage: 50.0,
-            memory_used: 1073741824,
-            memory_total: 2147483648,
-            memory_percent: 50.0,
-            battery_percent: 80.0,
-            battery_state: "Charging".to_string(),
-        };
+    fn test_config_modified() {
+        let path =
+            std::env::temp_dir().join(format!("system-stats-modified-{}.toml", std::process::id()));
+        assert_eq!(config_modified(&path), None);
 
-        let json = serde_json::to_string(&stats);
-        assert!(json.is_ok());
+        std::fs::write(&path, "refresh_interval_secs = 5\n").unwrap();
+        assert!(config_modified(&path).is_some());
 
-        let json_str = json.unwrap();
-        let deserialized: Result<SystemStats, _> = serde_json::from_str(&json_str);
-        assert!(deserialized.is_ok());
+        let update = load_config(Some(&path));
+        assert_eq!(update.config.refresh_interval_secs, 5);
+        assert!(update.errors.is_empty());
+        std::fs::remove_file(&path).unwrap();
 
-        let deserialized_stats = deserialized.unwrap();
-        assert_eq!(stats.cpu_usage, deserialized_stats.cpu_usage);
-        assert_eq!(stats.memory_used, deserialized_stats.memory_used);
+        let update = load_config(None);
+        assert_eq!(update.config, Config::default());
     }
 
     #[test]
     fn test_constants() {
-        assert_eq!(UPDATE_INTERVAL_SECS, 3);
-        assert_eq!(BYTES_TO_GB, 1073741824.0);
+        assert_eq!(CONFIG_FILE_NAME, "config.toml");
+        assert_eq!(CONFIG_POLL_INTERVAL_SECS, 2);
+        assert_eq!(MENU_CONFIG_ERRORS, "config_errors");
+        assert_eq!(MENU_RECENT_ALERTS, "recent_alerts");
         assert_eq!(TRAY_ID, "menu_bar_stats_tray");
-        assert_eq!(MENU_BATTERY, "battery");
-        assert_eq!(MENU_CPU, "cpu");
-        assert_eq!(MENU_MEMORY, "memory");
+        assert_eq!(MENU_LOAD_AVERAGE_TRAY, "load_average_tray");
+        assert_eq!(MENU_TEMPERATURE_TRAY, "temperature_tray");
+        assert_eq!(MENU_TOP_CPU, "top_cpu");
+        assert_eq!(MENU_TOP_MEMORY, "top_memory");
+        assert_eq!(TOP_PROCESS_COUNT, 5);
+        assert_eq!(MENU_PROCESS_CONFIRM, "process_confirm");
+        assert_eq!(MENU_PROCESS_STATUS, "process_status");
         assert_eq!(MENU_AUTOSTART, "autostart");
+        assert_eq!(MENU_HOOKS, "hooks");
         assert_eq!(MENU_QUIT, "quit");
     }
-
-    #[test]
-    fn test_system_stats_boundary_values() {
-        let stats = SystemStats {
-            cpu_usage: 100.0,
-            memory_used: u64::MAX,
-            memory_total: u64::MAX,
-            memory_percent: 100.0,
-            battery_percent: 100.0,
-            battery_state: "Full".to_string(),
-        };
-
-        let _ = format_tray_title(&stats);
-        let _ = format_battery_text(&stats);
-        let _ = format_cpu_text(&stats);
-        let _ = format_memory_text(&stats);
-    }
 }
@@ -0,0 +1,129 @@
+//! Tray menu entries contributed by each [`Collector`](crate::Collector).
+
+use crate::format::format_summary_text;
+use crate::history::History;
+
+/// One line of a collector's section of the tray menu.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuEntry {
+    /// Identifies the entry across refreshes, so the menu can be updated in place. Unique within
+    /// the collector; [`Registry::menu_entries`](crate::Registry::menu_entries) prefixes it with
+    /// the collector name.
+    pub id: String,
+    pub text: String,
+    /// Copied to the clipboard when the entry is clicked. Entries without it are disabled.
+    pub copy_text: Option<String>,
+    /// Entries of the submenu this entry opens. The tray menu nests one level deep, so the
+    /// children's own children are not shown.
+    pub children: Vec<MenuEntry>,
+}
+
+impl MenuEntry {
+    pub fn new(id: impl Into<String>, text: impl Into<String>, copy_text: Option<String>) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+            copy_text,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn submenu(
+        id: impl Into<String>,
+        text: impl Into<String>,
+        children: Vec<MenuEntry>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            text: text.into(),
+            copy_text: None,
+            children,
+        }
+    }
+
+    /// Prefixes the ids of this entry and its children with `prefix`.
+    pub(crate) fn with_id_prefix(mut self, prefix: &str) -> Self {
+        self.id = format!("{}{}", prefix, self.id);
+        self.children = self
+            .children
+            .into_iter()
+            .map(|child| child.with_id_prefix(prefix))
+            .collect();
+        self
+    }
+}
+
+/// Finds the entry with `id` among `entries` and their children.
+pub fn find_menu_entry<'a>(entries: &'a [MenuEntry], id: &str) -> Option<&'a MenuEntry> {
+    entries.iter().find_map(|entry| {
+        if entry.id == id {
+            Some(entry)
+        } else {
+            find_menu_entry(&entry.children, id)
+        }
+    })
+}
+
+/// Appends the min/avg/max of `metric` over `history` to `text`, after `label`. A single sample
+/// has nothing to summarize yet.
+pub fn with_summary(text: String, label: &str, history: &History, metric: &str) -> String {
+    if history.len() < 2 {
+        return text;
+    }
+    match history.summary(metric) {
+        Some(summary) => format!("{} · {}{}", text, label, format_summary_text(&summary)),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::SystemStats;
+
+    #[test]
+    fn test_find_menu_entry() {
+        let entries = [
+            MenuEntry::new("cpu", "CPU", Some("5%".to_string())),
+            MenuEntry::submenu(
+                "cores",
+                "CPU",
+                vec![MenuEntry::new("core_0", "Core 1", Some("7%".to_string()))],
+            ),
+        ]
+        .map(|entry| entry.with_id_prefix("cpu/"));
+
+        assert_eq!(entries[1].id, "cpu/cores");
+        assert_eq!(
+            find_menu_entry(&entries, "cpu/core_0").and_then(|entry| entry.copy_text.as_deref()),
+            Some("7%")
+        );
+        assert_eq!(find_menu_entry(&entries, "cpu/cpu").unwrap().text, "CPU");
+        assert!(find_menu_entry(&entries, "core_0").is_none());
+    }
+
+    #[test]
+    fn test_with_summary() {
+        let mut history = History::new(10);
+        let sample = |cpu_usage| SystemStats {
+            cpu_usage,
+            ..Default::default()
+        };
+
+        history.push(sample(10.0));
+        assert_eq!(
+            with_summary("CPU".to_string(), "", &history, "cpu.pct"),
+            "CPU"
+        );
+
+        history.push(sample(30.0));
+        assert_eq!(
+            with_summary("CPU".to_string(), "", &history, "cpu.pct"),
+            "CPU · min 10.0% avg 20.0% max 30.0%"
+        );
+        assert_eq!(
+            with_summary("CPU".to_string(), "avg ", &history, "gpu.pct"),
+            "CPU"
+        );
+    }
+}
@@ -0,0 +1,761 @@
+//! Prometheus and OpenMetrics exposition of the latest sample, served on localhost.
+//!
+//! Every [`Reading`] becomes a sample of the family `system_stats_<collector>_<name>_<unit>`,
+//! e.g. `disk.used` is `system_stats_disk_used_bytes`. Readings of a single device carry it as a
+//! label named after the collector, e.g. `core="core1"` or `mount_point="/"`; top processes carry
+//! their name in `process` and their id in `pid`, since names repeat. A family never
+//! mixes those with an aggregate: the aggregate of a reading that is also reported per device
+//! gets a name of its own, such as `system_stats_cpu_overall_usage_percent` for the usage of all
+//! cores or `system_stats_disk_fullest_percent` for the fullest volume. `total_*` readings are
+//! counters, all others gauges. The battery state and boot time, which have no reading, are
+//! added from the [`SystemStats`].
+//!
+//! ```text
+//! # HELP system_stats_cpu_usage_percent CPU usage of the core in `core`.
+//! # TYPE system_stats_cpu_usage_percent gauge
+//! system_stats_cpu_usage_percent{core="core1"} 20
+//! # HELP system_stats_cpu_overall_usage_percent CPU usage of all cores.
+//! # TYPE system_stats_cpu_overall_usage_percent gauge
+//! system_stats_cpu_overall_usage_percent 12.5
+//! ```
+//!
+//! [`MetricsServer`] answers `GET /metrics` with the Prometheus text format, or with OpenMetrics
+//! when the `Accept` header asks for `application/openmetrics-text`.
+
+use crate::battery::BATTERY_STATES;
+use crate::collect::parse_process_instance;
+use crate::stats::{Reading, SystemStats, Unit};
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_METRICS_PORT: u16 = 9851;
+pub const METRICS_PATH: &str = "/metrics";
+const METRIC_PREFIX: &str = "system_stats_";
+/// The keys the built-in aggregates are exported as, since their readings are also reported
+/// per device.
+const AGGREGATE_KEYS: [(&str, &str); 6] = [
+    ("cpu.usage", "cpu.overall_usage"),
+    ("disk.percent", "disk.fullest_percent"),
+    ("network.received", "network.overall_received"),
+    ("network.transmitted", "network.overall_transmitted"),
+    ("battery.percent", "battery.combined_percent"),
+    ("temperatures.current", "temperatures.hottest"),
+];
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest request head read before answering, far more than any scraper sends.
+const MAX_REQUEST_HEAD_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExpositionFormat {
+    /// The Prometheus text format, version 0.0.4.
+    #[default]
+    Prometheus,
+    OpenMetrics,
+}
+
+impl ExpositionFormat {
+    /// Picks the format a scraper asks for in its `Accept` header.
+    pub fn from_accept(accept: &str) -> Self {
+        if accept
+            .to_ascii_lowercase()
+            .contains("application/openmetrics-text")
+        {
+            ExpositionFormat::OpenMetrics
+        } else {
+            ExpositionFormat::Prometheus
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExpositionFormat::Prometheus => "text/plain; version=0.0.4; charset=utf-8",
+            ExpositionFormat::OpenMetrics => {
+                "application/openmetrics-text; version=1.0.0; charset=utf-8"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetricType {
+    Gauge,
+    Counter,
+}
+
+/// The labels of a sample, in order.
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Debug)]
+struct Family {
+    /// The family name, without the `_total` of counter samples.
+    name: String,
+    help: String,
+    unit: Option<&'static str>,
+    metric_type: MetricType,
+    samples: Vec<(Labels, f64)>,
+}
+
+impl Family {
+    fn new(
+        name: String,
+        help: String,
+        unit: Option<&'static str>,
+        metric_type: MetricType,
+    ) -> Self {
+        Self {
+            name,
+            help,
+            unit,
+            metric_type,
+            samples: Vec::new(),
+        }
+    }
+
+    fn sample_name(&self) -> String {
+        match self.metric_type {
+            MetricType::Gauge => self.name.clone(),
+            MetricType::Counter => format!("{}_total", self.name),
+        }
+    }
+
+    fn render(&self, format: ExpositionFormat, out: &mut String) {
+        // The Prometheus format names a counter family after its samples, OpenMetrics without
+        // the `_total` suffix.
+        let name = match format {
+            ExpositionFormat::Prometheus => self.sample_name(),
+            ExpositionFormat::OpenMetrics => self.name.clone(),
+        };
+        let metric_type = match self.metric_type {
+            MetricType::Gauge => "gauge",
+            MetricType::Counter => "counter",
+        };
+        out.push_str(&format!("# HELP {} {}\n", name, escape_help(&self.help)));
+        out.push_str(&format!("# TYPE {} {}\n", name, metric_type));
+        if let (ExpositionFormat::OpenMetrics, Some(unit)) = (format, self.unit) {
+            out.push_str(&format!("# UNIT {} {}\n", name, unit));
+        }
+
+        let sample_name = self.sample_name();
+        for (labels, value) in &self.samples {
+            if labels.is_empty() {
+                out.push_str(&format!("{} {}\n", sample_name, format_value(*value)));
+                continue;
+            }
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape_label_value(value)))
+                .collect();
+            out.push_str(&format!(
+                "{}{{{}}} {}\n",
+                sample_name,
+                labels.join(","),
+                format_value(*value)
+            ));
+        }
+    }
+}
+
+/// The unit suffix of a metric name. Counts are dimensionless and have none.
+fn unit_suffix(unit: Unit) -> Option<&'static str> {
+    match unit {
+        Unit::Count => None,
+        unit => Some(unit.name()),
+    }
+}
+
+/// The family name and type of the readings with `key` and `unit`.
+fn family_name(key: &str, unit: Unit) -> (String, MetricType) {
+    let (collector, field) = key.split_once('.').unwrap_or(("", key));
+    let (field, metric_type) = match field.strip_prefix("total_") {
+        Some(field) => (field, MetricType::Counter),
+        None => (field, MetricType::Gauge),
+    };
+
+    let mut name: String = [collector, field]
+        .iter()
+        .filter(|part| !part.is_empty())
+        .flat_map(|part| std::iter::once('_').chain(part.chars()))
+        .skip(1)
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    name.insert_str(0, METRIC_PREFIX);
+    if let Some(suffix) = unit_suffix(unit) {
+        if !name.ends_with(&format!("_{}", suffix)) {
+            name.push('_');
+            name.push_str(suffix);
+        }
+    }
+    (name, metric_type)
+}
+
+/// The key `reading` is exported as. Aggregates are renamed so they do not share a family with
+/// per-device readings: the built-in ones after [`AGGREGATE_KEYS`], any other reading in
+/// `per_device` with an `overall_` prefix.
+fn export_key(reading: &Reading, per_device: &HashSet<&str>) -> String {
+    let key = reading.key.as_str();
+    if reading.instance.is_some() {
+        return key.to_string();
+    }
+    if let Some((_, renamed)) = AGGREGATE_KEYS.iter().find(|(from, _)| *from == key) {
+        return renamed.to_string();
+    }
+    if !per_device.contains(key) {
+        return key.to_string();
+    }
+    match key.split_once('.') {
+        Some((collector, field)) => format!("{}.overall_{}", collector, field),
+        None => format!("overall_{}", key),
+    }
+}
+
+/// The label naming the device of a per-device reading.
+fn instance_label(key: &str) -> &'static str {
+    match key.split_once('.').map_or(key, |(collector, _)| collector) {
+        "cpu" => "core",
+        "disk" => "mount_point",
+        "network" => "interface",
+        "battery" => "battery",
+        "temperatures" => "sensor",
+        "processes" => "process",
+        _ => "device",
+    }
+}
+
+/// The labels of a reading: its instance under [`instance_label`], or the name and pid of a
+/// process.
+fn labels(key: &str, instance: Option<&str>) -> Labels {
+    let Some(instance) = instance else {
+        return Vec::new();
+    };
+    if instance_label(key) == "process" {
+        if let Some((name, pid)) = parse_process_instance(instance) {
+            return vec![("process", name.to_string()), ("pid", pid.to_string())];
+        }
+    }
+    vec![(instance_label(key), instance.to_string())]
+}
+
+fn help(key: &str) -> String {
+    let help = match key {
+        "cpu.usage" => "CPU usage of the core in `core`.",
+        "cpu.overall_usage" => "CPU usage of all cores.",
+        "cpu.load_one" => "Load average over the last minute.",
+        "cpu.load_five" => "Load average over the last 5 minutes.",
+        "cpu.load_fifteen" => "Load average over the last 15 minutes.",
+        "cpu.uptime" => "Time since boot.",
+        "memory.percent" => "Share of memory in use.",
+        "memory.used" => "Memory in use.",
+        "memory.total" => "Installed memory.",
+        "memory.available" => "Memory available to new processes without swapping.",
+        "memory.pressure" => "Memory pressure: 0 normal, 1 warning, 2 critical.",
+        "memory.swap_used" => "Swap in use.",
+        "memory.swap_percent" => "Share of swap in use.",
+        "disk.read" => "Bytes read from all disks per second.",
+        "disk.written" => "Bytes written to all disks per second.",
+        "disk.percent" => "Space used on the volume in `mount_point`.",
+        "disk.fullest_percent" => "Space used on the fullest volume.",
+        "disk.used" => "Space used on the volume in `mount_point`.",
+        "disk.total" => "Size of the volume in `mount_point`.",
+        "network.received" => "Bytes received per second on `interface`.",
+        "network.transmitted" => "Bytes transmitted per second on `interface`.",
+        "network.overall_received" => "Bytes received per second on all interfaces.",
+        "network.overall_transmitted" => "Bytes transmitted per second on all interfaces.",
+        "network.total_received" => "Bytes received on `interface` since boot.",
+        "network.total_transmitted" => "Bytes transmitted on `interface` since boot.",
+        "battery.percent" => "Charge of `battery`.",
+        "battery.combined_percent" => "Combined charge of all batteries.",
+        "battery.power" => "Power drawn from or charged into all batteries.",
+        "battery.time_to_empty" => "Time until the batteries are empty while discharging.",
+        "battery.health" => "Full charge capacity of `battery` relative to its design capacity.",
+        "battery.cycles" => "Charge cycles of `battery`.",
+        "processes.cpu_usage" => "CPU usage of a top process, named in `process` and `pid`.",
+        "processes.memory" => "Memory used by a top process, named in `process` and `pid`.",
+        "temperatures.current" => "Temperature of `sensor`.",
+        "temperatures.hottest" => "Temperature of the hottest sensor.",
+        _ => return format!("The `{}` reading.", key),
+    };
+    help.to_string()
+}
+
+fn escape_help(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_label_value(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Renders a sample and its readings as a complete exposition in `format`.
+pub fn render_metrics(
+    stats: &SystemStats,
+    readings: &[Reading],
+    format: ExpositionFormat,
+) -> String {
+    let per_device: HashSet<&str> = readings
+        .iter()
+        .filter(|reading| reading.instance.is_some())
+        .map(|reading| reading.key.as_str())
+        .collect();
+    let mut families: Vec<Family> = Vec::new();
+    let mut seen = HashSet::new();
+    for reading in readings {
+        let key = export_key(reading, &per_device);
+        let (name, metric_type) = family_name(&key, reading.unit);
+        let labels = labels(&key, reading.instance.as_deref());
+        if !seen.insert((name.clone(), labels.clone())) {
+            continue;
+        }
+        let index = match families.iter().position(|family| family.name == name) {
+            Some(index) => index,
+            None => {
+                families.push(Family::new(
+                    name,
+                    help(&key),
+                    unit_suffix(reading.unit),
+                    metric_type,
+                ));
+                families.len() - 1
+            }
+        };
+        families[index].samples.push((labels, reading.value));
+    }
+
+    if !stats.batteries.is_empty() {
+        let mut state = Family::new(
+            format!("{}battery_state", METRIC_PREFIX),
+            "1 for the current battery state in `state`, 0 for the others.".to_string(),
+            None,
+            MetricType::Gauge,
+        );
+        for name in BATTERY_STATES {
+            let value = if stats.battery_state == name {
+                1.0
+            } else {
+                0.0
+            };
+            state
+                .samples
+                .push((vec![("state", name.to_string())], value));
+        }
+        families.push(state);
+    }
+    if stats.boot_time > 0 {
+        let mut boot_time = Family::new(
+            format!("{}boot_time_seconds", METRIC_PREFIX),
+            "Time of the last boot in seconds since the Unix epoch.".to_string(),
+            Some("seconds"),
+            MetricType::Gauge,
+        );
+        boot_time.samples.push((Vec::new(), stats.boot_time as f64));
+        families.push(boot_time);
+    }
+
+    let mut out = String::new();
+    for family in &families {
+        family.render(format, &mut out);
+    }
+    if format == ExpositionFormat::OpenMetrics {
+        out.push_str("# EOF\n");
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+    /// Whether the body is left out, as for `HEAD` requests.
+    head_only: bool,
+}
+
+impl Response {
+    fn text(status: &'static str, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.to_string(),
+            head_only: false,
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        );
+        if self.status.starts_with("405") {
+            bytes.push_str("Allow: GET, HEAD\r\n");
+        }
+        bytes.push_str("\r\n");
+        if !self.head_only {
+            bytes.push_str(&self.body);
+        }
+        bytes.into_bytes()
+    }
+}
+
+/// Answers the request with head `head`. `snapshot` is only called for `/metrics`.
+fn respond(head: &str, snapshot: impl FnOnce() -> Option<(SystemStats, Vec<Reading>)>) -> Response {
+    let mut lines = head.lines();
+    let mut request = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request.next(), request.next()) else {
+        return Response::text("400 Bad Request", "Malformed request\n");
+    };
+    let accept = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("accept"))
+        .map_or("", |(_, value)| value.trim());
+
+    let head_only = match method {
+        "GET" => false,
+        "HEAD" => true,
+        _ => {
+            return Response::text(
+                "405 Method Not Allowed",
+                "Only GET and HEAD are supported\n",
+            )
+        }
+    };
+    let response = match target.split('?').next().unwrap_or_default() {
+        METRICS_PATH => match snapshot() {
+            Some((stats, readings)) => {
+                let format = ExpositionFormat::from_accept(accept);
+                Response {
+                    status: "200 OK",
+                    content_type: format.content_type(),
+                    body: render_metrics(&stats, &readings, format),
+                    head_only: false,
+                }
+            }
+            None => Response::text("503 Service Unavailable", "No sample collected yet\n"),
+        },
+        "/" => Response::text("200 OK", "system-stats exporter, metrics are at /metrics\n"),
+        _ => Response::text("404 Not Found", "Not found, metrics are at /metrics\n"),
+    };
+    Response {
+        head_only,
+        ..response
+    }
+}
+
+/// Reads up to the blank line that ends the request head. Request bodies are ignored.
+fn read_request_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() >= MAX_REQUEST_HEAD_BYTES {
+            break;
+        }
+        match stream.read(&mut buffer)? {
+            0 => break,
+            read => head.extend_from_slice(&buffer[..read]),
+        }
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    snapshot: &dyn Fn() -> Option<(SystemStats, Vec<Reading>)>,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let head = read_request_head(&mut stream)?;
+    stream.write_all(&respond(&head, snapshot).to_bytes())
+}
+
+/// An HTTP server on `127.0.0.1` serving `/metrics` from a thread of its own. Dropping it stops
+/// the server.
+#[derive(Debug)]
+pub struct MetricsServer {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+impl MetricsServer {
+    /// Binds to `port` on localhost, or to a free port for 0. `snapshot` returns the latest
+    /// sample and its readings, or `None` before the first one.
+    pub fn start<F>(port: u16, snapshot: F) -> io::Result<Self>
+    where
+        F: Fn() -> Option<(SystemStats, Vec<Reading>)> + Send + 'static,
+    {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let address = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let stopped = stop.clone();
+        std::thread::Builder::new()
+            .name("metrics-server".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let result = stream.and_then(|stream| handle_connection(stream, &snapshot));
+                    if let Err(e) = result {
+                        eprintln!("Failed to serve metrics request: {}", e);
+                    }
+                }
+            })?;
+        Ok(Self { address, stop })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wakes the server thread from `accept` so it sees the flag and releases the port.
+        let _ = TcpStream::connect_timeout(&self.address, REQUEST_TIMEOUT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::BatteryStats;
+
+    fn sample() -> (SystemStats, Vec<Reading>) {
+        let stats = SystemStats {
+            battery_state: "Charging".to_string(),
+            batteries: vec![BatteryStats::default()],
+            boot_time: 1_700_000_000,
+            ..Default::default()
+        };
+        let readings = vec![
+            Reading::new("cpu.usage", 12.5, Unit::Percent),
+            Reading::new("cpu.usage", 20.0, Unit::Percent).with_instance("core1"),
+            Reading::new("cpu.load_one", 1.5, Unit::Count),
+            Reading::new("memory.percent", 50.0, Unit::Percent),
+            Reading::new("network.total_received", 1024.0, Unit::Bytes)
+                .with_instance("en0 \"wifi\""),
+            Reading::new("gpu.usage", f64::NAN, Unit::Percent),
+            Reading::new("gpu.usage", 40.0, Unit::Percent).with_instance("gpu0"),
+            Reading::new("processes.memory", 2048.0, Unit::Bytes).with_instance("cargo (42)"),
+            Reading::new("processes.memory", 1024.0, Unit::Bytes).with_instance("cargo (43)"),
+        ];
+        (stats, readings)
+    }
+
+    #[test]
+    fn test_family_name() {
+        assert_eq!(
+            family_name("cpu.usage", Unit::Percent),
+            (
+                "system_stats_cpu_usage_percent".to_string(),
+                MetricType::Gauge
+            )
+        );
+        assert_eq!(
+            family_name("memory.swap_percent", Unit::Percent).0,
+            "system_stats_memory_swap_percent"
+        );
+        assert_eq!(
+            family_name("network.received", Unit::BytesPerSecond).0,
+            "system_stats_network_received_bytes_per_second"
+        );
+        assert_eq!(
+            family_name("network.total_received", Unit::Bytes),
+            (
+                "system_stats_network_received_bytes".to_string(),
+                MetricType::Counter
+            )
+        );
+        assert_eq!(
+            family_name("queue-depth", Unit::Count).0,
+            "system_stats_queue_depth"
+        );
+        assert_eq!(instance_label("disk.used"), "mount_point");
+
+        let per_device = HashSet::from(["disk.percent", "queue.depth"]);
+        let export = |reading: Reading| export_key(&reading, &per_device);
+        assert_eq!(
+            export(Reading::new("disk.percent", 90.0, Unit::Percent)),
+            "disk.fullest_percent"
+        );
+        assert_eq!(
+            export(Reading::new("disk.percent", 90.0, Unit::Percent).with_instance("/")),
+            "disk.percent"
+        );
+        assert_eq!(
+            export(Reading::new("queue.depth", 3.0, Unit::Count)),
+            "queue.overall_depth"
+        );
+        assert_eq!(
+            export(Reading::new("disk.read", 1.0, Unit::BytesPerSecond)),
+            "disk.read"
+        );
+        assert_eq!(
+            family_name("disk.fullest_percent", Unit::Percent).0,
+            "system_stats_disk_fullest_percent"
+        );
+        assert_eq!(instance_label("gpu.usage"), "device");
+        assert_eq!(
+            labels("processes.memory", Some("Web Content (12)")),
+            vec![
+                ("process", "Web Content".to_string()),
+                ("pid", "12".to_string())
+            ]
+        );
+        assert_eq!(
+            labels("processes.memory", Some("kernel_task")),
+            vec![("process", "kernel_task".to_string())]
+        );
+        assert!(labels("memory.percent", None).is_empty());
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let (stats, readings) = sample();
+        let prometheus = render_metrics(&stats, &readings, ExpositionFormat::Prometheus);
+        assert_eq!(
+            prometheus,
+            r#"# HELP system_stats_cpu_overall_usage_percent CPU usage of all cores.
+# TYPE system_stats_cpu_overall_usage_percent gauge
+system_stats_cpu_overall_usage_percent 12.5
+# HELP system_stats_cpu_usage_percent CPU usage of the core in `core`.
+# TYPE system_stats_cpu_usage_percent gauge
+system_stats_cpu_usage_percent{core="core1"} 20
+# HELP system_stats_cpu_load_one Load average over the last minute.
+# TYPE system_stats_cpu_load_one gauge
+system_stats_cpu_load_one 1.5
+# HELP system_stats_memory_percent Share of memory in use.
+# TYPE system_stats_memory_percent gauge
+system_stats_memory_percent 50
+# HELP system_stats_network_received_bytes_total Bytes received on `interface` since boot.
+# TYPE system_stats_network_received_bytes_total counter
+system_stats_network_received_bytes_total{interface="en0 \"wifi\""} 1024
+# HELP system_stats_gpu_overall_usage_percent The `gpu.overall_usage` reading.
+# TYPE system_stats_gpu_overall_usage_percent gauge
+system_stats_gpu_overall_usage_percent NaN
+# HELP system_stats_gpu_usage_percent The `gpu.usage` reading.
+# TYPE system_stats_gpu_usage_percent gauge
+system_stats_gpu_usage_percent{device="gpu0"} 40
+# HELP system_stats_processes_memory_bytes Memory used by a top process, named in `process` and `pid`.
+# TYPE system_stats_processes_memory_bytes gauge
+system_stats_processes_memory_bytes{process="cargo",pid="42"} 2048
+system_stats_processes_memory_bytes{process="cargo",pid="43"} 1024
+# HELP system_stats_battery_state 1 for the current battery state in `state`, 0 for the others.
+# TYPE system_stats_battery_state gauge
+system_stats_battery_state{state="Charging"} 1
+system_stats_battery_state{state="Discharging"} 0
+system_stats_battery_state{state="Full"} 0
+system_stats_battery_state{state="Empty"} 0
+system_stats_battery_state{state="Unknown"} 0
+# HELP system_stats_boot_time_seconds Time of the last boot in seconds since the Unix epoch.
+# TYPE system_stats_boot_time_seconds gauge
+system_stats_boot_time_seconds 1700000000
+"#
+        );
+
+        let openmetrics = render_metrics(&stats, &readings, ExpositionFormat::OpenMetrics);
+        assert!(openmetrics.contains(
+            "# TYPE system_stats_network_received_bytes counter\n\
+             # UNIT system_stats_network_received_bytes bytes\n\
+             system_stats_network_received_bytes_total{interface=\"en0 \\\"wifi\\\"\"} 1024\n"
+        ));
+        assert!(openmetrics.contains("# UNIT system_stats_cpu_overall_usage_percent percent\n"));
+        assert!(!openmetrics.contains("# UNIT system_stats_cpu_load_one"));
+        assert!(openmetrics.ends_with("# EOF\n"));
+
+        // A reading reported twice would be an invalid duplicate series.
+        let twice = [readings[0].clone(), readings[0].clone()];
+        let rendered = render_metrics(
+            &SystemStats::default(),
+            &twice,
+            ExpositionFormat::Prometheus,
+        );
+        assert_eq!(
+            rendered
+                .matches("system_stats_cpu_overall_usage_percent 12.5")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_respond() {
+        let get = |head: &str| respond(head, || Some(sample()));
+
+        let response = get("GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert_eq!(response.status, "200 OK");
+        assert_eq!(
+            response.content_type,
+            ExpositionFormat::Prometheus.content_type()
+        );
+        assert!(!response.body.contains("# EOF"));
+
+        let response = get(
+            "GET /metrics?x=1 HTTP/1.1\r\nACCEPT: application/openmetrics-text;version=1.0.0,text/plain;q=0.5\r\n\r\n",
+        );
+        assert_eq!(
+            response.content_type,
+            ExpositionFormat::OpenMetrics.content_type()
+        );
+        assert!(response.body.ends_with("# EOF\n"));
+
+        let response = get("HEAD /metrics HTTP/1.1\r\n\r\n");
+        assert!(response.head_only);
+        let bytes = String::from_utf8(response.to_bytes()).unwrap();
+        assert!(bytes.ends_with(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            response.body.len()
+        )));
+
+        assert_eq!(get("GET /other HTTP/1.1\r\n\r\n").status, "404 Not Found");
+        assert_eq!(get("garbage").status, "400 Bad Request");
+        let response = get("POST /metrics HTTP/1.1\r\n\r\n");
+        assert!(String::from_utf8(response.to_bytes())
+            .unwrap()
+            .starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert_eq!(
+            respond("GET /metrics HTTP/1.1\r\n\r\n", || None).status,
+            "503 Service Unavailable"
+        );
+    }
+
+    #[test]
+    fn test_metrics_server() {
+        let server = MetricsServer::start(0, || Some(sample())).unwrap();
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, server.port())).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("system_stats_boot_time_seconds 1700000000\n"));
+
+        let port = server.port();
+        drop(server);
+        for _ in 0..50 {
+            if TcpListener::bind((Ipv4Addr::LOCALHOST, port)).is_ok() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("the port was not released");
+    }
+}
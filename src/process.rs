@@ -0,0 +1,172 @@
+//! Sending signals to other processes, with guards for protected PIDs.
+
+use std::fmt;
+
+pub const PROTECTED_PIDS: [u32; 2] = [0, 1];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessSignal {
+    Terminate,
+    Kill,
+    Stop,
+    Continue,
+}
+
+impl ProcessSignal {
+    pub fn name(self) -> &'static str {
+        match self {
+            ProcessSignal::Terminate => "SIGTERM",
+            ProcessSignal::Kill => "SIGKILL",
+            ProcessSignal::Stop => "SIGSTOP",
+            ProcessSignal::Continue => "SIGCONT",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ProcessSignal::Terminate => "Terminate",
+            ProcessSignal::Kill => "Force Kill",
+            ProcessSignal::Stop => "Pause",
+            ProcessSignal::Continue => "Resume",
+        }
+    }
+
+    #[cfg(unix)]
+    fn as_raw(self) -> libc::c_int {
+        match self {
+            ProcessSignal::Terminate => libc::SIGTERM,
+            ProcessSignal::Kill => libc::SIGKILL,
+            ProcessSignal::Stop => libc::SIGSTOP,
+            ProcessSignal::Continue => libc::SIGCONT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessActionError {
+    Refused(String),
+    NotFound,
+    PermissionDenied,
+    Failed(String),
+}
+
+impl fmt::Display for ProcessActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessActionError::Refused(reason) => write!(f, "refused, {}", reason),
+            ProcessActionError::NotFound => write!(f, "process already gone"),
+            ProcessActionError::PermissionDenied => write!(f, "permission denied"),
+            ProcessActionError::Failed(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+fn check_pid_not_protected(pid: u32) -> Result<(), ProcessActionError> {
+    if PROTECTED_PIDS.contains(&pid) {
+        return Err(ProcessActionError::Refused(format!(
+            "PID {} is a protected system process",
+            pid
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn check_process_action_allowed(pid: u32, own_pid: u32) -> Result<(), ProcessActionError> {
+    check_pid_not_protected(pid)?;
+
+    if pid == own_pid {
+        return Err(ProcessActionError::Refused(
+            "cannot signal System Stats itself".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn send_process_signal(pid: u32, signal: ProcessSignal) -> Result<(), ProcessActionError> {
+    check_pid_not_protected(pid)?;
+    let pid = libc::pid_t::try_from(pid).map_err(|_| ProcessActionError::NotFound)?;
+
+    // SAFETY: kill(2) has no memory-safety preconditions. pid is positive, since 0 is refused
+    // above and larger values than pid_t holds are rejected by try_from, so kill cannot
+    // address a process group.
+    if unsafe { libc::kill(pid, signal.as_raw()) } == 0 {
+        return Ok(());
+    }
+
+    let error = std::io::Error::last_os_error();
+    match error.raw_os_error() {
+        Some(libc::ESRCH) => Err(ProcessActionError::NotFound),
+        Some(libc::EPERM) => Err(ProcessActionError::PermissionDenied),
+        _ => Err(ProcessActionError::Failed(error.to_string())),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_process_signal(_pid: u32, _signal: ProcessSignal) -> Result<(), ProcessActionError> {
+    Err(ProcessActionError::Failed(
+        "signals are not supported on this platform".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_process_action_allowed() {
+        assert!(check_process_action_allowed(4242, 100).is_ok());
+        assert!(matches!(
+            check_process_action_allowed(1, 100),
+            Err(ProcessActionError::Refused(_))
+        ));
+        assert!(matches!(
+            check_process_action_allowed(0, 100),
+            Err(ProcessActionError::Refused(_))
+        ));
+        assert!(matches!(
+            check_process_action_allowed(100, 100),
+            Err(ProcessActionError::Refused(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_process_signal() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .expect("Failed to spawn sleep");
+
+        assert_eq!(send_process_signal(child.id(), ProcessSignal::Stop), Ok(()));
+        assert_eq!(
+            send_process_signal(child.id(), ProcessSignal::Continue),
+            Ok(())
+        );
+        assert_eq!(
+            send_process_signal(child.id(), ProcessSignal::Terminate),
+            Ok(())
+        );
+        assert!(!child.wait().expect("Failed to wait for sleep").success());
+
+        // Pids stay far below pid_t::MAX, so this cannot hit a recycled pid.
+        assert_eq!(
+            send_process_signal(i32::MAX as u32, ProcessSignal::Terminate),
+            Err(ProcessActionError::NotFound)
+        );
+        assert_eq!(
+            send_process_signal(u32::MAX, ProcessSignal::Terminate),
+            Err(ProcessActionError::NotFound)
+        );
+        assert!(matches!(
+            send_process_signal(0, ProcessSignal::Kill),
+            Err(ProcessActionError::Refused(_))
+        ));
+        assert!(matches!(
+            send_process_signal(1, ProcessSignal::Kill),
+            Err(ProcessActionError::Refused(_))
+        ));
+    }
+}
@@ -0,0 +1,484 @@
+//! The [`Collector`] trait and the [`Registry`] that refreshes every collector once per tick.
+
+use crate::collect::builtin_collectors;
+use crate::config::{Config, ConfigError};
+use crate::format::{format_reading_text, format_reading_value, join_tray_segments, TrayOptions};
+use crate::history::History;
+use crate::menu::MenuEntry;
+use crate::stats::{Reading, SystemStats};
+use crate::template::TrayTemplate;
+use sysinfo::System;
+
+/// A source of metrics. Built-in collectors fill their own section of [`SystemStats`]; any other
+/// collector only needs [`Collector::name`], [`Collector::refresh`] and [`Collector::readings`].
+pub trait Collector: Send {
+    /// Unique name, also used as the first component of the collector's reading keys.
+    fn name(&self) -> &str;
+
+    /// Takes a new sample. `system` has already been refreshed for this tick.
+    fn refresh(&mut self, system: &System);
+
+    /// The readings from the latest sample.
+    fn readings(&self) -> Vec<Reading>;
+
+    /// Copies the latest sample into `stats`. By default the readings are appended to
+    /// [`SystemStats::extra`].
+    fn update_stats(&self, stats: &mut SystemStats) {
+        stats.extra.extend(self.readings());
+    }
+
+    /// The collector's menu bar segment, if it shows one. `history` is given when the segment
+    /// should draw a sparkline of recent samples instead of its current value alone.
+    fn tray_segment(&self, _options: &TrayOptions, _history: Option<&History>) -> Option<String> {
+        None
+    }
+
+    /// The collector's section of the tray menu, with summaries of `history` where useful. By
+    /// default there is one entry per reading that copies its value.
+    fn menu_entries(&self, _options: &TrayOptions, _history: &History) -> Vec<MenuEntry> {
+        self.readings()
+            .iter()
+            .map(|reading| {
+                MenuEntry::new(
+                    reading.id(),
+                    format_reading_text(reading),
+                    Some(format_reading_value(reading)),
+                )
+            })
+            .collect()
+    }
+
+    /// Applies the settings from a new or reloaded [`Config`].
+    fn configure(&mut self, _config: &Config) {}
+}
+
+/// Owns the shared [`System`], the registered collectors, in tray and menu order, and the
+/// [`History`] of recent snapshots.
+pub struct Registry {
+    system: System,
+    collectors: Vec<Box<dyn Collector>>,
+    history: History,
+    tray_segments: Option<Vec<String>>,
+    sparkline_segments: Vec<String>,
+    tray_template: Option<TrayTemplate>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        let mut system = System::new();
+        system.refresh_cpu();
+        system.refresh_memory();
+
+        Self {
+            system,
+            collectors: Vec::new(),
+            history: History::default(),
+            tray_segments: None,
+            sparkline_segments: Vec::new(),
+            tray_template: None,
+        }
+    }
+
+    /// A registry with the battery, CPU, memory, disk, network, process and temperature
+    /// collectors.
+    pub fn with_builtin_collectors() -> Self {
+        let mut registry = Self::new();
+        for collector in builtin_collectors() {
+            registry.register(collector);
+        }
+        registry
+    }
+
+    /// Adds a collector after the existing ones, replacing any collector with the same name
+    /// in place.
+    pub fn register(&mut self, collector: Box<dyn Collector>) {
+        match self
+            .collectors
+            .iter_mut()
+            .find(|existing| existing.name() == collector.name())
+        {
+            Some(existing) => *existing = collector,
+            None => self.collectors.push(collector),
+        }
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Option<Box<dyn Collector>> {
+        let index = self
+            .collectors
+            .iter()
+            .position(|collector| collector.name() == name)?;
+        Some(self.collectors.remove(index))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.collectors.iter().map(|collector| collector.name())
+    }
+
+    /// Sets which collectors show a menu bar segment, in order. Collectors that are not listed
+    /// only appear in the menu; `None` shows every collector in registration order. Returns the
+    /// listed names that match no registered collector.
+    pub fn set_tray_segments(&mut self, segments: Option<Vec<String>>) -> Vec<String> {
+        let unknown = segments
+            .iter()
+            .flatten()
+            .filter(|name| !self.names().any(|existing| existing == name.as_str()))
+            .cloned()
+            .collect();
+        self.tray_segments = segments;
+        unknown
+    }
+
+    /// Applies `config` to the tray, the history and every collector. Returns an error for each
+    /// configured segment that matches no registered collector.
+    pub fn configure(&mut self, config: &Config) -> Vec<ConfigError> {
+        for collector in &mut self.collectors {
+            collector.configure(config);
+        }
+        self.set_tray_template(config.tray_template.clone());
+        self.history.set_capacity(config.history_size);
+
+        let unknown = |field, names: Vec<String>| {
+            names.into_iter().map(move |name| ConfigError::Invalid {
+                field,
+                message: format!("no collector named {:?}", name),
+            })
+        };
+        let segments = self.set_tray_segments(Some(config.tray_segments.clone()));
+        let sparklines = self.set_sparkline_segments(config.tray_sparklines.clone());
+        unknown("tray.segments", segments)
+            .chain(unknown("tray.sparklines", sparklines))
+            .collect()
+    }
+
+    /// Sets which collectors draw a sparkline of the [`History`] in their tray segment. Returns
+    /// the names that match no registered collector.
+    pub fn set_sparkline_segments(&mut self, segments: Vec<String>) -> Vec<String> {
+        let unknown = segments
+            .iter()
+            .filter(|name| !self.names().any(|existing| existing == name.as_str()))
+            .cloned()
+            .collect();
+        self.sparkline_segments = segments;
+        unknown
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Renders the tray title from `template` instead of the collectors' segments.
+    pub fn set_tray_template(&mut self, template: Option<TrayTemplate>) {
+        self.tray_template = template;
+    }
+
+    pub fn system(&self) -> &System {
+        &self.system
+    }
+
+    /// Refreshes the shared [`System`] and then every collector in registration order.
+    pub fn refresh(&mut self) {
+        self.system.refresh_cpu();
+        self.system.refresh_memory();
+        self.system.refresh_processes();
+
+        for collector in &mut self.collectors {
+            collector.refresh(&self.system);
+        }
+        self.history.push(self.stats());
+    }
+
+    pub fn stats(&self) -> SystemStats {
+        let mut stats = SystemStats::default();
+        for collector in &self.collectors {
+            collector.update_stats(&mut stats);
+        }
+        stats
+    }
+
+    pub fn readings(&self) -> Vec<Reading> {
+        self.collectors
+            .iter()
+            .flat_map(|collector| collector.readings())
+            .collect()
+    }
+
+    /// Every collector's menu entries in registration order, with ids prefixed by
+    /// `<collector name>/` so they are unique across collectors.
+    pub fn menu_entries(&self, options: &TrayOptions) -> Vec<MenuEntry> {
+        self.collectors
+            .iter()
+            .flat_map(|collector| {
+                let prefix = format!("{}/", collector.name());
+                collector
+                    .menu_entries(options, &self.history)
+                    .into_iter()
+                    .map(move |entry| entry.with_id_prefix(&prefix))
+            })
+            .collect()
+    }
+
+    pub fn tray_title(&self, options: &TrayOptions) -> String {
+        if let Some(template) = &self.tray_template {
+            return template.render(&self.stats(), options);
+        }
+
+        let collectors: Vec<&dyn Collector> = match &self.tray_segments {
+            Some(names) => names
+                .iter()
+                .filter_map(|name| {
+                    self.collectors
+                        .iter()
+                        .find(|collector| collector.name() == name)
+                })
+                .map(|collector| collector.as_ref())
+                .collect(),
+            None => self
+                .collectors
+                .iter()
+                .map(|collector| collector.as_ref())
+                .collect(),
+        };
+
+        join_tray_segments(collectors.into_iter().filter_map(|collector| {
+            let history = self
+                .sparkline_segments
+                .iter()
+                .any(|name| name == collector.name())
+                .then_some(&self.history);
+            collector.tray_segment(options, history)
+        }))
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::format_tray_title;
+    use crate::stats::Unit;
+
+    struct QueueCollector {
+        name: &'static str,
+        depth: u32,
+    }
+
+    impl Collector for QueueCollector {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn refresh(&mut self, _system: &System) {
+            self.depth += 1;
+        }
+
+        fn readings(&self) -> Vec<Reading> {
+            vec![Reading::new(
+                format!("{}.depth", self.name),
+                self.depth as f64,
+                Unit::Count,
+            )]
+        }
+
+        fn tray_segment(&self, options: &TrayOptions, history: Option<&History>) -> Option<String> {
+            match history {
+                Some(history) => Some(format!(
+                    "📬 {}",
+                    history
+                        .values(
+                            &format!("reading.{}.depth", self.name),
+                            options.sparkline_length
+                        )
+                        .iter()
+                        .map(|depth| depth.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )),
+                None => Some(format!("📬 {}", self.depth)),
+            }
+        }
+    }
+
+    fn queue(name: &'static str) -> Box<dyn Collector> {
+        Box::new(QueueCollector { name, depth: 0 })
+    }
+
+    #[test]
+    fn test_registry_register_and_unregister() {
+        let mut registry = Registry::new();
+        registry.register(queue("mail"));
+        registry.register(queue("jobs"));
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["mail", "jobs"]);
+
+        registry.refresh();
+        registry.refresh();
+        registry.register(queue("mail"));
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["mail", "jobs"]);
+        assert_eq!(registry.readings()[0].value, 0.0);
+        assert_eq!(registry.readings()[1].value, 2.0);
+
+        assert!(registry.unregister("mail").is_some());
+        assert!(registry.unregister("mail").is_none());
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["jobs"]);
+    }
+
+    #[test]
+    fn test_registry_custom_collector() {
+        let mut registry = Registry::new();
+        registry.register(queue("mail"));
+        registry.refresh();
+
+        let stats = registry.stats();
+        assert_eq!(
+            stats.extra,
+            vec![Reading::new("mail.depth", 1.0, Unit::Count)]
+        );
+        assert_eq!(registry.tray_title(&TrayOptions::default()), "📬 1");
+        assert_eq!(
+            registry.menu_entries(&TrayOptions::default()),
+            [MenuEntry::new(
+                "mail/mail.depth",
+                "mail.depth: 1",
+                Some("1".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn test_registry_tray_segments() {
+        let mut registry = Registry::new();
+        registry.register(queue("mail"));
+        registry.register(queue("jobs"));
+        registry.refresh();
+        registry.register(queue("mail"));
+        let options = TrayOptions::default();
+
+        assert!(registry
+            .set_tray_segments(Some(vec!["jobs".to_string(), "mail".to_string()]))
+            .is_empty());
+        assert_eq!(registry.tray_title(&options), "📬 1 |  📬 0");
+
+        let unknown = registry.set_tray_segments(Some(vec!["gpu".to_string(), "jobs".to_string()]));
+        assert_eq!(unknown, ["gpu"]);
+        assert_eq!(registry.tray_title(&options), "📬 1");
+        assert_eq!(registry.readings().len(), 2);
+
+        assert!(registry.set_tray_segments(Some(Vec::new())).is_empty());
+        assert_eq!(registry.tray_title(&options), "");
+
+        assert!(registry.set_tray_segments(None).is_empty());
+        assert_eq!(registry.tray_title(&options), "📬 0 |  📬 1");
+
+        let template =
+            TrayTemplate::compile("mail {reading.mail.depth}, jobs {reading.jobs.depth}");
+        registry.set_tray_template(Some(template.unwrap()));
+        assert_eq!(registry.tray_title(&options), "mail 0, jobs 1");
+        registry.set_tray_template(None);
+        assert_eq!(registry.tray_title(&options), "📬 0 |  📬 1");
+    }
+
+    #[test]
+    fn test_registry_history_and_sparklines() {
+        let mut registry = Registry::new();
+        registry.register(queue("mail"));
+        registry.register(queue("jobs"));
+        for _ in 0..3 {
+            registry.refresh();
+        }
+        assert_eq!(registry.history().len(), 3);
+        assert_eq!(
+            registry.history().values("reading.mail.depth", 8),
+            [1.0, 2.0, 3.0]
+        );
+
+        let unknown = registry.set_sparkline_segments(vec!["mail".to_string(), "gpu".to_string()]);
+        assert_eq!(unknown, ["gpu"]);
+        let options = TrayOptions {
+            sparkline_length: 2,
+            ..Default::default()
+        };
+        assert_eq!(registry.tray_title(&options), "📬 2,3 |  📬 3");
+
+        let config = Config {
+            history_size: 2,
+            tray_segments: vec!["jobs".to_string()],
+            tray_sparklines: vec!["jobs".to_string(), "disk".to_string()],
+            ..Default::default()
+        };
+        let errors = registry.configure(&config);
+        assert_eq!(
+            errors,
+            [ConfigError::Invalid {
+                field: "tray.sparklines",
+                message: "no collector named \"disk\"".to_string(),
+            }]
+        );
+        assert_eq!(registry.history().len(), 2);
+        assert_eq!(registry.tray_title(&options), "📬 2,3");
+    }
+
+    #[test]
+    fn test_registry_builtin_collectors() {
+        let mut registry = Registry::with_builtin_collectors();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            [
+                "battery",
+                "cpu",
+                "memory",
+                "disk",
+                "network",
+                "processes",
+                "temperatures"
+            ]
+        );
+
+        registry.refresh();
+        let stats = registry.stats();
+        assert!(stats.extra.is_empty());
+        assert!(stats.memory_total > 0);
+        assert!(!stats.cpu_cores.is_empty());
+
+        let options = TrayOptions::default();
+        assert_eq!(
+            registry.tray_title(&options),
+            format_tray_title(&stats, &options)
+        );
+
+        registry.register(queue("mail"));
+        assert!(registry.tray_title(&options).ends_with(" |  📬 0"));
+
+        let config = Config {
+            tray_segments: vec!["memory".to_string(), "mail".to_string()],
+            ..Default::default()
+        };
+        assert!(registry.configure(&config).is_empty());
+        let title = registry.tray_title(&options);
+        assert!(title.starts_with("💾 "));
+        assert!(title.ends_with(" |  📬 0"));
+
+        let readings = registry.readings();
+        for key in [
+            "battery.percent",
+            "cpu.usage",
+            "memory.percent",
+            "disk.read",
+        ] {
+            assert!(
+                readings
+                    .iter()
+                    .any(|reading| reading.key == key && reading.instance.is_none()),
+                "missing {}",
+                key
+            );
+        }
+        assert!(readings.iter().all(|reading| {
+            let collector = reading.key.split('.').next().unwrap();
+            registry.names().any(|name| name == collector)
+        }));
+    }
+}
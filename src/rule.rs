@@ -0,0 +1,749 @@
+//! The alert rule language: compound conditions over [`SystemStats`].
+//!
+//! ```text
+//! cpu > 85 and battery.state == "Discharging" for 2m
+//! mem.percent > 90 or swap.used > 4GiB
+//! not battery.charging and (temp.current > 90C or net.rx >= 50MiB/s)
+//! ```
+//!
+//! - Metrics are the tray template placeholders, see `template`. `cpu` is short for `cpu.pct`
+//!   and `mem.percent` for `mem.pct`, and so on for every `*.pct` placeholder.
+//! - `<`, `<=`, `>`, `>=`, `==` and `!=` compare a metric with a literal. Numbers take an
+//!   optional unit: `%`, `W`, the sizes `B` to `TiB` (with `/s` for rates), `C` or `F`, and the
+//!   durations `s`, `m`, `h` and `d`. Text is compared case-insensitively with `==` or `!=`,
+//!   quoted or as a single word, and flags such as `battery.charging` with `true` or `false`.
+//! - Flags can stand on their own. `not`, `and` and `or` (or `!`, `&&` and `||`) combine
+//!   conditions, binding in that order, and parentheses group them.
+//! - A trailing `for <duration>`, e.g. `for 2m` or `for 1h 30m`, only fires once the condition
+//!   has held that long.
+//!
+//! Comparisons with a value that is not available are false. Battery metrics are not available
+//! without a battery, rather than reading 0%. [`Expression::parse`] checks metric names, units and
+//! types up front and reports the column of the first problem.
+
+use crate::format::format_reading_value;
+use crate::stats::{Reading, SystemStats, Unit};
+use crate::template::{lookup, placeholder_kind, DisplayUnit, Kind, Operator, Value, OPERATORS};
+use std::fmt;
+
+/// A problem found while parsing a rule, with the 1-based character column where it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number { value: f64, unit: String },
+    Text(String),
+    Bool(bool),
+    Operator(Operator),
+    And,
+    Or,
+    Not,
+    For,
+    Open,
+    Close,
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct Spanned {
+    token: Token,
+    column: usize,
+    text: String,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Spanned>, RuleError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        if c.is_whitespace() {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        let error = |message| RuleError {
+            column: start + 1,
+            message,
+        };
+        let rest: String = chars[index..].iter().take(2).collect();
+        let next = chars.get(index + 1).copied();
+
+        let token = if let Some((symbol, operator)) = OPERATORS
+            .iter()
+            .find(|(symbol, _)| rest.starts_with(symbol))
+        {
+            index += symbol.len();
+            Token::Operator(*operator)
+        } else if rest == "&&" || rest == "||" {
+            index += 2;
+            if c == '&' {
+                Token::And
+            } else {
+                Token::Or
+            }
+        } else if c == '!' || c == '(' || c == ')' {
+            index += 1;
+            match c {
+                '!' => Token::Not,
+                '(' => Token::Open,
+                _ => Token::Close,
+            }
+        } else if c == '"' {
+            index += 1;
+            let mut text = String::new();
+            loop {
+                match chars.get(index) {
+                    None => return Err(error("unclosed string".to_string())),
+                    Some('"') => break,
+                    Some('\\') if matches!(chars.get(index + 1), Some('"' | '\\')) => {
+                        text.push(chars[index + 1]);
+                        index += 2;
+                    }
+                    Some(&c) => {
+                        text.push(c);
+                        index += 1;
+                    }
+                }
+            }
+            index += 1;
+            Token::Text(text)
+        } else if c.is_ascii_digit() || (c == '.' && next.is_some_and(|c| c.is_ascii_digit())) {
+            while chars
+                .get(index)
+                .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+            {
+                index += 1;
+            }
+            let digits: String = chars[start..index].iter().collect();
+            let value = digits
+                .parse::<f64>()
+                .map_err(|_| error(format!("invalid number {:?}", digits)))?;
+
+            let unit_start = index;
+            if chars.get(index) == Some(&'%') {
+                index += 1;
+            } else {
+                while chars.get(index).is_some_and(|c| c.is_ascii_alphabetic()) {
+                    index += 1;
+                }
+                if index > unit_start
+                    && chars.get(index) == Some(&'/')
+                    && chars.get(index + 1) == Some(&'s')
+                {
+                    index += 2;
+                }
+            }
+            Token::Number {
+                value,
+                unit: chars[unit_start..index].iter().collect(),
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            while chars
+                .get(index)
+                .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+            {
+                index += 1;
+            }
+            let word: String = chars[start..index].iter().collect();
+            match word.as_str() {
+                "and" => Token::And,
+                "or" => Token::Or,
+                "not" => Token::Not,
+                "for" => Token::For,
+                "true" => Token::Bool(true),
+                "false" => Token::Bool(false),
+                _ => Token::Name(word),
+            }
+        } else {
+            return Err(error(format!("unexpected character {:?}", c)));
+        };
+
+        tokens.push(Spanned {
+            token,
+            column: start + 1,
+            text: chars[start..index].iter().collect(),
+        });
+    }
+
+    tokens.push(Spanned {
+        token: Token::End,
+        column: chars.len() + 1,
+        text: String::new(),
+    });
+    Ok(tokens)
+}
+
+/// The unit written after a number literal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LiteralUnit {
+    Percent,
+    Watts,
+    Display(DisplayUnit),
+    /// A size per second, e.g. `MiB/s`.
+    Rate(DisplayUnit),
+}
+
+impl LiteralUnit {
+    fn parse(unit: &str) -> Option<Self> {
+        match unit {
+            "%" => Some(LiteralUnit::Percent),
+            "W" => Some(LiteralUnit::Watts),
+            _ => match unit.strip_suffix("/s") {
+                Some(size) => match DisplayUnit::parse(size)? {
+                    display @ DisplayUnit::Bytes { .. } => Some(LiteralUnit::Rate(display)),
+                    _ => None,
+                },
+                None => DisplayUnit::parse(unit).map(LiteralUnit::Display),
+            },
+        }
+    }
+
+    fn applies_to(self, unit: Unit) -> bool {
+        match self {
+            LiteralUnit::Percent => unit == Unit::Percent,
+            LiteralUnit::Watts => unit == Unit::Watts,
+            LiteralUnit::Display(display) => display.applies_to(unit),
+            LiteralUnit::Rate(_) => unit == Unit::BytesPerSecond,
+        }
+    }
+
+    /// Converts `value` to the base unit of the metrics it applies to, e.g. bytes or °C.
+    fn to_base(self, value: f64) -> f64 {
+        match self {
+            LiteralUnit::Percent | LiteralUnit::Watts => value,
+            LiteralUnit::Display(display) | LiteralUnit::Rate(display) => match display {
+                DisplayUnit::Bytes { exponent, .. } => value * 1024f64.powi(exponent),
+                DisplayUnit::Celsius => value,
+                DisplayUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+                DisplayUnit::Duration { divisor } => value * divisor as f64,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Number {
+        value: f64,
+        unit: Option<LiteralUnit>,
+    },
+    Text(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Flag(String),
+    Compare {
+        name: String,
+        operator: Operator,
+        literal: Literal,
+    },
+}
+
+impl Expr {
+    fn holds(&self, stats: &SystemStats) -> bool {
+        match self {
+            Expr::Or(left, right) => left.holds(stats) || right.holds(stats),
+            Expr::And(left, right) => left.holds(stats) && right.holds(stats),
+            Expr::Not(expr) => !expr.holds(stats),
+            Expr::Flag(name) => sample_value(stats, name) == Some(Value::Bool(true)),
+            Expr::Compare {
+                name,
+                operator,
+                literal,
+            } => match (sample_value(stats, name), literal) {
+                (
+                    Some(Value::Number(value, unit)),
+                    Literal::Number {
+                        value: operand,
+                        unit: literal_unit,
+                    },
+                ) => {
+                    value.is_finite()
+                        && literal_unit.is_none_or(|literal_unit| literal_unit.applies_to(unit))
+                        && operator.compare(value, *operand)
+                }
+                (Some(Value::Text(value)), Literal::Text(operand)) => {
+                    value.eq_ignore_ascii_case(operand) == (*operator == Operator::Equal)
+                }
+                (Some(Value::Bool(value)), Literal::Bool(operand)) => {
+                    (value == *operand) == (*operator == Operator::Equal)
+                }
+                _ => false,
+            },
+        }
+    }
+
+    /// The metrics the expression refers to, in order of appearance.
+    fn names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expr::Or(left, right) | Expr::And(left, right) => {
+                left.names(names);
+                right.names(names);
+            }
+            Expr::Not(expr) => expr.names(names),
+            Expr::Flag(name) | Expr::Compare { name, .. } => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+}
+
+/// The value of placeholder `name` for rule evaluation. Battery metrics are missing without a
+/// battery, apart from `battery.present` and `battery.count`.
+pub(crate) fn sample_value(stats: &SystemStats, name: &str) -> Option<Value> {
+    if name.starts_with("battery.")
+        && !matches!(name, "battery.present" | "battery.count")
+        && stats.batteries.is_empty()
+    {
+        return None;
+    }
+    lookup(stats, name)
+}
+
+/// Resolves a metric name to its placeholder, accepting `cpu` for `cpu.pct` and `mem.percent`
+/// for `mem.pct`.
+fn resolve_name(name: &str) -> Option<(String, Kind)> {
+    let mut candidates = vec![name.to_string(), format!("{}.pct", name)];
+    if let Some(base) = name.strip_suffix(".percent") {
+        candidates.push(format!("{}.pct", base));
+    }
+    candidates.into_iter().find_map(|candidate| {
+        let kind = placeholder_kind(&candidate)?;
+        // `*.bar` placeholders are for display only.
+        (kind != Kind::Text || !candidate.ends_with(".bar")).then_some((candidate, kind))
+    })
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Spanned {
+        &self.tokens[self.position]
+    }
+
+    fn next(&mut self) -> Spanned {
+        let token = self.tokens[self.position].clone();
+        if token.token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn unexpected(token: &Spanned, expected: &str) -> RuleError {
+        let found = match token.token {
+            Token::End => "the end of the rule".to_string(),
+            _ => format!("{:?}", token.text),
+        };
+        RuleError {
+            column: token.column,
+            message: format!("expected {}, found {}", expected, found),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, RuleError> {
+        let mut expr = self.and()?;
+        while self.peek().token == Token::Or {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, RuleError> {
+        let mut expr = self.unary()?;
+        while self.peek().token == Token::And {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, RuleError> {
+        if self.peek().token == Token::Not {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, RuleError> {
+        let token = self.next();
+        match token.token {
+            Token::Open => {
+                let expr = self.or()?;
+                let close = self.next();
+                if close.token != Token::Close {
+                    return Err(Self::unexpected(&close, "')'"));
+                }
+                Ok(expr)
+            }
+            Token::Name(ref name) => {
+                let (name, kind) = resolve_name(name).ok_or_else(|| RuleError {
+                    column: token.column,
+                    message: format!("unknown metric {:?}", name),
+                })?;
+                match self.peek().token {
+                    Token::Operator(operator) => {
+                        self.next();
+                        self.comparison(name, kind, operator)
+                    }
+                    _ if kind == Kind::Bool => Ok(Expr::Flag(name)),
+                    _ => Err(Self::unexpected(
+                        self.peek(),
+                        &format!("a comparison after {}", name),
+                    )),
+                }
+            }
+            _ => Err(Self::unexpected(&token, "a metric")),
+        }
+    }
+
+    fn comparison(
+        &mut self,
+        name: String,
+        kind: Kind,
+        operator: Operator,
+    ) -> Result<Expr, RuleError> {
+        let token = self.next();
+        let error = |message| RuleError {
+            column: token.column,
+            message,
+        };
+        let equality = matches!(operator, Operator::Equal | Operator::NotEqual);
+
+        let literal = match (kind, &token.token) {
+            (Kind::Number(_) | Kind::Reading, Token::Number { value, unit: text }) => {
+                let unit = match text.as_str() {
+                    "" => None,
+                    text => Some(
+                        LiteralUnit::parse(text)
+                            .ok_or_else(|| error(format!("unknown unit {:?}", text)))?,
+                    ),
+                };
+                if let (Kind::Number(metric_unit), Some(unit)) = (kind, unit) {
+                    if !unit.applies_to(metric_unit) {
+                        return Err(error(format!("unit {:?} does not apply to {}", text, name)));
+                    }
+                }
+                Literal::Number {
+                    value: unit.map_or(*value, |unit| unit.to_base(*value)),
+                    unit,
+                }
+            }
+            (Kind::Text, Token::Text(text) | Token::Name(text)) if equality => {
+                Literal::Text(text.clone())
+            }
+            (Kind::Bool, Token::Bool(value)) if equality => Literal::Bool(*value),
+            (Kind::Text | Kind::Bool, _) if !equality => {
+                return Err(error(format!(
+                    "{} can only be compared with == or !=",
+                    name
+                )))
+            }
+            (Kind::Number(_) | Kind::Reading, _) => {
+                return Err(Self::unexpected(
+                    &token,
+                    &format!("a number to compare {} with", name),
+                ))
+            }
+            (Kind::Text, _) => {
+                return Err(Self::unexpected(
+                    &token,
+                    &format!("text to compare {} with", name),
+                ))
+            }
+            (Kind::Bool, _) => {
+                return Err(Self::unexpected(
+                    &token,
+                    &format!("true or false to compare {} with", name),
+                ))
+            }
+        };
+        Ok(Expr::Compare {
+            name,
+            operator,
+            literal,
+        })
+    }
+
+    /// `for 2m`, `for 1h 30m` or `for 1h30m`, in seconds.
+    fn duration(&mut self) -> Result<u64, RuleError> {
+        let mut secs = 0.0;
+        let mut parts = 0;
+        while let Token::Number { value, unit } = self.peek().token.clone() {
+            let token = self.next();
+            match LiteralUnit::parse(&unit) {
+                Some(unit @ LiteralUnit::Display(DisplayUnit::Duration { .. })) => {
+                    secs += unit.to_base(value);
+                    parts += 1;
+                }
+                _ => {
+                    return Err(RuleError {
+                        column: token.column,
+                        message: format!(
+                            "expected a duration such as 90s, 2m or 1h, found {:?}",
+                            token.text
+                        ),
+                    })
+                }
+            }
+        }
+        if parts == 0 {
+            return Err(Self::unexpected(
+                self.peek(),
+                "a duration such as 90s, 2m or 1h",
+            ));
+        }
+        Ok(secs.round() as u64)
+    }
+}
+
+/// A parsed rule condition with its optional `for` window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    source: String,
+    expr: Expr,
+    for_secs: u64,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, RuleError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        let for_secs = match parser.peek().token {
+            Token::For => {
+                parser.next();
+                parser.duration()?
+            }
+            _ => 0,
+        };
+        let end = parser.next();
+        if end.token != Token::End {
+            let expected = if for_secs == 0 {
+                "'and', 'or' or 'for'"
+            } else {
+                "the end of the rule"
+            };
+            return Err(Parser::unexpected(&end, expected));
+        }
+
+        Ok(Self {
+            source: source.trim().to_string(),
+            expr,
+            for_secs,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// How long the condition has to hold before the rule fires.
+    pub fn for_secs(&self) -> u64 {
+        self.for_secs
+    }
+
+    pub fn holds(&self, stats: &SystemStats) -> bool {
+        self.expr.holds(stats)
+    }
+
+    /// The current values of the metrics in the expression, e.g.
+    /// `cpu.pct is 91.0%, battery.state is Discharging`.
+    pub fn describe(&self, stats: &SystemStats) -> String {
+        let mut names = Vec::new();
+        self.expr.names(&mut names);
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let value = match sample_value(stats, name)? {
+                    Value::Number(value, unit) => {
+                        format_reading_value(&Reading::new("", value, unit))
+                    }
+                    Value::Text(text) => text,
+                    Value::Bool(value) => value.to_string(),
+                };
+                Some(format!("{} is {}", name, value))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::BatteryStats;
+
+    fn stats() -> SystemStats {
+        SystemStats {
+            cpu_usage: 90.0,
+            memory_percent: 50.0,
+            swap_used: 5 * 1024 * 1024 * 1024,
+            battery_percent: 40.0,
+            battery_state: "Discharging".to_string(),
+            batteries: vec![BatteryStats::default()],
+            ..Default::default()
+        }
+    }
+
+    fn holds(source: &str, stats: &SystemStats) -> bool {
+        Expression::parse(source)
+            .unwrap_or_else(|e| panic!("{}: {}", source, e))
+            .holds(stats)
+    }
+
+    fn error(source: &str) -> String {
+        Expression::parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_rule_evaluation() {
+        let stats = stats();
+        for (source, expected) in [
+            ("cpu > 85", true),
+            ("cpu.pct > 85%", true),
+            ("cpu > 85 and battery.state == \"Discharging\"", true),
+            ("cpu > 85 and battery.state == charging", false),
+            ("mem.percent > 90 or swap.used > 4GiB", true),
+            ("mem.percent > 90 or swap.used > 6GiB", false),
+            ("not (mem > 40) || cpu >= 90 && battery.discharging", true),
+            ("battery.charging == false and !battery.full", true),
+            ("battery.state != \"DISCHARGING\"", false),
+        ] {
+            assert_eq!(holds(source, &stats), expected, "{}", source);
+        }
+
+        // `or` binds looser than `and`.
+        assert!(holds("cpu < 10 and mem < 10 or cpu > 50", &stats));
+        assert!(!holds("cpu < 10 and (mem < 10 or cpu > 50)", &stats));
+
+        // Missing values are false, including battery metrics without a battery.
+        let desktop = SystemStats::default();
+        assert!(!holds("battery.pct < 10", &desktop));
+        assert!(!holds("temp.current > 0C", &desktop));
+        assert!(holds("not battery.present", &desktop));
+
+        let hot = SystemStats {
+            temperatures: vec![crate::stats::TemperatureStats {
+                label: "CPU".to_string(),
+                current: 95.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(holds("temp.current > 200F", &hot));
+        assert!(!holds("temp.current > 205F", &hot));
+    }
+
+    #[test]
+    fn test_rule_for_and_describe() {
+        let rule =
+            Expression::parse("  cpu > 85 and battery.state == \"Discharging\" for 2m ").unwrap();
+        assert_eq!(rule.for_secs(), 120);
+        assert_eq!(
+            rule.source(),
+            "cpu > 85 and battery.state == \"Discharging\" for 2m"
+        );
+        assert_eq!(
+            rule.describe(&stats()),
+            "cpu.pct is 90.0%, battery.state is Discharging"
+        );
+        assert_eq!(
+            Expression::parse("cpu > 1 for 1h 30m").unwrap().for_secs(),
+            5400
+        );
+        assert_eq!(
+            Expression::parse("cpu > 1 for 1h30s").unwrap().for_secs(),
+            3630
+        );
+        assert_eq!(Expression::parse("cpu > 1").unwrap().for_secs(), 0);
+        assert_eq!(
+            Expression::parse("net.rx >= 1.5MiB/s").unwrap().expr,
+            Expr::Compare {
+                name: "net.rx".to_string(),
+                operator: Operator::GreaterOrEqual,
+                literal: Literal::Number {
+                    value: 1.5 * 1024.0 * 1024.0,
+                    unit: LiteralUnit::parse("MiB/s"),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_rule_errors() {
+        assert_eq!(error("gpu > 50"), "column 1: unknown metric \"gpu\"");
+        assert_eq!(
+            error("cpu > 85 and"),
+            "column 13: expected a metric, found the end of the rule"
+        );
+        assert_eq!(
+            error("cpu > 4GiB"),
+            "column 7: unit \"GiB\" does not apply to cpu.pct"
+        );
+        assert_eq!(error("swap.used > 4XB"), "column 13: unknown unit \"XB\"");
+        assert_eq!(
+            error("battery.state > 3"),
+            "column 17: battery.state can only be compared with == or !="
+        );
+        assert_eq!(
+            error("mem.pct == \"high\""),
+            "column 12: expected a number to compare mem.pct with, found \"\\\"high\\\"\""
+        );
+        assert_eq!(
+            error("(cpu > 50 or mem > 50"),
+            "column 22: expected ')', found the end of the rule"
+        );
+        assert_eq!(
+            error("cpu"),
+            "column 4: expected a comparison after cpu.pct, found the end of the rule"
+        );
+        assert_eq!(
+            error("cpu > 50 for 2"),
+            "column 14: expected a duration such as 90s, 2m or 1h, found \"2\""
+        );
+        assert_eq!(
+            error("cpu > 50 mem > 50"),
+            "column 10: expected 'and', 'or' or 'for', found \"mem\""
+        );
+        assert_eq!(error("cpu > 50 # x"), "column 10: unexpected character '#'");
+        assert_eq!(
+            error("battery.state == \"Disch"),
+            "column 18: unclosed string"
+        );
+    }
+}
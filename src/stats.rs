@@ -0,0 +1,285 @@
+//! Plain data types describing a single stats snapshot.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SystemStats {
+    pub cpu_usage: f32,
+    pub cpu_cores: Vec<f32>,
+    pub load_average_one: f64,
+    pub load_average_five: f64,
+    pub load_average_fifteen: f64,
+    pub uptime_secs: u64,
+    /// Seconds since the Unix epoch.
+    pub boot_time: u64,
+    pub memory_used: u64,
+    pub memory_total: u64,
+    pub memory_percent: f32,
+    pub memory_available: u64,
+    pub memory_free: u64,
+    /// Memory the OS can reclaim on demand, such as caches, approximated as available minus
+    /// free. Not the OS's own cached figure, which not every platform reports.
+    #[serde(alias = "memory_cached")]
+    pub memory_reclaimable: u64,
+    pub memory_pressure: MemoryPressure,
+    pub swap_used: u64,
+    pub swap_total: u64,
+    pub swap_percent: f32,
+    pub battery_percent: f32,
+    pub battery_state: String,
+    pub batteries: Vec<BatteryStats>,
+    pub disk: DiskStats,
+    pub network: NetworkStats,
+    pub top_cpu_processes: Vec<ProcessStats>,
+    pub top_memory_processes: Vec<ProcessStats>,
+    pub temperatures: Vec<TemperatureStats>,
+    /// Readings from collectors that have no dedicated field above.
+    #[serde(default)]
+    pub extra: Vec<Reading>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    Percent,
+    Bytes,
+    BytesPerSecond,
+    Celsius,
+    Seconds,
+    Watts,
+    Count,
+}
+
+impl Unit {
+    /// The unit's serialized name, e.g. `bytes_per_second`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Unit::Percent => "percent",
+            Unit::Bytes => "bytes",
+            Unit::BytesPerSecond => "bytes_per_second",
+            Unit::Celsius => "celsius",
+            Unit::Seconds => "seconds",
+            Unit::Watts => "watts",
+            Unit::Count => "count",
+        }
+    }
+}
+
+/// A single named value produced by a collector.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Reading {
+    /// Dotted key starting with the collector name, e.g. `cpu.usage`.
+    pub key: String,
+    /// The device the reading belongs to, e.g. a core, mount point or interface. Readings
+    /// without an instance are the collector-wide aggregate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Reading {
+    pub fn new(key: impl Into<String>, value: f64, unit: Unit) -> Self {
+        Self {
+            key: key.into(),
+            instance: None,
+            value,
+            unit,
+        }
+    }
+
+    pub fn with_instance(mut self, instance: impl Into<String>) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// The key plus the instance in brackets, e.g. `disk.percent[/]`.
+    pub fn id(&self) -> String {
+        match &self.instance {
+            Some(instance) => format!("{}[{}]", self.key, instance),
+            None => self.key.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BatteryStats {
+    pub name: String,
+    pub percent: f32,
+    pub state: String,
+    pub details: BatteryDetails,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BatteryDetails {
+    pub energy_wh: f32,
+    pub energy_full_wh: f32,
+    pub energy_full_design_wh: f32,
+    pub health_percent: f32,
+    /// Absolute charge or discharge power.
+    pub power_watts: f32,
+    pub voltage: f32,
+    pub temperature_celsius: Option<f32>,
+    pub cycle_count: Option<u32>,
+    pub time_to_full_secs: Option<u64>,
+    pub time_to_empty_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TemperatureStats {
+    pub label: String,
+    pub current: f32,
+    pub max: f32,
+    pub critical: Option<f32>,
+}
+
+impl SystemStats {
+    pub fn hottest_temperature(&self) -> Option<&TemperatureStats> {
+        self.temperatures
+            .iter()
+            .max_by(|a, b| a.current.total_cmp(&b.current))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ProcessStats {
+    pub pid: u32,
+    pub name: String,
+    pub cpu_usage: f32,
+    pub memory: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemoryPressure {
+    #[default]
+    Normal,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DiskStats {
+    pub read_per_sec: u64,
+    pub written_per_sec: u64,
+    pub volumes: Vec<DiskVolumeStats>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DiskVolumeStats {
+    pub mount_point: String,
+    pub file_system: String,
+    pub used: u64,
+    pub total: u64,
+    pub percent: f32,
+}
+
+impl DiskStats {
+    pub fn fullest_volume(&self) -> Option<&DiskVolumeStats> {
+        self.volumes
+            .iter()
+            .max_by(|a, b| a.percent.total_cmp(&b.percent))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NetworkStats {
+    pub received_per_sec: u64,
+    pub transmitted_per_sec: u64,
+    pub interfaces: Vec<NetworkInterfaceStats>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NetworkInterfaceStats {
+    pub name: String,
+    pub received_per_sec: u64,
+    pub transmitted_per_sec: u64,
+    pub total_received: u64,
+    pub total_transmitted: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_stats_clone() {
+        let stats = SystemStats {
+            cpu_usage: 50.0,
+            memory_used: 1073741824,
+            memory_total: 2147483648,
+            memory_percent: 50.0,
+            battery_percent: 80.0,
+            battery_state: "Charging".to_string(),
+            ..Default::default()
+        };
+
+        let cloned = stats.clone();
+        assert_eq!(stats.cpu_usage, cloned.cpu_usage);
+        assert_eq!(stats.memory_used, cloned.memory_used);
+        assert_eq!(stats.memory_total, cloned.memory_total);
+        assert_eq!(stats.memory_percent, cloned.memory_percent);
+        assert_eq!(stats.battery_percent, cloned.battery_percent);
+        assert_eq!(stats.battery_state, cloned.battery_state);
+    }
+
+    #[test]
+    fn test_system_stats_serialization() {
+        let stats = SystemStats {
+            cpu_usage: 50.0,
+            memory_used: 1073741824,
+            memory_total: 2147483648,
+            memory_percent: 50.0,
+            battery_percent: 80.0,
+            battery_state: "Charging".to_string(),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&stats);
+        assert!(json.is_ok());
+
+        let json_str = json.unwrap();
+        let deserialized: Result<SystemStats, _> = serde_json::from_str(&json_str);
+        assert!(deserialized.is_ok());
+
+        let deserialized_stats = deserialized.unwrap();
+        assert_eq!(stats.cpu_usage, deserialized_stats.cpu_usage);
+        assert_eq!(stats.memory_used, deserialized_stats.memory_used);
+    }
+
+    #[test]
+    fn test_reading_id_and_serialization() {
+        let aggregate = Reading::new("cpu.usage", 42.5, Unit::Percent);
+        assert_eq!(aggregate.id(), "cpu.usage");
+
+        let volume = Reading::new("disk.percent", 71.0, Unit::Percent).with_instance("/");
+        assert_eq!(volume.id(), "disk.percent[/]");
+
+        let json = serde_json::to_string(&aggregate).unwrap();
+        assert_eq!(json, r#"{"key":"cpu.usage","value":42.5,"unit":"percent"}"#);
+        let parsed: Reading = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, aggregate);
+
+        for unit in [
+            Unit::Percent,
+            Unit::Bytes,
+            Unit::BytesPerSecond,
+            Unit::Celsius,
+            Unit::Seconds,
+            Unit::Watts,
+            Unit::Count,
+        ] {
+            assert_eq!(
+                serde_json::to_string(&unit).unwrap(),
+                format!("\"{}\"", unit.name())
+            );
+        }
+
+        let legacy: SystemStats = serde_json::from_str(
+            &serde_json::to_string(&SystemStats::default())
+                .unwrap()
+                .replace(r#","extra":[]"#, ""),
+        )
+        .unwrap();
+        assert!(legacy.extra.is_empty());
+    }
+}
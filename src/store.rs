@@ -0,0 +1,762 @@
+//! Append-only on-disk time series of [`Reading`]s.
+//!
+//! Samples are kept in JSON Lines files at three resolutions: raw samples for the last hour,
+//! per-minute min/avg/max for the last day and per-hour min/avg/max for the last month. Each
+//! resolution is split into segment files covering a fixed period, such as `raw-1760693400.jsonl`
+//! for five minutes of raw samples, so old data is dropped by deleting whole files.
+//! [`TimeSeriesStore::compact`] moves segments that aged out of a tier into the next coarser one,
+//! and once the store outgrows its size cap the oldest data is dropped until it is back at 90% of
+//! the cap.
+//!
+//! Every sample is appended as a single line and synced before `append` returns; a line cut
+//! short by a crash is truncated when the store is opened again. Rewrites go through a
+//! temporary file that is renamed over the original, so a segment is never half written.
+
+use crate::stats::Reading;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory of the store inside the app's data directory.
+pub const STORE_DIR_NAME: &str = "history";
+pub const DEFAULT_STORE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+pub const RAW_RETENTION_SECS: u64 = 60 * 60;
+pub const MINUTE_RETENTION_SECS: u64 = 24 * 60 * 60;
+pub const HOUR_RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
+/// Segment file name prefix, resolution, retention and segment length of each tier, finest
+/// first. Raw samples have no resolution of their own. Segments are aligned to their length,
+/// which is a multiple of the next tier's resolution, so a period is never split across two.
+const TIERS: [(&str, u64, u64, u64); 3] = [
+    ("raw", 0, RAW_RETENTION_SECS, 5 * 60),
+    ("minute", 60, MINUTE_RETENTION_SECS, 60 * 60),
+    ("hour", 60 * 60, HOUR_RETENTION_SECS, 24 * 60 * 60),
+];
+/// How often `append` compacts the store while it is under its size cap.
+pub const COMPACT_INTERVAL_SECS: u64 = 5 * 60;
+
+/// One value of a metric. Downsampled points cover a whole minute or hour and carry the
+/// average as `value` plus the extremes seen in that period; raw points have all three equal.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Point {
+    /// Seconds since the Unix epoch; the start of the period for downsampled points.
+    pub timestamp: u64,
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// All metrics stored for one point in time, keyed by [`Reading::id`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub timestamp: u64,
+    /// Length of the period the values average over, or 0 for a raw sample.
+    pub resolution_secs: u64,
+    pub values: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+enum StoredValue {
+    Exact(f64),
+    /// Min, average and max over a downsampled period, and the number of raw samples averaged.
+    Range(f64, f64, f64, u64),
+}
+
+impl StoredValue {
+    fn bounds(self) -> (f64, f64, f64) {
+        match self {
+            StoredValue::Exact(value) => (value, value, value),
+            StoredValue::Range(min, avg, max, _) => (min, avg, max),
+        }
+    }
+
+    /// How many raw samples the value stands for, so coarser periods weight it accordingly.
+    fn count(self) -> u64 {
+        match self {
+            StoredValue::Exact(_) => 1,
+            StoredValue::Range(_, _, _, count) => count,
+        }
+    }
+}
+
+/// All metrics of one sample, keyed by [`Reading::id`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Record {
+    t: u64,
+    v: BTreeMap<String, StoredValue>,
+}
+
+#[derive(Debug)]
+struct Tier {
+    dir: PathBuf,
+    name: &'static str,
+    resolution_secs: u64,
+    retention_secs: u64,
+    segment_secs: u64,
+    /// Size of each segment file, keyed by the start of the period it covers.
+    segments: BTreeMap<u64, u64>,
+    last_timestamp: Option<u64>,
+}
+
+impl Tier {
+    fn open(
+        dir: &Path,
+        (name, resolution_secs, retention_secs, segment_secs): (&'static str, u64, u64, u64),
+    ) -> io::Result<Self> {
+        let mut segments = BTreeMap::new();
+        for (start, path) in segment_paths(dir, name)? {
+            segments.insert(start, recover(&path)?);
+        }
+        let mut tier = Self {
+            dir: dir.to_path_buf(),
+            name,
+            resolution_secs,
+            retention_secs,
+            segment_secs,
+            segments,
+            last_timestamp: None,
+        };
+        // Segments are ordered by time, so the newest record is in the newest non-empty one.
+        for &start in tier.segments.keys().rev() {
+            tier.last_timestamp = read_records(&tier.segment_path(start))?
+                .iter()
+                .map(|record| record.t)
+                .max();
+            if tier.last_timestamp.is_some() {
+                break;
+            }
+        }
+        Ok(tier)
+    }
+
+    fn segment_path(&self, start: u64) -> PathBuf {
+        self.dir.join(format!("{}-{}.jsonl", self.name, start))
+    }
+
+    fn segment_start(&self, timestamp: u64) -> u64 {
+        timestamp - timestamp % self.segment_secs
+    }
+
+    /// Paths of every segment, oldest first.
+    fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.segments.keys().map(|&start| self.segment_path(start))
+    }
+
+    fn bytes(&self) -> u64 {
+        self.segments.values().sum()
+    }
+
+    fn append(&mut self, records: &[Record]) -> io::Result<()> {
+        let mut buffers: BTreeMap<u64, Vec<u8>> = BTreeMap::new();
+        for record in records {
+            let buffer = buffers.entry(self.segment_start(record.t)).or_default();
+            serde_json::to_writer(&mut *buffer, record)?;
+            buffer.push(b'\n');
+        }
+
+        for (start, buffer) in buffers {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.segment_path(start))?;
+            file.write_all(&buffer)?;
+            file.sync_data()?;
+            *self.segments.entry(start).or_default() += buffer.len() as u64;
+        }
+        for record in records {
+            self.last_timestamp = Some(self.last_timestamp.map_or(record.t, |t| t.max(record.t)));
+        }
+        Ok(())
+    }
+
+    /// Starts of the segments that end at or before `cutoff`.
+    fn segments_before(&self, cutoff: u64) -> Vec<u64> {
+        self.segments
+            .keys()
+            .copied()
+            .take_while(|start| start + self.segment_secs <= cutoff)
+            .collect()
+    }
+
+    fn remove_segment(&mut self, start: u64) -> io::Result<()> {
+        match fs::remove_file(self.segment_path(start)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        self.segments.remove(&start);
+        Ok(())
+    }
+
+    /// Replaces a segment with `records` through a temporary file.
+    fn rewrite_segment(&mut self, start: u64, records: &[Record]) -> io::Result<()> {
+        if records.is_empty() {
+            return self.remove_segment(start);
+        }
+        let mut buffer = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut buffer, record)?;
+            buffer.push(b'\n');
+        }
+
+        let path = self.segment_path(start);
+        let temp_path = path.with_extension("tmp");
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&buffer)?;
+        file.sync_data()?;
+        fs::rename(&temp_path, &path)?;
+
+        self.segments.insert(start, buffer.len() as u64);
+        Ok(())
+    }
+
+    /// Frees at least `excess` bytes, or everything, by deleting the oldest segments. Only a
+    /// segment that is larger than what is left to free is read and rewritten without its
+    /// oldest lines. Returns the number of bytes freed.
+    fn drop_oldest(&mut self, excess: u64) -> io::Result<u64> {
+        let mut freed = 0;
+        while let Some((&start, &bytes)) = self.segments.first_key_value() {
+            if freed >= excess {
+                break;
+            }
+            if bytes <= excess - freed {
+                self.remove_segment(start)?;
+                freed += bytes;
+                continue;
+            }
+
+            let records = read_records(&self.segment_path(start))?;
+            let mut dropped = 0;
+            let mut line_bytes = 0;
+            for record in &records {
+                if freed + line_bytes >= excess {
+                    break;
+                }
+                line_bytes += serde_json::to_vec(record)?.len() as u64 + 1;
+                dropped += 1;
+            }
+            self.rewrite_segment(start, &records[dropped..])?;
+            freed += bytes.saturating_sub(self.segments.get(&start).copied().unwrap_or(0));
+            break;
+        }
+        Ok(freed)
+    }
+}
+
+/// The segment files of tier `name` in `dir`, oldest first.
+fn segment_paths(dir: &Path, name: &str) -> io::Result<Vec<(u64, PathBuf)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let start = path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(|file_name| file_name.strip_prefix(name)?.strip_prefix('-'))
+            .and_then(|rest| rest.strip_suffix(".jsonl")?.parse().ok());
+        if let Some(start) = start {
+            paths.push((start, path));
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Truncates a line left unfinished by a crash and returns the resulting file size.
+fn recover(path: &Path) -> io::Result<u64> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let complete = contents
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |index| index + 1);
+    if complete < contents.len() {
+        OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(complete as u64)?;
+    }
+    Ok(complete as u64)
+}
+
+/// Records in file order. Lines that do not parse are skipped.
+fn read_records(path: &Path) -> io::Result<Vec<Record>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+struct Accumulator {
+    min: f64,
+    sum: f64,
+    max: f64,
+    count: u64,
+}
+
+/// Merges records into one min/avg/max record per `resolution_secs` period. Averages are
+/// weighted by the number of raw samples behind each value.
+fn downsample(records: &[Record], resolution_secs: u64) -> Vec<Record> {
+    let mut buckets: BTreeMap<u64, BTreeMap<&str, Accumulator>> = BTreeMap::new();
+    for record in records {
+        let bucket = buckets
+            .entry(record.t - record.t % resolution_secs)
+            .or_default();
+        for (metric, value) in &record.v {
+            let (min, avg, max) = value.bounds();
+            let entry = bucket.entry(metric).or_insert(Accumulator {
+                min: f64::INFINITY,
+                sum: 0.0,
+                max: f64::NEG_INFINITY,
+                count: 0,
+            });
+            entry.min = entry.min.min(min);
+            entry.sum += avg * value.count() as f64;
+            entry.max = entry.max.max(max);
+            entry.count += value.count();
+        }
+    }
+
+    buckets
+        .into_iter()
+        .map(|(t, metrics)| Record {
+            t,
+            v: metrics
+                .into_iter()
+                .map(|(metric, total)| {
+                    let avg = total.sum / total.count as f64;
+                    (
+                        metric.to_string(),
+                        StoredValue::Range(total.min, avg, total.max, total.count),
+                    )
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+pub fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub struct TimeSeriesStore {
+    /// Finest resolution first: raw, per-minute, per-hour.
+    tiers: [Tier; 3],
+    max_bytes: u64,
+    last_compaction: Option<u64>,
+}
+
+impl TimeSeriesStore {
+    /// Opens the store in `dir`, creating it if needed and repairing lines cut short by a crash.
+    pub fn open(dir: &Path, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            tiers: [
+                Tier::open(dir, TIERS[0])?,
+                Tier::open(dir, TIERS[1])?,
+                Tier::open(dir, TIERS[2])?,
+            ],
+            max_bytes,
+            last_compaction: None,
+        })
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    pub fn set_max_bytes(&mut self, max_bytes: u64) {
+        self.max_bytes = max_bytes;
+    }
+
+    /// Total size of all tiers on disk.
+    pub fn size_bytes(&self) -> u64 {
+        self.tiers.iter().map(Tier::bytes).sum()
+    }
+
+    /// Appends the readings taken at `timestamp` and compacts the store when it is due or over
+    /// its size cap. Non-finite values are skipped.
+    pub fn append(&mut self, timestamp: u64, readings: &[Reading]) -> io::Result<()> {
+        let values: BTreeMap<String, StoredValue> = readings
+            .iter()
+            .filter(|reading| reading.value.is_finite())
+            .map(|reading| (reading.id(), StoredValue::Exact(reading.value)))
+            .collect();
+        if values.is_empty() {
+            return Ok(());
+        }
+        self.tiers[0].append(&[Record {
+            t: timestamp,
+            v: values,
+        }])?;
+
+        let compaction_due = self
+            .last_compaction
+            .is_none_or(|last| timestamp >= last + COMPACT_INTERVAL_SECS);
+        if compaction_due || self.size_bytes() > self.max_bytes {
+            self.compact(timestamp)?;
+        }
+        Ok(())
+    }
+
+    /// Downsamples every segment that aged out of its tier, drops data older than a month and,
+    /// if the store outgrew `max_bytes`, the oldest data until it is back at 90% of the cap.
+    pub fn compact(&mut self, now: u64) -> io::Result<()> {
+        self.last_compaction = Some(now);
+
+        for index in 0..self.tiers.len() {
+            let (current, coarser) = self.tiers[index..].split_at_mut(1);
+            let tier = &mut current[0];
+            for start in tier.segments_before(now.saturating_sub(tier.retention_secs)) {
+                if let Some(next) = coarser.first_mut() {
+                    // Periods already written before a crash interrupted the last compaction
+                    // are skipped, so they are not counted twice.
+                    let expired = read_records(&tier.segment_path(start))?;
+                    let downsampled: Vec<Record> = downsample(&expired, next.resolution_secs)
+                        .into_iter()
+                        .filter(|record| next.last_timestamp.is_none_or(|last| record.t > last))
+                        .collect();
+                    next.append(&downsampled)?;
+                }
+                tier.remove_segment(start)?;
+            }
+        }
+
+        self.enforce_size_cap()
+    }
+
+    /// Once the store is over `max_bytes`, drops the oldest data, coarsest tier first, down to
+    /// 90% of the cap, so the cap is not enforced again on every append.
+    fn enforce_size_cap(&mut self) -> io::Result<()> {
+        let size = self.size_bytes();
+        if size <= self.max_bytes {
+            return Ok(());
+        }
+        let mut excess = size - (self.max_bytes - self.max_bytes / 10);
+        for tier in self.tiers.iter_mut().rev() {
+            if excess == 0 {
+                break;
+            }
+            excess = excess.saturating_sub(tier.drop_oldest(excess)?);
+        }
+        Ok(())
+    }
+
+    /// Points of `metric` (a [`Reading::id`] such as `cpu.usage` or `disk.percent[/]`) with a
+    /// timestamp in `range`, oldest first, at the finest resolution still stored for each period.
+    pub fn query(&self, metric: &str, range: Range<u64>) -> io::Result<Vec<Point>> {
+        let mut points = Vec::new();
+        for path in self.tiers.iter().flat_map(Tier::paths) {
+            for record in read_records(&path)? {
+                if !range.contains(&record.t) {
+                    continue;
+                }
+                if let Some(value) = record.v.get(metric) {
+                    let (min, value, max) = value.bounds();
+                    points.push(Point {
+                        timestamp: record.t,
+                        value,
+                        min,
+                        max,
+                    });
+                }
+            }
+        }
+        points.sort_by_key(|point| point.timestamp);
+        Ok(points)
+    }
+
+    /// Every stored sample with a timestamp in `range`, oldest first, with downsampled periods
+    /// reduced to their averages.
+    pub fn samples(&self, range: Range<u64>) -> io::Result<Vec<Sample>> {
+        collect_samples(
+            self.tiers
+                .iter()
+                .flat_map(|tier| tier.paths().map(move |path| (path, tier.resolution_secs))),
+            range,
+        )
+    }
+}
+
+/// [`TimeSeriesStore::samples`] for a store another process may be writing to, such as the
+/// running app. Nothing is created or repaired.
+pub fn read_samples(dir: &Path, range: Range<u64>) -> io::Result<Vec<Sample>> {
+    if !dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no stored history at {}", dir.display()),
+        ));
+    }
+    let mut paths = Vec::new();
+    for (name, resolution_secs, _, _) in TIERS {
+        for (_, path) in segment_paths(dir, name)? {
+            paths.push((path, resolution_secs));
+        }
+    }
+    collect_samples(paths.into_iter(), range)
+}
+
+fn collect_samples(
+    segments: impl Iterator<Item = (PathBuf, u64)>,
+    range: Range<u64>,
+) -> io::Result<Vec<Sample>> {
+    let mut samples = Vec::new();
+    for (path, resolution_secs) in segments {
+        for record in read_records(&path)? {
+            if !range.contains(&record.t) {
+                continue;
+            }
+            samples.push(Sample {
+                timestamp: record.t,
+                resolution_secs,
+                values: record
+                    .v
+                    .into_iter()
+                    .map(|(metric, value)| (metric, value.bounds().1))
+                    .collect(),
+            });
+        }
+    }
+    samples.sort_by_key(|sample| sample.timestamp);
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Unit;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "system-stats-store-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn cpu(value: f64) -> Vec<Reading> {
+        vec![
+            Reading::new("cpu.usage", value, Unit::Percent),
+            Reading::new("cpu.temperature", f64::NAN, Unit::Celsius),
+        ]
+    }
+
+    #[test]
+    fn test_store_append_query_and_recover() {
+        let dir = temp_dir("recover");
+        let mut store = TimeSeriesStore::open(&dir, DEFAULT_STORE_MAX_BYTES).unwrap();
+        for (timestamp, value) in [(1_000, 10.0), (1_003, 20.0), (1_006, 30.0)] {
+            store.append(timestamp, &cpu(value)).unwrap();
+        }
+        store.append(1_009, &[]).unwrap();
+
+        let points = store.query("cpu.usage", 1_003..1_010).unwrap();
+        assert_eq!(
+            points,
+            [
+                Point {
+                    timestamp: 1_003,
+                    value: 20.0,
+                    min: 20.0,
+                    max: 20.0
+                },
+                Point {
+                    timestamp: 1_006,
+                    value: 30.0,
+                    min: 30.0,
+                    max: 30.0
+                },
+            ]
+        );
+        assert!(store
+            .query("cpu.temperature", 0..u64::MAX)
+            .unwrap()
+            .is_empty());
+
+        // A crash in the middle of an append leaves half a line behind.
+        let raw = dir.join("raw-900.jsonl");
+        let mut file = OpenOptions::new().append(true).open(&raw).unwrap();
+        file.write_all(b"{\"t\":1009,\"v\":{\"cpu.us").unwrap();
+        drop(file);
+
+        let mut store = TimeSeriesStore::open(&dir, DEFAULT_STORE_MAX_BYTES).unwrap();
+        assert_eq!(store.size_bytes(), fs::metadata(&raw).unwrap().len());
+        store.append(1_012, &cpu(40.0)).unwrap();
+        let values: Vec<f64> = store
+            .query("cpu.usage", 0..u64::MAX)
+            .unwrap()
+            .iter()
+            .map(|point| point.value)
+            .collect();
+        assert_eq!(values, [10.0, 20.0, 30.0, 40.0]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_downsampling_and_retention() {
+        let dir = temp_dir("downsample");
+        let mut store = TimeSeriesStore::open(&dir, DEFAULT_STORE_MAX_BYTES).unwrap();
+        let start = 3_600 * 1_000;
+        for (offset, value) in [(0, 10.0), (30, 30.0), (60, 50.0), (3_600, 70.0)] {
+            store.append(start + offset, &cpu(value)).unwrap();
+        }
+
+        // Two hours later the first minute is downsampled, the last sample is still raw.
+        let now = start + 2 * 3_600 + 30;
+        store.compact(now).unwrap();
+        let points = store.query("cpu.usage", 0..u64::MAX).unwrap();
+        assert_eq!(
+            points,
+            [
+                Point {
+                    timestamp: start,
+                    value: 20.0,
+                    min: 10.0,
+                    max: 30.0
+                },
+                Point {
+                    timestamp: start + 60,
+                    value: 50.0,
+                    min: 50.0,
+                    max: 50.0
+                },
+                Point {
+                    timestamp: start + 3_600,
+                    value: 70.0,
+                    min: 70.0,
+                    max: 70.0
+                },
+            ]
+        );
+
+        // Compacting again does not count the same minute twice, even if the raw tier still
+        // held it because a crash interrupted the previous compaction.
+        store.tiers[0]
+            .append(&[Record {
+                t: start,
+                v: BTreeMap::from([("cpu.usage".to_string(), StoredValue::Exact(90.0))]),
+            }])
+            .unwrap();
+        store.compact(now).unwrap();
+        assert_eq!(store.query("cpu.usage", 0..u64::MAX).unwrap(), points);
+
+        let samples = store.samples(start..start + 3_600).unwrap();
+        assert_eq!(
+            samples
+                .iter()
+                .map(|sample| (sample.timestamp, sample.resolution_secs))
+                .collect::<Vec<_>>(),
+            [(start, 60), (start + 60, 60)]
+        );
+        assert_eq!(samples[0].values["cpu.usage"], 20.0);
+        assert_eq!(read_samples(&dir, start..start + 3_600).unwrap(), samples);
+        assert!(read_samples(&dir.join("missing"), 0..u64::MAX).is_err());
+
+        // A day later everything is hourly, averaged over the raw samples rather than the
+        // per-minute averages.
+        store.compact(start + 2 * 86_400).unwrap();
+        let points = store.query("cpu.usage", 0..u64::MAX).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(
+            points[0],
+            Point {
+                timestamp: start,
+                value: 30.0,
+                min: 10.0,
+                max: 50.0
+            }
+        );
+
+        // After a month it is gone.
+        store.compact(start + 40 * 86_400).unwrap();
+        assert!(store.query("cpu.usage", 0..u64::MAX).unwrap().is_empty());
+        assert_eq!(store.size_bytes(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_size_cap() {
+        let dir = temp_dir("cap");
+        let mut store = TimeSeriesStore::open(&dir, DEFAULT_STORE_MAX_BYTES).unwrap();
+        for offset in 0..10 {
+            store.append(1_000 + offset, &cpu(offset as f64)).unwrap();
+        }
+        let line_bytes = store.size_bytes() / 10;
+        let values = |store: &TimeSeriesStore| -> Vec<f64> {
+            store
+                .query("cpu.usage", 0..u64::MAX)
+                .unwrap()
+                .iter()
+                .map(|point| point.value)
+                .collect()
+        };
+
+        // Going over the cap trims the store to 90% of it, which leaves room to grow.
+        store.set_max_bytes(line_bytes * 5 + 2);
+        store.append(1_010, &cpu(10.0)).unwrap();
+        assert!(store.size_bytes() <= store.max_bytes() - store.max_bytes() / 10);
+        assert_eq!(values(&store), [7.0, 8.0, 9.0, 10.0]);
+
+        store.append(1_011, &cpu(11.0)).unwrap();
+        assert_eq!(values(&store), [7.0, 8.0, 9.0, 10.0, 11.0]);
+        store.append(1_012, &cpu(12.0)).unwrap();
+        assert_eq!(values(&store), [9.0, 10.0, 11.0, 12.0]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_size_cap_drops_segments() {
+        let dir = temp_dir("segments");
+        let mut store = TimeSeriesStore::open(&dir, DEFAULT_STORE_MAX_BYTES).unwrap();
+        for (timestamp, value) in [(1_200, 1.0), (1_500, 2.0), (1_800, 3.0)] {
+            store.append(timestamp, &cpu(value)).unwrap();
+        }
+        let segment_bytes = store.size_bytes() / 3;
+        assert!(dir.join("raw-1500.jsonl").exists());
+
+        store.set_max_bytes(segment_bytes * 2);
+        store.compact(1_800).unwrap();
+        assert!(!dir.join("raw-1200.jsonl").exists());
+        assert!(!dir.join("raw-1500.jsonl").exists());
+        assert_eq!(store.size_bytes(), segment_bytes);
+
+        let store = TimeSeriesStore::open(&dir, segment_bytes * 2).unwrap();
+        assert_eq!(store.size_bytes(), segment_bytes);
+        assert_eq!(store.tiers[0].last_timestamp, Some(1_800));
+        assert_eq!(
+            store
+                .query("cpu.usage", 0..u64::MAX)
+                .unwrap()
+                .iter()
+                .map(|point| point.value)
+                .collect::<Vec<_>>(),
+            [3.0]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1082 @@
+//! User-defined tray title templates.
+//!
+//! A template is literal text with placeholders in braces, e.g.
+//! `{battery.bar}{battery.pct}% {cpu.pct:.1}% {mem.used:GiB}`:
+//!
+//! - `{name}` or `{name:spec}` inserts a value. The spec is `[align width][precision][unit]`,
+//!   where align is `<`, `>` or `^`, precision is `N` or `.N` and unit is one of `B`, `KB`,
+//!   `KiB`, `MB`, `MiB`, `GB`, `GiB`, `TB`, `TiB` for sizes and rates, `C` or `F` for
+//!   temperatures and `s`, `m`, `h` or `d` for durations. `{mem.pct:>5.1}` right-aligns the
+//!   memory percentage in five columns with one decimal place.
+//! - `{name.bar}` draws a one-character bar for any percentage, e.g. `{mem.pct.bar}`. For the
+//!   `*.pct` placeholders `{mem.bar}` is short for `{mem.pct.bar}`.
+//! - `{?condition}...{/}` only renders its body when the condition holds. A condition is a
+//!   placeholder name, optionally negated with `!`, or a comparison such as `cpu.pct > 80` or
+//!   `mem.pressure != normal`. Missing values are false.
+//! - `{{` and `}}` insert literal braces.
+//!
+//! Values that are not available, like `temp.current` without sensors, render as nothing.
+//! [`TrayTemplate::compile`] rejects unknown placeholders and invalid specs up front.
+//! `reading.<key>` refers to a collector [`Reading`](crate::stats::Reading) in
+//! [`SystemStats::extra`] by id.
+
+use crate::collect::aggregate_time_to_empty;
+use crate::format::{
+    create_bar, format_bytes, format_duration_short, format_local_timestamp, format_rate,
+    format_tray_number, load_percent, scale_bytes, TrayOptions, SIZE_UNITS,
+};
+use crate::stats::{MemoryPressure, SystemStats, Unit};
+use std::fmt;
+
+const BAR_SUFFIX: &str = ".bar";
+const READING_PREFIX: &str = "reading.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Number(Unit),
+    Text,
+    Bool,
+    /// A collector reading, whose unit is only known once it has been sampled.
+    Reading,
+}
+
+/// Every placeholder apart from `*.bar` and `reading.*`, with the kind of value it produces.
+const PLACEHOLDERS: &[(&str, Kind)] = &[
+    ("battery.pct", Kind::Number(Unit::Percent)),
+    ("battery.state", Kind::Text),
+    ("battery.charging", Kind::Bool),
+    ("battery.discharging", Kind::Bool),
+    ("battery.full", Kind::Bool),
+    ("battery.present", Kind::Bool),
+    ("battery.count", Kind::Number(Unit::Count)),
+    ("battery.power", Kind::Number(Unit::Watts)),
+    ("battery.health", Kind::Number(Unit::Percent)),
+    ("battery.cycles", Kind::Number(Unit::Count)),
+    ("battery.time_to_empty", Kind::Number(Unit::Seconds)),
+    ("battery.time_to_full", Kind::Number(Unit::Seconds)),
+    ("cpu.pct", Kind::Number(Unit::Percent)),
+    ("cpu.cores", Kind::Number(Unit::Count)),
+    ("cpu.max_core", Kind::Number(Unit::Percent)),
+    ("load.one", Kind::Number(Unit::Count)),
+    ("load.five", Kind::Number(Unit::Count)),
+    ("load.fifteen", Kind::Number(Unit::Count)),
+    ("load.pct", Kind::Number(Unit::Percent)),
+    ("uptime", Kind::Number(Unit::Seconds)),
+    ("boot_time", Kind::Text),
+    ("mem.used", Kind::Number(Unit::Bytes)),
+    ("mem.total", Kind::Number(Unit::Bytes)),
+    ("mem.available", Kind::Number(Unit::Bytes)),
+    ("mem.free", Kind::Number(Unit::Bytes)),
+    ("mem.reclaimable", Kind::Number(Unit::Bytes)),
+    ("mem.pct", Kind::Number(Unit::Percent)),
+    ("mem.pressure", Kind::Text),
+    ("swap.used", Kind::Number(Unit::Bytes)),
+    ("swap.total", Kind::Number(Unit::Bytes)),
+    ("swap.pct", Kind::Number(Unit::Percent)),
+    ("disk.read", Kind::Number(Unit::BytesPerSecond)),
+    ("disk.written", Kind::Number(Unit::BytesPerSecond)),
+    ("disk.pct", Kind::Number(Unit::Percent)),
+    ("disk.used", Kind::Number(Unit::Bytes)),
+    ("disk.total", Kind::Number(Unit::Bytes)),
+    ("disk.mount", Kind::Text),
+    ("disk.volumes", Kind::Number(Unit::Count)),
+    ("net.rx", Kind::Number(Unit::BytesPerSecond)),
+    ("net.tx", Kind::Number(Unit::BytesPerSecond)),
+    ("net.total_rx", Kind::Number(Unit::Bytes)),
+    ("net.total_tx", Kind::Number(Unit::Bytes)),
+    ("net.interfaces", Kind::Number(Unit::Count)),
+    ("top_cpu.name", Kind::Text),
+    ("top_cpu.pid", Kind::Number(Unit::Count)),
+    ("top_cpu.pct", Kind::Number(Unit::Percent)),
+    ("top_mem.name", Kind::Text),
+    ("top_mem.pid", Kind::Number(Unit::Count)),
+    ("top_mem.used", Kind::Number(Unit::Bytes)),
+    ("temp.current", Kind::Number(Unit::Celsius)),
+    ("temp.max", Kind::Number(Unit::Celsius)),
+    ("temp.critical", Kind::Number(Unit::Celsius)),
+    ("temp.label", Kind::Text),
+];
+
+/// The names of all fixed placeholders, for documentation and error messages.
+pub fn placeholder_names() -> impl Iterator<Item = &'static str> {
+    PLACEHOLDERS.iter().map(|(name, _)| *name)
+}
+
+pub(crate) fn placeholder_kind(name: &str) -> Option<Kind> {
+    if name.starts_with(READING_PREFIX) && name.len() > READING_PREFIX.len() {
+        return Some(Kind::Reading);
+    }
+    if name.ends_with(BAR_SUFFIX) {
+        return bar_base(name).map(|_| Kind::Text);
+    }
+    PLACEHOLDERS
+        .iter()
+        .find(|(placeholder, _)| *placeholder == name)
+        .map(|(_, kind)| *kind)
+}
+
+/// The percentage a `*.bar` placeholder draws: `cpu.max_core.bar` uses `cpu.max_core` and
+/// `battery.bar` is short for `battery.pct.bar`.
+fn bar_base(name: &str) -> Option<String> {
+    let base = name.strip_suffix(BAR_SUFFIX)?;
+    [base.to_string(), format!("{}.pct", base)]
+        .into_iter()
+        .find(|base| placeholder_kind(base) == Some(Kind::Number(Unit::Percent)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Number(f64, Unit),
+    Text(String),
+    Bool(bool),
+}
+
+fn number(value: impl Into<f64>, unit: Unit) -> Option<Value> {
+    Some(Value::Number(value.into(), unit))
+}
+
+fn text(value: impl Into<String>) -> Option<Value> {
+    Some(Value::Text(value.into()))
+}
+
+pub(crate) fn lookup(stats: &SystemStats, name: &str) -> Option<Value> {
+    if let Some(id) = name.strip_prefix(READING_PREFIX) {
+        return stats
+            .extra
+            .iter()
+            .find(|reading| reading.id() == id)
+            .map(|reading| Value::Number(reading.value, reading.unit));
+    }
+    if name.ends_with(BAR_SUFFIX) {
+        return match lookup(stats, &bar_base(name)?)? {
+            Value::Number(percent, Unit::Percent) => text(create_bar(percent as f32)),
+            _ => None,
+        };
+    }
+
+    let batteries = &stats.batteries;
+    let fullest = stats.disk.fullest_volume();
+    let hottest = stats.hottest_temperature();
+    let top_cpu = stats.top_cpu_processes.first();
+    let top_memory = stats.top_memory_processes.first();
+
+    match name {
+        "battery.pct" => number(stats.battery_percent, Unit::Percent),
+        "battery.state" => text(stats.battery_state.as_str()),
+        "battery.charging" => Some(Value::Bool(stats.battery_state == "Charging")),
+        "battery.discharging" => Some(Value::Bool(stats.battery_state == "Discharging")),
+        "battery.full" => Some(Value::Bool(stats.battery_state == "Full")),
+        "battery.present" => Some(Value::Bool(!batteries.is_empty())),
+        "battery.count" => number(batteries.len() as f64, Unit::Count),
+        "battery.power" => number(
+            batteries
+                .iter()
+                .map(|battery| battery.details.power_watts)
+                .sum::<f32>(),
+            Unit::Watts,
+        ),
+        "battery.health" if !batteries.is_empty() => number(
+            batteries
+                .iter()
+                .map(|battery| battery.details.health_percent)
+                .sum::<f32>()
+                / batteries.len() as f32,
+            Unit::Percent,
+        ),
+        "battery.cycles" => batteries
+            .iter()
+            .filter_map(|battery| battery.details.cycle_count)
+            .max()
+            .and_then(|cycles| number(cycles, Unit::Count)),
+        "battery.time_to_empty" => aggregate_time_to_empty(batteries)
+            .filter(|_| stats.battery_state == "Discharging")
+            .and_then(|secs| number(secs as f64, Unit::Seconds)),
+        "battery.time_to_full" => batteries
+            .iter()
+            .filter_map(|battery| battery.details.time_to_full_secs)
+            .max()
+            .filter(|_| stats.battery_state == "Charging")
+            .and_then(|secs| number(secs as f64, Unit::Seconds)),
+        "cpu.pct" => number(stats.cpu_usage, Unit::Percent),
+        "cpu.cores" => number(stats.cpu_cores.len() as f64, Unit::Count),
+        "cpu.max_core" => stats
+            .cpu_cores
+            .iter()
+            .copied()
+            .max_by(f32::total_cmp)
+            .and_then(|usage| number(usage, Unit::Percent)),
+        "load.one" => number(stats.load_average_one, Unit::Count),
+        "load.five" => number(stats.load_average_five, Unit::Count),
+        "load.fifteen" => number(stats.load_average_fifteen, Unit::Count),
+        "load.pct" => number(load_percent(stats), Unit::Percent),
+        "uptime" => number(stats.uptime_secs as f64, Unit::Seconds),
+        "boot_time" if stats.boot_time > 0 => text(format_local_timestamp(stats.boot_time)),
+        "mem.used" => number(stats.memory_used as f64, Unit::Bytes),
+        "mem.total" => number(stats.memory_total as f64, Unit::Bytes),
+        "mem.available" => number(stats.memory_available as f64, Unit::Bytes),
+        "mem.free" => number(stats.memory_free as f64, Unit::Bytes),
+        "mem.reclaimable" => number(stats.memory_reclaimable as f64, Unit::Bytes),
+        "mem.pct" => number(stats.memory_percent, Unit::Percent),
+        "mem.pressure" => text(match stats.memory_pressure {
+            MemoryPressure::Normal => "Normal",
+            MemoryPressure::Warning => "Warning",
+            MemoryPressure::Critical => "Critical",
+        }),
+        "swap.used" => number(stats.swap_used as f64, Unit::Bytes),
+        "swap.total" => number(stats.swap_total as f64, Unit::Bytes),
+        "swap.pct" => number(stats.swap_percent, Unit::Percent),
+        "disk.read" => number(stats.disk.read_per_sec as f64, Unit::BytesPerSecond),
+        "disk.written" => number(stats.disk.written_per_sec as f64, Unit::BytesPerSecond),
+        "disk.pct" => fullest.and_then(|volume| number(volume.percent, Unit::Percent)),
+        "disk.used" => fullest.and_then(|volume| number(volume.used as f64, Unit::Bytes)),
+        "disk.total" => fullest.and_then(|volume| number(volume.total as f64, Unit::Bytes)),
+        "disk.mount" => fullest.and_then(|volume| text(volume.mount_point.as_str())),
+        "disk.volumes" => number(stats.disk.volumes.len() as f64, Unit::Count),
+        "net.rx" => number(stats.network.received_per_sec as f64, Unit::BytesPerSecond),
+        "net.tx" => number(
+            stats.network.transmitted_per_sec as f64,
+            Unit::BytesPerSecond,
+        ),
+        "net.total_rx" => number(
+            stats
+                .network
+                .interfaces
+                .iter()
+                .map(|interface| interface.total_received as f64)
+                .sum::<f64>(),
+            Unit::Bytes,
+        ),
+        "net.total_tx" => number(
+            stats
+                .network
+                .interfaces
+                .iter()
+                .map(|interface| interface.total_transmitted as f64)
+                .sum::<f64>(),
+            Unit::Bytes,
+        ),
+        "net.interfaces" => number(stats.network.interfaces.len() as f64, Unit::Count),
+        "top_cpu.name" => top_cpu.and_then(|process| text(process.name.as_str())),
+        "top_cpu.pid" => top_cpu.and_then(|process| number(process.pid, Unit::Count)),
+        "top_cpu.pct" => top_cpu.and_then(|process| number(process.cpu_usage, Unit::Percent)),
+        "top_mem.name" => top_memory.and_then(|process| text(process.name.as_str())),
+        "top_mem.pid" => top_memory.and_then(|process| number(process.pid, Unit::Count)),
+        "top_mem.used" => top_memory.and_then(|process| number(process.memory as f64, Unit::Bytes)),
+        "temp.current" => hottest.and_then(|sensor| number(sensor.current, Unit::Celsius)),
+        "temp.max" => hottest.and_then(|sensor| number(sensor.max, Unit::Celsius)),
+        "temp.critical" => hottest
+            .and_then(|sensor| sensor.critical)
+            .and_then(|critical| number(critical, Unit::Celsius)),
+        "temp.label" => hottest.and_then(|sensor| text(sensor.label.as_str())),
+        _ => None,
+    }
+}
+
+/// Whether placeholder `name` produces a number, e.g. `cpu.pct` or `reading.gpu.usage`.
+pub(crate) fn is_numeric_metric(name: &str) -> bool {
+    matches!(
+        placeholder_kind(name),
+        Some(Kind::Number(_) | Kind::Reading)
+    )
+}
+
+/// The numeric value of placeholder `name`, e.g. `cpu.pct`, with its unit.
+pub(crate) fn metric_value(stats: &SystemStats, name: &str) -> Option<(f64, Unit)> {
+    match lookup(stats, name)? {
+        Value::Number(value, unit) if value.is_finite() => Some((value, unit)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DisplayUnit {
+    /// Bytes divided by `1024^exponent`, shown with `label`.
+    Bytes {
+        exponent: i32,
+        label: &'static str,
+    },
+    Celsius,
+    Fahrenheit,
+    /// Seconds divided by `divisor`.
+    Duration {
+        divisor: u64,
+    },
+}
+
+impl DisplayUnit {
+    pub(crate) fn parse(unit: &str) -> Option<Self> {
+        let bytes = |exponent, label| Some(DisplayUnit::Bytes { exponent, label });
+        match unit {
+            "B" => bytes(0, "B"),
+            "KB" => bytes(1, "KB"),
+            "KiB" => bytes(1, "KiB"),
+            "MB" => bytes(2, "MB"),
+            "MiB" => bytes(2, "MiB"),
+            "GB" => bytes(3, "GB"),
+            "GiB" => bytes(3, "GiB"),
+            "TB" => bytes(4, "TB"),
+            "TiB" => bytes(4, "TiB"),
+            "C" => Some(DisplayUnit::Celsius),
+            "F" => Some(DisplayUnit::Fahrenheit),
+            "s" => Some(DisplayUnit::Duration { divisor: 1 }),
+            "m" => Some(DisplayUnit::Duration { divisor: 60 }),
+            "h" => Some(DisplayUnit::Duration { divisor: 3_600 }),
+            "d" => Some(DisplayUnit::Duration { divisor: 86_400 }),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn applies_to(self, unit: Unit) -> bool {
+        match self {
+            DisplayUnit::Bytes { .. } => matches!(unit, Unit::Bytes | Unit::BytesPerSecond),
+            DisplayUnit::Celsius | DisplayUnit::Fahrenheit => unit == Unit::Celsius,
+            DisplayUnit::Duration { .. } => unit == Unit::Seconds,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Spec {
+    align: Option<Align>,
+    width: usize,
+    precision: Option<usize>,
+    unit: Option<DisplayUnit>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let mut parsed = Spec::default();
+        let mut rest = spec;
+
+        let align = match rest.chars().next() {
+            Some('<') => Some(Align::Left),
+            Some('>') => Some(Align::Right),
+            Some('^') => Some(Align::Center),
+            _ => None,
+        };
+        if let Some(align) = align {
+            rest = &rest[1..];
+            let digits = leading_digits(rest);
+            if digits.is_empty() {
+                return Err(format!("missing width after alignment in {:?}", spec));
+            }
+            parsed.align = Some(align);
+            parsed.width = parse_number(digits, spec)?;
+            rest = &rest[digits.len()..];
+        }
+
+        let had_dot = rest.starts_with('.');
+        if had_dot {
+            rest = &rest[1..];
+        }
+        let digits = leading_digits(rest);
+        if !digits.is_empty() {
+            parsed.precision = Some(parse_number(digits, spec)?);
+            rest = &rest[digits.len()..];
+        } else if had_dot {
+            return Err(format!("missing precision after '.' in {:?}", spec));
+        }
+
+        if !rest.is_empty() {
+            parsed.unit =
+                Some(DisplayUnit::parse(rest).ok_or_else(|| format!("unknown unit {:?}", rest))?);
+        }
+        Ok(parsed)
+    }
+
+    fn pad(&self, value: String) -> String {
+        let width = self.width;
+        match self.align {
+            None => value,
+            Some(Align::Left) => format!("{:<width$}", value),
+            Some(Align::Right) => format!("{:>width$}", value),
+            Some(Align::Center) => format!("{:^width$}", value),
+        }
+    }
+}
+
+fn leading_digits(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+fn parse_number(digits: &str, spec: &str) -> Result<usize, String> {
+    digits
+        .parse::<usize>()
+        .ok()
+        .filter(|&number| number <= 64)
+        .ok_or_else(|| format!("number too large in {:?}", spec))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operator {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+/// Longer symbols first, so `>=` is not read as `>`.
+pub(crate) const OPERATORS: [(&str, Operator); 6] = [
+    (">=", Operator::GreaterOrEqual),
+    ("<=", Operator::LessOrEqual),
+    ("==", Operator::Equal),
+    ("!=", Operator::NotEqual),
+    (">", Operator::Greater),
+    ("<", Operator::Less),
+];
+
+impl Operator {
+    pub(crate) fn compare(self, value: f64, operand: f64) -> bool {
+        match self {
+            Operator::Less => value < operand,
+            Operator::LessOrEqual => value <= operand,
+            Operator::Greater => value > operand,
+            Operator::GreaterOrEqual => value >= operand,
+            Operator::Equal => value == operand,
+            Operator::NotEqual => value != operand,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Truthy {
+        name: String,
+        negate: bool,
+    },
+    Compare {
+        name: String,
+        operator: Operator,
+        operand: Operand,
+    },
+}
+
+impl Condition {
+    fn parse(condition: &str) -> Result<Self, String> {
+        let operator = OPERATORS
+            .iter()
+            .filter_map(|(symbol, operator)| {
+                condition
+                    .find(symbol)
+                    .map(|index| (index, *symbol, *operator))
+            })
+            .min_by_key(|(index, symbol, _)| (*index, std::cmp::Reverse(symbol.len())));
+
+        let Some((index, symbol, operator)) = operator else {
+            let condition = condition.trim();
+            let (name, negate) = match condition.strip_prefix('!') {
+                Some(name) => (name.trim(), true),
+                None => (condition, false),
+            };
+            checked_kind(name)?;
+            return Ok(Condition::Truthy {
+                name: name.to_string(),
+                negate,
+            });
+        };
+
+        let name = condition[..index].trim();
+        let operand = condition[index + symbol.len()..].trim();
+        let kind = checked_kind(name)?;
+        if operand.is_empty() {
+            return Err(format!(
+                "missing value after {:?} in {:?}",
+                symbol, condition
+            ));
+        }
+
+        let operand = match operand.parse::<f64>() {
+            Ok(number) if kind != Kind::Text => Operand::Number(number),
+            _ if kind == Kind::Text && matches!(operator, Operator::Equal | Operator::NotEqual) => {
+                Operand::Text(operand.to_string())
+            }
+            _ => {
+                return Err(format!(
+                    "{} cannot be compared with {} {}",
+                    name, symbol, operand
+                ))
+            }
+        };
+        Ok(Condition::Compare {
+            name: name.to_string(),
+            operator,
+            operand,
+        })
+    }
+
+    fn holds(&self, stats: &SystemStats) -> bool {
+        match self {
+            Condition::Truthy { name, negate } => {
+                let truthy = match lookup(stats, name) {
+                    Some(Value::Bool(value)) => value,
+                    Some(Value::Number(value, _)) => value != 0.0,
+                    Some(Value::Text(value)) => !value.is_empty(),
+                    None => false,
+                };
+                truthy != *negate
+            }
+            Condition::Compare {
+                name,
+                operator,
+                operand,
+            } => match (lookup(stats, name), operand) {
+                (Some(Value::Number(value, _)), Operand::Number(operand)) => {
+                    operator.compare(value, *operand)
+                }
+                (Some(Value::Text(value)), Operand::Text(operand)) => {
+                    value.eq_ignore_ascii_case(operand) == (*operator == Operator::Equal)
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+fn checked_kind(name: &str) -> Result<Kind, String> {
+    if name.is_empty() {
+        return Err("empty placeholder".to_string());
+    }
+    placeholder_kind(name).ok_or_else(|| format!("unknown placeholder {:?}", name))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Value {
+        name: String,
+        spec: Spec,
+    },
+    Conditional {
+        condition: Condition,
+        body: Vec<Node>,
+    },
+}
+
+/// A problem found while compiling a template, with the 1-based character column of the
+/// offending tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// A compiled tray title template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrayTemplate {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+impl TrayTemplate {
+    /// Parses `source`, reporting every unknown placeholder, invalid spec and unbalanced
+    /// conditional at once.
+    pub fn compile(source: &str) -> Result<Self, Vec<TemplateError>> {
+        let mut errors = Vec::new();
+        // Open conditionals, each with the nodes that precede it and its opening column.
+        let mut stack: Vec<(Condition, Vec<Node>, usize)> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.char_indices().peekable();
+        let column = |offset: usize| source[..offset].chars().count() + 1;
+
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|&(_, next)| next) == Some('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek().map(|&(_, next)| next) == Some('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => errors.push(TemplateError {
+                    column: column(offset),
+                    message: "unmatched '}', write '}}' for a literal brace".to_string(),
+                }),
+                '{' => {
+                    let start = offset + 1;
+                    let Some(end) = source[start..].find('}').map(|end| start + end) else {
+                        errors.push(TemplateError {
+                            column: column(offset),
+                            message: "unclosed '{'".to_string(),
+                        });
+                        break;
+                    };
+                    while chars.peek().is_some_and(|&(index, _)| index <= end) {
+                        chars.next();
+                    }
+
+                    if !literal.is_empty() {
+                        nodes.push(Node::Text(std::mem::take(&mut literal)));
+                    }
+
+                    let tag = &source[start..end];
+                    let error = |message| TemplateError {
+                        column: column(offset),
+                        message,
+                    };
+                    if let Some(condition) = tag.strip_prefix('?') {
+                        match Condition::parse(condition) {
+                            Ok(condition) => {
+                                stack.push((condition, std::mem::take(&mut nodes), column(offset)))
+                            }
+                            Err(message) => {
+                                errors.push(error(message));
+                                // Keep nesting balanced so the matching `{/}` is not reported.
+                                stack.push((
+                                    Condition::Truthy {
+                                        name: String::new(),
+                                        negate: false,
+                                    },
+                                    std::mem::take(&mut nodes),
+                                    column(offset),
+                                ));
+                            }
+                        }
+                    } else if tag.trim() == "/" {
+                        match stack.pop() {
+                            Some((condition, outer, _)) => {
+                                let body = std::mem::replace(&mut nodes, outer);
+                                nodes.push(Node::Conditional { condition, body });
+                            }
+                            None => errors.push(error("'{/}' without a matching '{?...}'".into())),
+                        }
+                    } else {
+                        let (name, spec) = match tag.split_once(':') {
+                            Some((name, spec)) => (name.trim(), spec.trim()),
+                            None => (tag.trim(), ""),
+                        };
+                        match compile_value(name, spec) {
+                            Ok(node) => nodes.push(node),
+                            Err(message) => errors.push(error(message)),
+                        }
+                    }
+                }
+                c => literal.push(c),
+            }
+        }
+
+        for (_, _, column) in &stack {
+            errors.push(TemplateError {
+                column: *column,
+                message: "'{?...}' is never closed with '{/}'".to_string(),
+            });
+        }
+        if !literal.is_empty() {
+            nodes.push(Node::Text(literal));
+        }
+
+        if errors.is_empty() {
+            Ok(Self {
+                source: source.to_string(),
+                nodes,
+            })
+        } else {
+            errors.sort_by_key(|error| error.column);
+            Err(errors)
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Renders the template. `options` supplies the default precision and temperature unit for
+    /// placeholders without a spec.
+    pub fn render(&self, stats: &SystemStats, options: &TrayOptions) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, stats, options, &mut output);
+        output
+    }
+}
+
+fn compile_value(name: &str, spec: &str) -> Result<Node, String> {
+    let kind = checked_kind(name)?;
+    let spec = Spec::parse(spec)?;
+
+    match (kind, spec.unit) {
+        (Kind::Number(unit), Some(display)) if !display.applies_to(unit) => {
+            return Err(format!(
+                "unit {:?} does not apply to {}",
+                spec_unit_name(display),
+                name
+            ))
+        }
+        (Kind::Text | Kind::Bool, Some(display)) => {
+            return Err(format!(
+                "unit {:?} does not apply to {}",
+                spec_unit_name(display),
+                name
+            ))
+        }
+        (Kind::Text | Kind::Bool, None) if spec.precision.is_some() => {
+            return Err(format!("precision does not apply to {}", name))
+        }
+        _ => {}
+    }
+
+    Ok(Node::Value {
+        name: name.to_string(),
+        spec,
+    })
+}
+
+fn spec_unit_name(unit: DisplayUnit) -> &'static str {
+    match unit {
+        DisplayUnit::Bytes { label, .. } => label,
+        DisplayUnit::Celsius => "C",
+        DisplayUnit::Fahrenheit => "F",
+        DisplayUnit::Duration { divisor: 1 } => "s",
+        DisplayUnit::Duration { divisor: 60 } => "m",
+        DisplayUnit::Duration { divisor: 3_600 } => "h",
+        DisplayUnit::Duration { .. } => "d",
+    }
+}
+
+fn render_nodes(nodes: &[Node], stats: &SystemStats, options: &TrayOptions, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Value { name, spec } => {
+                let value = lookup(stats, name)
+                    .map(|value| format_value(value, spec, options))
+                    .unwrap_or_default();
+                output.push_str(&spec.pad(value));
+            }
+            Node::Conditional { condition, body } => {
+                if condition.holds(stats) {
+                    render_nodes(body, stats, options, output);
+                }
+            }
+        }
+    }
+}
+
+fn format_value(value: Value, spec: &Spec, options: &TrayOptions) -> String {
+    let (value, unit) = match value {
+        Value::Text(text) => return text,
+        Value::Bool(value) => return if value { "yes" } else { "no" }.to_string(),
+        Value::Number(value, unit) if value.is_finite() => (value, unit),
+        Value::Number(_, unit) => (0.0, unit),
+    };
+    let precision = spec.precision;
+
+    match (unit, spec.unit) {
+        (_, Some(DisplayUnit::Bytes { exponent, label })) if unit_is_bytes(unit) => {
+            let scaled = value / 1024f64.powi(exponent);
+            let suffix = if unit == Unit::BytesPerSecond {
+                "/s"
+            } else {
+                ""
+            };
+            format!("{:.*} {}{}", precision.unwrap_or(1), scaled, label, suffix)
+        }
+        (Unit::Celsius, Some(DisplayUnit::Fahrenheit)) => {
+            format!("{:.*}", precision.unwrap_or(0), value * 9.0 / 5.0 + 32.0)
+        }
+        (Unit::Celsius, Some(DisplayUnit::Celsius)) => {
+            format!("{:.*}", precision.unwrap_or(0), value)
+        }
+        (Unit::Seconds, Some(DisplayUnit::Duration { divisor })) => {
+            format!("{:.*}", precision.unwrap_or(0), value / divisor as f64)
+        }
+        (Unit::Percent, _) => {
+            format_tray_number(value as f32, precision.unwrap_or(options.precision))
+        }
+        (Unit::Celsius, _) => format_tray_number(
+            options.temperature_unit.convert(value as f32),
+            precision.unwrap_or(options.precision),
+        ),
+        (Unit::Bytes | Unit::BytesPerSecond, _) => {
+            let bytes = value.max(0.0).round() as u64;
+            match (precision, unit) {
+                (None, Unit::Bytes) => format_bytes(bytes),
+                (None, _) => format_rate(bytes),
+                (Some(precision), _) => {
+                    let (scaled, label) = scale_bytes(bytes, &SIZE_UNITS);
+                    let suffix = if unit == Unit::BytesPerSecond {
+                        "/s"
+                    } else {
+                        ""
+                    };
+                    format!("{:.*} {}{}", precision, scaled, label, suffix)
+                }
+            }
+        }
+        (Unit::Seconds, _) => format_duration_short(value.max(0.0).round() as u64),
+        (Unit::Watts, _) => format!("{:.*}", precision.unwrap_or(1), value),
+        (Unit::Count, _) => match precision {
+            Some(precision) => format!("{:.*}", precision, value),
+            None if value.fract() == 0.0 => format!("{:.0}", value),
+            None => format!("{:.2}", value),
+        },
+    }
+}
+
+fn unit_is_bytes(unit: Unit) -> bool {
+    matches!(unit, Unit::Bytes | Unit::BytesPerSecond)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::TemperatureUnit;
+    use crate::stats::{
+        BatteryDetails, BatteryStats, DiskStats, DiskVolumeStats, NetworkInterfaceStats,
+        NetworkStats, ProcessStats, Reading, TemperatureStats,
+    };
+
+    fn sample_stats() -> SystemStats {
+        SystemStats {
+            cpu_usage: 42.26,
+            cpu_cores: vec![30.0, 90.0],
+            load_average_one: 1.5,
+            load_average_five: 1.0,
+            load_average_fifteen: 0.5,
+            uptime_secs: 3_660,
+            boot_time: 86_400,
+            memory_used: 6 * 1024 * 1024 * 1024,
+            memory_total: 16 * 1024 * 1024 * 1024,
+            memory_percent: 37.5,
+            memory_available: 10 * 1024 * 1024 * 1024,
+            memory_free: 4 * 1024 * 1024 * 1024,
+            memory_reclaimable: 6 * 1024 * 1024 * 1024,
+            memory_pressure: MemoryPressure::Warning,
+            swap_used: 512 * 1024 * 1024,
+            swap_total: 2 * 1024 * 1024 * 1024,
+            swap_percent: 25.0,
+            battery_percent: 81.0,
+            battery_state: "Discharging".to_string(),
+            batteries: vec![BatteryStats {
+                name: "Battery 1".to_string(),
+                percent: 81.0,
+                state: "Discharging".to_string(),
+                details: BatteryDetails {
+                    health_percent: 93.0,
+                    power_watts: 7.25,
+                    cycle_count: Some(321),
+                    time_to_empty_secs: Some(7_200),
+                    ..Default::default()
+                },
+            }],
+            disk: DiskStats {
+                read_per_sec: 2 * 1024 * 1024,
+                written_per_sec: 512,
+                volumes: vec![DiskVolumeStats {
+                    mount_point: "/".to_string(),
+                    file_system: "apfs".to_string(),
+                    used: 400 * 1024 * 1024 * 1024,
+                    total: 500 * 1024 * 1024 * 1024,
+                    percent: 80.0,
+                }],
+            },
+            network: NetworkStats {
+                received_per_sec: 1536,
+                transmitted_per_sec: 0,
+                interfaces: vec![NetworkInterfaceStats {
+                    name: "en0".to_string(),
+                    received_per_sec: 1536,
+                    transmitted_per_sec: 0,
+                    total_received: 1024,
+                    total_transmitted: 2048,
+                }],
+            },
+            top_cpu_processes: vec![ProcessStats {
+                pid: 42,
+                name: "rustc".to_string(),
+                cpu_usage: 180.0,
+                memory: 1024 * 1024 * 1024,
+            }],
+            top_memory_processes: vec![ProcessStats {
+                pid: 7,
+                name: "Safari".to_string(),
+                cpu_usage: 3.0,
+                memory: 3 * 1024 * 1024 * 1024,
+            }],
+            temperatures: vec![TemperatureStats {
+                label: "CPU die".to_string(),
+                current: 71.6,
+                max: 80.0,
+                critical: Some(100.0),
+            }],
+            extra: vec![Reading::new("gpu.usage", 12.5, Unit::Percent)],
+        }
+    }
+
+    fn render(source: &str, stats: &SystemStats) -> String {
+        TrayTemplate::compile(source)
+            .unwrap_or_else(|errors| panic!("{}: {:?}", source, errors))
+            .render(stats, &TrayOptions::default())
+    }
+
+    #[test]
+    fn test_template_placeholders() {
+        let stats = sample_stats();
+        for name in placeholder_names().filter(|name| *name != "battery.time_to_full") {
+            assert!(lookup(&stats, name).is_some(), "{} has no value", name);
+        }
+
+        assert_eq!(
+            render(
+                "{battery.bar}{battery.pct:0}% {cpu.pct:.1}% {mem.used:GiB}",
+                &stats
+            ),
+            "▇81% 42.3% 6.0 GiB"
+        );
+        assert_eq!(
+            render(
+                "{battery.state} {battery.power}W {battery.time_to_empty}",
+                &stats
+            ),
+            "Discharging 7.2W 2h 0m"
+        );
+        assert_eq!(
+            render("{load.one} {load.five:2} {uptime} {uptime:m}", &stats),
+            "1.50 1.00 1h 1m 61"
+        );
+        assert_eq!(
+            render("{boot_time}", &stats),
+            format_local_timestamp(86_400)
+        );
+        assert_eq!(
+            render("{mem.total} {swap.pct}% {mem.pressure}", &stats),
+            "16.0 GB 25% Warning"
+        );
+        assert_eq!(
+            render(
+                "{disk.read} {disk.written} {disk.pct}% of {disk.mount}",
+                &stats
+            ),
+            "2.0 MB/s 512 B/s 80% of /"
+        );
+        assert_eq!(
+            render("↓ {net.rx} ↑ {net.tx:KiB} {net.rx:.2}", &stats),
+            "↓ 2 KB/s ↑ 0.0 KiB/s 1.50 KB/s"
+        );
+        assert_eq!(
+            render("{top_cpu.name} ({top_cpu.pid}) {top_mem.used:GB}", &stats),
+            "rustc (42) 3.0 GB"
+        );
+        assert_eq!(
+            render("{temp.current}° {temp.current:F} {temp.critical:C}", &stats),
+            "72° 161 100"
+        );
+        assert_eq!(render("{reading.gpu.usage}%", &stats), "13%");
+        assert_eq!(render("{reading.gpu.missing}", &stats), "");
+        assert_eq!(render("{{literal}} {cpu.pct}", &stats), "{literal} 42");
+    }
+
+    #[test]
+    fn test_template_options_and_padding() {
+        let stats = sample_stats();
+        let template =
+            TrayTemplate::compile("[{cpu.pct:>6}] [{temp.current:<4}] [{mem.pct:^8.1}]").unwrap();
+        assert_eq!(
+            template.render(&stats, &TrayOptions::default()),
+            "[    42] [72  ] [  37.5  ]"
+        );
+
+        let options = TrayOptions {
+            precision: 1,
+            temperature_unit: TemperatureUnit::Fahrenheit,
+            ..Default::default()
+        };
+        assert_eq!(
+            template.render(&stats, &options),
+            "[  42.3] [160.9] [  37.5  ]"
+        );
+    }
+
+    #[test]
+    fn test_template_conditionals() {
+        let stats = sample_stats();
+        let source = "{?battery.discharging}🔋 {battery.pct}% {/}{?!battery.charging}unplugged{/}";
+        assert_eq!(render(source, &stats), "🔋 81% unplugged");
+
+        let charging = SystemStats {
+            battery_state: "Charging".to_string(),
+            ..stats.clone()
+        };
+        assert_eq!(render(source, &charging), "");
+
+        assert_eq!(
+            render("{?cpu.pct > 40}hot{/}{?cpu.pct<=40}cool{/}", &stats),
+            "hot"
+        );
+        assert_eq!(
+            render("{?mem.pressure != normal}⚠️ {?mem.pct>=90}!{/}{/}", &stats),
+            "⚠️ "
+        );
+        assert_eq!(render("{?temp.current}🌡{/}", &stats), "🌡");
+        assert_eq!(render("{?temp.current}🌡{/}", &SystemStats::default()), "");
+        assert_eq!(render("{?reading.gpu.usage > 10}gpu{/}", &stats), "gpu");
+    }
+
+    #[test]
+    fn test_template_compile_errors() {
+        let errors =
+            TrayTemplate::compile("{cpu.pct} {cpu.temp} {mem.used:parsecs} {battery.state:.1}")
+                .unwrap_err();
+        assert_eq!(
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "column 11: unknown placeholder \"cpu.temp\"",
+                "column 22: unknown unit \"parsecs\"",
+                "column 41: precision does not apply to battery.state",
+            ]
+        );
+
+        for (source, message) in [
+            ("{cpu.pct:GiB}", "unit \"GiB\" does not apply to cpu.pct"),
+            ("{mem.used.bar}", "unknown placeholder \"mem.used.bar\""),
+            ("{cpu.pct", "unclosed '{'"),
+            ("cpu}", "unmatched '}', write '}}' for a literal brace"),
+            ("{/}", "'{/}' without a matching '{?...}'"),
+            (
+                "{?battery.charging}x",
+                "'{?...}' is never closed with '{/}'",
+            ),
+            (
+                "{?cpu.pct > hot}{/}",
+                "cpu.pct cannot be compared with > hot",
+            ),
+            (
+                "{?battery.state > 3}{/}",
+                "battery.state cannot be compared with > 3",
+            ),
+            ("{?gpu}{/}", "unknown placeholder \"gpu\""),
+            ("{cpu.pct:>}", "missing width after alignment in \">\""),
+            ("{}", "empty placeholder"),
+        ] {
+            let errors = TrayTemplate::compile(source).unwrap_err();
+            assert_eq!(errors.len(), 1, "{}: {:?}", source, errors);
+            assert_eq!(errors[0].message, message, "{}", source);
+        }
+    }
+}